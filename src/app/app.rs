@@ -1,19 +1,36 @@
 use std::process::exit;
 use std::env::current_dir;
+use std::path::{Path, PathBuf};
+use teo_parser::ast::schema::Schema;
+use teo_parser::diagnostics::diagnostics::Diagnostics;
+use std::time::Instant;
+use maplit::hashmap;
 use teo_result::{Error, Result};
 use teo_runtime::namespace::Namespace;
 use crate::app::ctx::Ctx;
 use teo_runtime::utils::find_main_schema_file;
 use crate::cli::parse::{parse as cli_parse};
-use teo_parser::{parse as schema_parse};
-use teo_parser::diagnostics::printer::print_diagnostics;
+use crate::cli::command::{CLI, CLICommand, OutputFormat, ServeCommand};
+use crate::cli::exit_code;
 use teo_runtime::stdlib::load::{load as load_std};
 use teo_runtime::schema::load::load_schema::load_schema;
-use crate::cli::run::run;
+use crate::cli::run::{done_event, exit_on_json_error, run};
 use dotenvy::dotenv;
 use teo_runtime::connection::transaction;
 use crate::app::callbacks::callback::AsyncCallbackArgument;
+use crate::app::program::{AsyncProgramCallback, ProgramSignature};
 use crate::prelude::{Entrance, RuntimeVersion};
+use crate::server::static_files::StaticFiles;
+use crate::server::options::ServerOptions;
+use crate::server::panic::PanicReport;
+use crate::idempotency::Idempotency;
+use crate::webhook::Webhook;
+use crate::schedule::Schedule;
+use crate::audit::Audit;
+use crate::server::admin::AdminDashboard;
+use crate::{profile, stdlib};
+use crate::message::{diagnostics_message, event, set_json_output};
+use serde_json::json;
 
 #[derive(Debug)]
 pub struct App { }
@@ -38,19 +55,98 @@ impl App {
         }
         Ctx::set_argv(argv);
         let cli = cli_parse(Ctx::get().runtime_version.clone(), Ctx::get().entrance, Ctx::argv());
+        set_json_output(cli.output == OutputFormat::Json);
+        if !cli.command.requires_schema() {
+            Ctx::set_cli(cli);
+            return Ok(Self { });
+        }
+        let start = Instant::now();
+        let (main_schema_file, schema, diagnostics) = match Self::parse_cli_schema(&cli) {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                done_event(cli.command.name(), start, Some(&error));
+                exit_on_json_error(Some(&error));
+                return Err(error);
+            }
+        };
+        diagnostics_message(&diagnostics);
+        if diagnostics.has_errors() {
+            // doctor reports the errors along with its other checks
+            if let CLICommand::Doctor(_) = cli.command {
+                Ctx::set_schema_has_errors(true);
+            } else {
+                event("done", json!({ "command": cli.command.name(), "result": "failure", "message": "schema has errors", "exit": exit_code::SCHEMA }));
+                exit(exit_code::SCHEMA);
+            }
+        }
+        load_std(Ctx::main_namespace_mut());
+        stdlib::define_pipeline_items(Ctx::main_namespace_mut());
+        Ctx::set_schema(schema);
+        Ctx::set_main_schema(main_schema_file.to_str().unwrap().to_owned(), None);
+        Ctx::set_cli(cli);
+        Ok(Self { })
+    }
+
+    fn parse_cli_schema(cli: &CLI) -> Result<(PathBuf, Schema, Diagnostics)> {
         let current_dir = match current_dir() {
             Ok(current_dir) => current_dir,
             Err(e) => Err(Error::new(format!("{}", e)))?,
         };
         let main_schema_file = find_main_schema_file(cli.schema.as_ref().map(AsRef::as_ref), &current_dir)?;
-        let (schema, diagnostics) = schema_parse(main_schema_file.as_path().to_str().unwrap(), None, None);
-        print_diagnostics(&diagnostics, true);
+        let (schema, diagnostics) = stdlib::parse_schema(main_schema_file.as_path().to_str().unwrap(), None)?;
+        Ok((main_schema_file, schema, diagnostics))
+    }
+
+    /// Create an app from a schema file without reading command line
+    /// arguments, e.g. for a [`TestServer`](crate::test::TestServer).
+    pub fn new_with_schema_path(path: impl AsRef<Path>) -> Result<Self> {
+        let Some(path) = path.as_ref().to_str() else {
+            Err(Error::new("schema path is not valid unicode"))?
+        };
+        Self::new_without_cli(path.to_owned(), None)
+    }
+
+    /// Create an app from schema source code without reading command line
+    /// arguments. Imports are resolved relative to the current directory.
+    pub fn new_with_schema_source(source: impl Into<String>) -> Result<Self> {
+        let current_dir = match current_dir() {
+            Ok(current_dir) => current_dir,
+            Err(e) => Err(Error::new(format!("{}", e)))?,
+        };
+        let path = current_dir.join("schema.teo").to_str().unwrap().to_owned();
+        Self::new_without_cli(path, Some(source.into()))
+    }
+
+    fn new_without_cli(main: String, source: Option<String>) -> Result<Self> {
+        let _ = dotenv();
+        if !Ctx::create() {
+            Err(Error::new("cannot create app while there is an existing instance"))?
+        }
+        let unsaved_files = source.clone().map(|source| hashmap!{ main.clone() => source });
+        let (schema, diagnostics) = stdlib::parse_schema(main.as_str(), unsaved_files)?;
+        diagnostics_message(&diagnostics);
         if diagnostics.has_errors() {
-            exit(1);
+            Ctx::drop()?;
+            Err(Error::new("schema has errors"))?
         }
         load_std(Ctx::main_namespace_mut());
+        stdlib::define_pipeline_items(Ctx::main_namespace_mut());
         Ctx::set_schema(schema);
-        Ctx::set_cli(cli);
+        Ctx::set_main_schema(main, source);
+        Ctx::set_cli(CLI {
+            command: CLICommand::Serve(ServeCommand {
+                no_migration: false,
+                no_autoseed: false,
+                options: ServerOptions::default(),
+                watch: false,
+                admin: None,
+                strict_migrations: false,
+            }),
+            schema: Some(Ctx::main_schema().0.clone()),
+            env: None,
+            output: OutputFormat::Text,
+            silent: true,
+        });
         Ok(Self { })
     }
 
@@ -68,6 +164,59 @@ impl App {
         });
     }
 
+    /// Define a program taking typed arguments, e.g. `cargo teo run import
+    /// users.csv --dry`. The arguments are parsed and validated before `f` is
+    /// called.
+    pub fn program_with_args<T, F>(&self, name: &str, desc: Option<T>, signature: ProgramSignature, f: F) where T: Into<String>, F: AsyncProgramCallback + 'static {
+        Ctx::insert_program_with_signature(name, desc.map(|desc| desc.into()), signature, f);
+    }
+
+    /// Serve a directory of static files under `prefix`, without writing a
+    /// custom handler.
+    pub fn static_files(&self, prefix: &str, static_files: StaticFiles) {
+        Ctx::insert_static_files(prefix, static_files);
+    }
+
+    /// Tune the worker pool and connections. Flags passed to `serve` take
+    /// precedence.
+    pub fn server_options(&self, server_options: ServerOptions) {
+        Ctx::set_server_options(server_options);
+    }
+
+    /// Called with every panic caught while handling a request, e.g. to
+    /// forward it to a crash reporter. The client receives a 500 response.
+    pub fn on_panic<F>(&self, f: F) where F: Fn(&PanicReport) + Send + Sync + 'static {
+        Ctx::set_panic_hook(f);
+    }
+
+    /// Replay stored responses for requests repeating an `Idempotency-Key`.
+    pub fn idempotency(&self, idempotency: Idempotency) {
+        Ctx::set_idempotency(idempotency);
+    }
+
+    /// Send signed webhooks when models calling `$std.webhookSave` or
+    /// `$std.webhookDelete` are written.
+    pub fn webhook(&self, webhook: Webhook) {
+        Ctx::insert_webhook(webhook);
+    }
+
+    /// Run a program on a cron schedule while `serve` is running. Runs are
+    /// recorded into `std.ProgramRun`.
+    pub fn schedule(&self, schedule: Schedule) {
+        Ctx::insert_schedule(schedule);
+    }
+
+    /// Record creates, updates and deletes of models calling the
+    /// `$std.audit*` callbacks into `std.AuditLog`.
+    pub fn audit(&self, audit: Audit) {
+        Ctx::set_audit(audit);
+    }
+
+    /// Mount the generated admin dashboard on the server.
+    pub fn admin_dashboard(&self, admin_dashboard: AdminDashboard) {
+        Ctx::set_admin_dashboard(admin_dashboard);
+    }
+
     pub fn main_namespace(&self) -> &'static Namespace {
         Ctx::main_namespace()
     }
@@ -77,12 +226,26 @@ impl App {
     }
 
     pub async fn run(&self) -> Result<()> {
-        self.prepare_for_run().await?;
+        let start = Instant::now();
+        if let Err(error) = self.prepare_for_run().await {
+            done_event(Ctx::cli().command.name(), start, Some(&error));
+            exit_on_json_error(Some(&error));
+            return Err(error);
+        }
         self.run_without_prepare().await
     }
 
     pub async fn prepare_for_run(&self) -> Result<()> {
-        load_schema(Ctx::main_namespace_mut(), Ctx::schema(), Ctx::cli().command.ignores_loading()).await
+        if Ctx::schema_has_errors() || !Ctx::cli().command.requires_schema() {
+            return Ok(());
+        }
+        profile::apply_webhooks(Ctx::cli().env())?;
+        if let Some(schema) = stdlib::declare_missing_models(Ctx::schema()).await? {
+            Ctx::set_schema(schema);
+        }
+        load_schema(Ctx::main_namespace_mut(), Ctx::schema(), Ctx::cli().command.ignores_loading()).await?;
+        profile::apply(Ctx::main_namespace_mut(), Ctx::cli().env())?;
+        profile::apply_server(Ctx::main_namespace(), Ctx::cli().env())
     }
 
     pub async fn run_without_prepare(&self) -> Result<()> {
@@ -1,5 +1,6 @@
 use educe::Educe;
 use std::collections::BTreeMap;
+use std::mem;
 use std::ops::{Deref, DerefMut};
 use std::sync::{Arc, Mutex};
 use maplit::btreemap;
@@ -7,19 +8,30 @@ use once_cell::sync::OnceCell;
 use teo_parser::ast::schema::Schema;
 use teo_result::Result;
 use teo_runtime::connection;
+use teo_runtime::connection::transaction;
 use teo_runtime::namespace::Namespace;
 use crate::app::callbacks::callback::AsyncCallback;
+use crate::app::program::{AsyncProgramCallback, ProgramArgs, ProgramSignature};
 use crate::cli::command::CLI;
 use crate::cli::entrance::Entrance;
 use crate::cli::runtime_version::RuntimeVersion;
+use crate::server::static_files::StaticFiles;
+use crate::server::options::ServerOptions;
+use crate::server::panic::PanicHook;
+use crate::idempotency::Idempotency;
+use crate::webhook::Webhook;
+use crate::schedule::Schedule;
+use crate::audit::Audit;
+use crate::server::admin::AdminDashboard;
 
 
 #[derive(Educe)]
 #[educe(Debug)]
 pub struct Program {
     #[educe(Debug(ignore))]
-    pub(crate) func: Arc<dyn AsyncCallback>,
+    pub(crate) func: Arc<dyn AsyncProgramCallback>,
     pub(crate) desc: Option<String>,
+    pub(crate) signature: ProgramSignature,
 }
 
 
@@ -34,12 +46,24 @@ pub struct Ctx {
     pub(crate) cli: Option<CLI>,
     #[educe(Debug(ignore))]
     pub(crate) schema: Option<Schema>,
+    /// The main schema file, and its source when it isn't read from disk.
+    pub(crate) main_schema: Option<(String, Option<String>)>,
+    pub(crate) schema_has_errors: bool,
     #[educe(Debug(ignore))]
     pub(crate) setup: Option<Arc<dyn AsyncCallback>>,
     #[educe(Debug(ignore))]
     pub(crate) programs: BTreeMap<String, Program>,
     #[educe(Debug(ignore))]
     pub(crate) conn_ctx: Option<connection::Ctx>,
+    pub(crate) static_files: Vec<(String, StaticFiles)>,
+    pub(crate) server_options: ServerOptions,
+    #[educe(Debug(ignore))]
+    pub(crate) panic_hook: Option<Arc<dyn PanicHook>>,
+    pub(crate) idempotency: Option<Idempotency>,
+    pub(crate) webhooks: Vec<Webhook>,
+    pub(crate) schedules: Vec<Schedule>,
+    pub(crate) audit: Option<Audit>,
+    pub(crate) admin_dashboard: Option<AdminDashboard>,
 }
 
 impl Ctx {
@@ -53,22 +77,42 @@ impl Ctx {
             main_namespace: Namespace::main(),
             cli: None,
             schema: None,
+            main_schema: None,
+            schema_has_errors: false,
             setup: None,
             programs: btreemap!{},
             conn_ctx: None,
+            static_files: vec![],
+            server_options: ServerOptions::default(),
+            panic_hook: None,
+            idempotency: None,
+            webhooks: vec![],
+            schedules: vec![],
+            audit: None,
+            admin_dashboard: None,
         }
     }
 
     pub(in crate::app) fn create() -> bool {
-        if CURRENT.get().is_none() {
-            CURRENT.set(Arc::new(Mutex::new(Self::new()))).unwrap();
-            true
-        } else {
-            false
+        match CURRENT.get() {
+            None => {
+                CURRENT.set(Arc::new(Mutex::new(Self::new()))).unwrap();
+                true
+            }
+            Some(ctx) => {
+                // a dropped app can be replaced, e.g. between tests
+                let mut ctx = ctx.lock().unwrap();
+                if ctx.loaded {
+                    false
+                } else {
+                    ctx.reload();
+                    true
+                }
+            }
         }
     }
 
-    pub(in crate::app) fn drop() -> Result<()> {
+    pub(crate) fn drop() -> Result<()> {
         Ok(Self::get_mut().reset())
     }
 
@@ -100,8 +144,31 @@ impl Ctx {
         self.loaded = false;
     }
 
+    /// Reset every field for a new app. References handed out as `'static`
+    /// may still point into the namespace and the schema of the dropped
+    /// app, so those are leaked instead of dropped.
     fn reload(&mut self) {
-        self.main_namespace = Namespace::main();
+        mem::forget(mem::replace(&mut self.main_namespace, Namespace::main()));
+        if let Some(schema) = self.schema.take() {
+            mem::forget(schema);
+        }
+        self.argv = None;
+        self.runtime_version = RuntimeVersion::Rust(env!("TEO_RUSTC_VERSION"));
+        self.entrance = Entrance::APP;
+        self.cli = None;
+        self.main_schema = None;
+        self.schema_has_errors = false;
+        self.setup = None;
+        self.programs = btreemap!{};
+        self.conn_ctx = None;
+        self.static_files = vec![];
+        self.server_options = ServerOptions::default();
+        self.panic_hook = None;
+        self.idempotency = None;
+        self.webhooks = vec![];
+        self.schedules = vec![];
+        self.audit = None;
+        self.admin_dashboard = None;
         self.loaded = true;
     }
 
@@ -137,6 +204,23 @@ impl Ctx {
         Ctx::get().schema.as_ref().unwrap()
     }
 
+    pub(crate) fn set_main_schema(path: String, source: Option<String>) {
+        Ctx::get_mut().main_schema = Some((path, source));
+    }
+
+    pub(crate) fn main_schema() -> &'static (String, Option<String>) {
+        Ctx::get().main_schema.as_ref().unwrap()
+    }
+
+    /// Only `doctor` runs with a schema which has errors.
+    pub(crate) fn schema_has_errors() -> bool {
+        Ctx::get().schema_has_errors
+    }
+
+    pub(crate) fn set_schema_has_errors(schema_has_errors: bool) {
+        Ctx::get_mut().schema_has_errors = schema_has_errors;
+    }
+
     pub fn set_entrance(entrance: Entrance) {
         Ctx::get_mut().entrance = entrance;
     }
@@ -159,11 +243,90 @@ impl Ctx {
     }
 
     pub fn insert_program<F>(name: &str, desc: Option<String>, f: F) where F: AsyncCallback + 'static {
+        Ctx::insert_program_with_signature(name, desc, ProgramSignature::new(), move |ctx: transaction::Ctx, _args: ProgramArgs| f.call(ctx));
+    }
+
+    pub fn insert_program_with_signature<F>(name: &str, desc: Option<String>, signature: ProgramSignature, f: F) where F: AsyncProgramCallback + 'static {
         Ctx::get_mut().programs.insert(
             name.to_owned(),
-            Program { func: Arc::new(f), desc }
+            Program { func: Arc::new(f), desc, signature }
         );
     }
+
+    pub fn static_files() -> &'static Vec<(String, StaticFiles)> {
+        &Ctx::get().static_files
+    }
+
+    pub fn server_options() -> &'static ServerOptions {
+        &Ctx::get().server_options
+    }
+
+    pub fn set_server_options(server_options: ServerOptions) {
+        Ctx::get_mut().server_options = server_options;
+    }
+
+    pub fn panic_hook() -> Option<&'static Arc<dyn PanicHook>> {
+        Ctx::get().panic_hook.as_ref()
+    }
+
+    pub fn set_panic_hook<F>(f: F) where F: PanicHook + 'static {
+        Ctx::get_mut().panic_hook = Some(Arc::new(f));
+    }
+
+    pub fn idempotency() -> Option<&'static Idempotency> {
+        Ctx::get().idempotency.as_ref()
+    }
+
+    pub fn set_idempotency(idempotency: Idempotency) {
+        Ctx::get_mut().idempotency = Some(idempotency);
+    }
+
+    pub fn webhooks() -> &'static Vec<Webhook> {
+        &Ctx::get().webhooks
+    }
+
+    pub fn insert_webhook(webhook: Webhook) {
+        let webhooks = &mut Ctx::get_mut().webhooks;
+        webhooks.retain(|w| w.name != webhook.name);
+        webhooks.push(webhook);
+    }
+
+    pub fn schedules() -> &'static Vec<Schedule> {
+        &Ctx::get().schedules
+    }
+
+    pub fn insert_schedule(schedule: Schedule) {
+        Ctx::get_mut().schedules.push(schedule);
+    }
+
+    pub fn audit() -> Option<&'static Audit> {
+        Ctx::get().audit.as_ref()
+    }
+
+    pub fn set_audit(audit: Audit) {
+        Ctx::get_mut().audit = Some(audit);
+    }
+
+    pub fn admin_dashboard() -> Option<&'static AdminDashboard> {
+        Ctx::get().admin_dashboard.as_ref()
+    }
+
+    pub fn admin_dashboard_mut() -> Option<&'static mut AdminDashboard> {
+        Ctx::get_mut().admin_dashboard.as_mut()
+    }
+
+    pub fn set_admin_dashboard(admin_dashboard: AdminDashboard) {
+        Ctx::get_mut().admin_dashboard = Some(admin_dashboard);
+    }
+
+    pub fn insert_static_files(prefix: &str, static_files: StaticFiles) {
+        let prefix = format!("/{}", prefix.trim_matches('/'));
+        let mounts = &mut Ctx::get_mut().static_files;
+        mounts.retain(|(p, _)| p != &prefix);
+        mounts.push((prefix, static_files));
+        // longer prefixes are matched first
+        mounts.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+    }
 }
 
 static CURRENT: OnceCell<Arc<Mutex<Ctx>>> = OnceCell::new();
\ No newline at end of file
@@ -10,30 +10,50 @@ use teo_sql_connector::schema::dialect::SQLDialect;
 use teo_mongodb_connector::connector::MongoDBConnection;
 use crate::app::ctx::Ctx;
 use teo_runtime::connection::Ctx as ConnCtx;
-use crate::message::info_message;
+use serde_json::json;
+use crate::message::{event, info_message, json_output};
+use crate::profile::mask_password;
 
 pub async fn connect_databases(namespace: &mut Namespace, silent: bool) -> Result<()> {
+    connect_namespace(namespace, silent).await?;
+    install_connections();
+    Ok(())
+}
+
+/// Connect the databases of a namespace without making them the app's, e.g.
+/// a reloaded namespace before it replaces the running one.
+pub(crate) async fn connect_namespace(namespace: &mut Namespace, silent: bool) -> Result<()> {
     may_connect_database(namespace, silent).await?;
     for namespace in namespace.namespaces.values_mut() {
         may_connect_database(namespace, silent).await?;
     }
+    Ok(())
+}
+
+/// Use the connections of the main namespace for new transactions.
+pub(crate) fn install_connections() {
     let ctx = ConnCtx::from_namespace(Ctx::main_namespace());
     Ctx::get_mut().conn_ctx = Some(ctx);
-    Ok(())
 }
 
 pub async fn may_connect_database(namespace: &mut Namespace, silent: bool) -> Result<()> {
     if namespace.connector.is_none() { return Ok(()) }
     let connector = namespace.connector.as_ref().unwrap();
     let connection = connection_for_connector(connector).await;
-    if !silent {
+    if json_output() {
+        event("connect", json!({
+            "namespace": if namespace.path.is_empty() { "main".to_string() } else { namespace.path().join(".") },
+            "connector": connector.provider.lowercase_desc(),
+            "url": mask_password(&connector.url),
+        }));
+    } else if !silent {
         info_message(format!("{} connector connected for `{}` at \"{}\"", connector.provider.lowercase_desc(), if namespace.path.is_empty() { "main".to_string() } else { namespace.path().join(".") }, connector.url));
     }
     namespace.connection = Some(connection);
     Ok(())
 }
 
-async fn connection_for_connector(connector: &Connector) -> Arc<dyn Connection> {
+pub(crate) async fn connection_for_connector(connector: &Connector) -> Arc<dyn Connection> {
     if connector.provider.is_mongo() {
         Arc::new(MongoDBConnection::new(connector.url.as_str()).await)
     } else {
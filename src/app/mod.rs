@@ -1,6 +1,7 @@
 pub mod app;
 pub mod ctx;
 pub mod callbacks;
+pub mod program;
 pub mod database;
 
 pub use app::App;
@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use clap::{Arg, ArgAction, ArgMatches, Command as ClapCommand, value_parser};
+use futures_util::future::BoxFuture;
+use teo_result::{Error, Result};
+use teo_runtime::connection::transaction::Ctx;
+use teo_runtime::Value;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ProgramArgType {
+    String,
+    Int,
+    Float,
+    Bool,
+}
+
+impl ProgramArgType {
+
+    fn value_name(&self) -> &'static str {
+        match self {
+            ProgramArgType::String => "STRING",
+            ProgramArgType::Int => "INT",
+            ProgramArgType::Float => "FLOAT",
+            ProgramArgType::Bool => "BOOL",
+        }
+    }
+}
+
+/// An argument or option accepted by a program.
+///
+/// Arguments are positional and options are passed as `--name value`. Bool
+/// options are flags and never take a value.
+#[derive(Debug, Clone)]
+pub struct ProgramArg {
+    name: String,
+    r#type: ProgramArgType,
+    positional: bool,
+    required: bool,
+    default: Option<String>,
+    desc: Option<String>,
+}
+
+impl ProgramArg {
+
+    pub fn argument(name: impl Into<String>, r#type: ProgramArgType) -> Self {
+        Self { name: name.into(), r#type, positional: true, required: true, default: None, desc: None }
+    }
+
+    pub fn option(name: impl Into<String>, r#type: ProgramArgType) -> Self {
+        Self { name: name.into(), r#type, positional: false, required: false, default: None, desc: None }
+    }
+
+    pub fn flag(name: impl Into<String>) -> Self {
+        Self::option(name, ProgramArgType::Bool)
+    }
+
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    pub fn default(mut self, default: impl Into<String>) -> Self {
+        self.default = Some(default.into());
+        self
+    }
+
+    pub fn desc(mut self, desc: impl Into<String>) -> Self {
+        self.desc = Some(desc.into());
+        self
+    }
+
+    fn clap_arg(&self) -> Arg {
+        let mut arg = Arg::new(self.name.clone());
+        if !self.positional {
+            arg = arg.long(self.name.clone());
+        }
+        if let Some(desc) = &self.desc {
+            arg = arg.help(desc.clone());
+        }
+        if !self.positional && self.r#type == ProgramArgType::Bool {
+            return arg.action(ArgAction::SetTrue);
+        }
+        arg = arg.action(ArgAction::Set).num_args(1).value_name(self.r#type.value_name()).required(self.required && self.default.is_none());
+        if let Some(default) = &self.default {
+            arg = arg.default_value(default.clone());
+        }
+        match self.r#type {
+            ProgramArgType::String => arg,
+            ProgramArgType::Int => arg.value_parser(value_parser!(i64)),
+            ProgramArgType::Float => arg.value_parser(value_parser!(f64)),
+            ProgramArgType::Bool => arg.value_parser(value_parser!(bool)),
+        }
+    }
+
+    fn usage(&self) -> String {
+        let usage = if self.positional {
+            format!("<{}>", self.name.to_uppercase())
+        } else if self.r#type == ProgramArgType::Bool {
+            format!("--{}", self.name)
+        } else {
+            format!("--{} <{}>", self.name, self.r#type.value_name())
+        };
+        if self.required && self.default.is_none() && !(self.r#type == ProgramArgType::Bool && !self.positional) {
+            usage
+        } else {
+            format!("[{}]", usage)
+        }
+    }
+
+    fn value(&self, matches: &ArgMatches) -> Option<Value> {
+        if !self.positional && self.r#type == ProgramArgType::Bool {
+            return Some(Value::Bool(matches.get_flag(&self.name)));
+        }
+        match self.r#type {
+            ProgramArgType::String => matches.get_one::<String>(&self.name).map(|v| Value::String(v.clone())),
+            ProgramArgType::Int => matches.get_one::<i64>(&self.name).map(|v| Value::Int64(*v)),
+            ProgramArgType::Float => matches.get_one::<f64>(&self.name).map(|v| Value::Float(*v)),
+            ProgramArgType::Bool => matches.get_one::<bool>(&self.name).map(|v| Value::Bool(*v)),
+        }
+    }
+}
+
+/// The arguments and options a program accepts.
+///
+/// Signatures are declared in Rust with
+/// [`App::program_with_args`](crate::app::App::program_with_args). The
+/// schema language has no syntax for program declarations, so a schema
+/// can't declare them.
+#[derive(Debug, Clone, Default)]
+pub struct ProgramSignature {
+    args: Vec<ProgramArg>,
+}
+
+impl ProgramSignature {
+
+    pub fn new() -> Self {
+        Self { args: vec![] }
+    }
+
+    pub fn arg(mut self, arg: ProgramArg) -> Self {
+        self.args.push(arg);
+        self
+    }
+
+    pub(crate) fn usage(&self) -> String {
+        self.args.iter().map(|a| a.usage()).collect::<Vec<String>>().join(" ")
+    }
+
+    /// Parse the arguments following the program name. Invalid arguments and
+    /// `--help` print clap's output and exit.
+    pub(crate) fn parse(&self, name: &str, desc: Option<&String>, argv: &Vec<String>) -> ProgramArgs {
+        match self.command(name, desc).try_get_matches_from(std::iter::once(name.to_owned()).chain(argv.iter().cloned())) {
+            Ok(matches) => self.values(&matches),
+            Err(e) => e.exit(),
+        }
+    }
+
+    /// Parse arguments not coming from the command line, e.g. of a schedule.
+    pub(crate) fn try_parse(&self, name: &str, argv: &Vec<String>) -> Result<ProgramArgs> {
+        match self.command(name, None).try_get_matches_from(std::iter::once(name.to_owned()).chain(argv.iter().cloned())) {
+            Ok(matches) => Ok(self.values(&matches)),
+            // keep clap's first line without the usage
+            Err(e) => Err(Error::new(e.to_string().lines().next().unwrap_or_default().trim_start_matches("error: ").to_owned())),
+        }
+    }
+
+    fn command(&self, name: &str, desc: Option<&String>) -> ClapCommand {
+        let mut command = ClapCommand::new(format!("run {}", name)).disable_version_flag(true);
+        if let Some(desc) = desc {
+            command = command.about(desc.clone());
+        }
+        for arg in &self.args {
+            command = command.arg(arg.clap_arg());
+        }
+        command
+    }
+
+    fn values(&self, matches: &ArgMatches) -> ProgramArgs {
+        let mut values = BTreeMap::new();
+        for arg in &self.args {
+            if let Some(value) = arg.value(matches) {
+                values.insert(arg.name.clone(), value);
+            }
+        }
+        ProgramArgs { values }
+    }
+}
+
+/// Parsed arguments and options passed to a program.
+#[derive(Debug, Clone, Default)]
+pub struct ProgramArgs {
+    values: BTreeMap<String, Value>,
+}
+
+impl ProgramArgs {
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    pub fn string(&self, name: &str) -> Result<Option<String>> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(value) => value.as_str().map(|s| Some(s.to_owned())).ok_or_else(|| mismatch(name, "String")),
+        }
+    }
+
+    pub fn int(&self, name: &str) -> Result<Option<i64>> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(value) => value.as_int64().map(Some).ok_or_else(|| mismatch(name, "Int")),
+        }
+    }
+
+    pub fn float(&self, name: &str) -> Result<Option<f64>> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(value) => value.as_float().map(Some).ok_or_else(|| mismatch(name, "Float")),
+        }
+    }
+
+    pub fn bool(&self, name: &str) -> Result<bool> {
+        match self.get(name) {
+            None => Ok(false),
+            Some(value) => value.as_bool().ok_or_else(|| mismatch(name, "Bool")),
+        }
+    }
+}
+
+fn mismatch(name: &str, r#type: &str) -> Error {
+    Error::new(format!("program argument `{}` is not {}", name, r#type))
+}
+
+pub trait AsyncProgramCallback: Send + Sync {
+    fn call(&self, ctx: Ctx, args: ProgramArgs) -> BoxFuture<'static, Result<()>>;
+}
+
+impl<F, Fut> AsyncProgramCallback for F where
+    F: Fn(Ctx, ProgramArgs) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<()>> + Send + 'static {
+    fn call(&self, ctx: Ctx, args: ProgramArgs) -> BoxFuture<'static, Result<()>> {
+        Box::pin(self(ctx, args))
+    }
+}
@@ -0,0 +1,74 @@
+use chrono::{DateTime, Utc};
+use teo_result::{Error, Result};
+use teo_runtime::connection::transaction;
+use teo_runtime::teon;
+use teo_runtime::Value;
+use crate::app::Ctx;
+use crate::audit::models::audit_log::AuditLog;
+use crate::cli::command::AuditCommand;
+use crate::message::json_output;
+
+pub(crate) async fn audit(command: &AuditCommand) -> Result<()> {
+    let ctx = transaction::Ctx::new(Ctx::conn_ctx().clone());
+    let mut r#where = teon!({});
+    let filters = r#where.as_dictionary_mut().unwrap();
+    if let Some(model) = &command.model {
+        filters.insert("model".to_owned(), Value::String(model.clone()));
+    }
+    if let Some(action) = &command.action {
+        filters.insert("action".to_owned(), Value::String(action.clone()));
+    }
+    if let Some(identifier) = &command.identifier {
+        filters.insert("identifier".to_owned(), teon!({ "contains": identifier.as_str() }));
+    }
+    if let Some(identity) = &command.identity {
+        filters.insert("identity".to_owned(), teon!({ "contains": identity.as_str() }));
+    }
+    if let Some(request_id) = &command.request_id {
+        filters.insert("requestId".to_owned(), Value::String(request_id.clone()));
+    }
+    if let Some(since) = &command.since {
+        let since = match DateTime::parse_from_rfc3339(since) {
+            Ok(since) => since.with_timezone(&Utc),
+            Err(_) => Err(Error::new(format!("invalid date `{}`, expect RFC 3339", since)))?,
+        };
+        filters.insert("createdAt".to_owned(), teon!({ "gte": since }));
+    }
+    let logs = AuditLog::find_many(teon!({
+        "where": r#where,
+        "orderBy": { "createdAt": "desc" },
+        "take": command.limit as i32,
+    }), ctx).await?;
+    if json_output() {
+        let values: Vec<serde_json::Value> = logs.iter().map(|log| serde_json::json!({
+            "id": log.id(),
+            "model": log.model(),
+            "identifier": serde_json::from_str::<serde_json::Value>(&log.identifier()).unwrap_or(serde_json::Value::Null),
+            "identity": log.identity().and_then(|i| serde_json::from_str::<serde_json::Value>(&i).ok()),
+            "requestId": log.request_id(),
+            "action": log.action(),
+            "changes": serde_json::from_str::<serde_json::Value>(&log.changes()).unwrap_or(serde_json::Value::Null),
+            "createdAt": log.created_at().to_rfc3339(),
+        })).collect();
+        println!("{}", serde_json::Value::Array(values));
+        return Ok(());
+    }
+    println!("+-{:<25}-+-{:<24}-+-{:<24}-+-{:<6}-+-{:<48}-+", "-".repeat(25), "-".repeat(24), "-".repeat(24), "-".repeat(6), "-".repeat(48));
+    println!("| {:^25} | {:^24} | {:^24} | {:^6} | {:^48} |", "Time", "Model", "Identifier", "Action", "Changed fields");
+    println!("+-{:<25}-+-{:<24}-+-{:<24}-+-{:<6}-+-{:<48}-+", "-".repeat(25), "-".repeat(24), "-".repeat(24), "-".repeat(6), "-".repeat(48));
+    if logs.is_empty() {
+        println!("| {:^139} |", "No audit logs.");
+    } else {
+        for log in logs {
+            let changed_fields = match serde_json::from_str::<serde_json::Value>(&log.changes()) {
+                Ok(serde_json::Value::Object(changes)) => changes.keys().cloned().collect::<Vec<String>>().join(", "),
+                _ => String::new(),
+            };
+            let changed_fields: String = changed_fields.chars().take(48).collect();
+            let identifier: String = log.identifier().chars().take(24).collect();
+            println!("| {:<25} | {:<24} | {:<24} | {:<6} | {:<48} |", log.created_at().format("%Y-%m-%d %H:%M:%S UTC").to_string(), log.model(), identifier, log.action(), changed_fields);
+        }
+    }
+    println!("+-{:<25}---{:<24}---{:<24}---{:<6}---{:<48}-+", "-".repeat(25), "-".repeat(24), "-".repeat(24), "-".repeat(6), "-".repeat(48));
+    Ok(())
+}
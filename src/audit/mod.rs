@@ -0,0 +1,231 @@
+pub mod models;
+pub(crate) mod command;
+
+use std::sync::Arc;
+use educe::Educe;
+use chrono::Utc;
+use key_path::path;
+use serde_json::{json, Map, Value as JsonValue};
+use teo_result::{Error, Result};
+use teo_runtime::arguments::Arguments;
+use teo_runtime::model::Object;
+use teo_runtime::namespace::Namespace;
+use teo_runtime::request::Request;
+use teo_runtime::teon;
+use teo_runtime::traits::named::Named;
+use teo_runtime::Value;
+use crate::app::Ctx;
+use crate::audit::models::audit_log::AuditLog;
+use crate::server::panic::REQUEST_ID_HEADER;
+use crate::stdlib::before_save::BeforeSave;
+
+const REDACTED: &str = "[redacted]";
+
+pub trait AuditIdentity: Send + Sync {
+    fn call(&self, request: &Request) -> Option<JsonValue>;
+}
+
+impl<F> AuditIdentity for F where F: Fn(&Request) -> Option<JsonValue> + Send + Sync {
+    fn call(&self, request: &Request) -> Option<JsonValue> {
+        self(request)
+    }
+}
+
+/// Opt-in audit log of mutations, stored in the `std.AuditLog` model which
+/// ships with the crate.
+///
+/// Models declare the `$std.auditBefore`, `$std.auditSave` and
+/// `$std.auditDelete` callbacks to be recorded, see
+/// [`define_pipeline_items`]. Values of excluded fields are never written to
+/// the log, only the fact that they changed.
+#[derive(Educe, Clone)]
+#[educe(Debug)]
+pub struct Audit {
+    excluded_models: Vec<String>,
+    excluded_fields: Vec<(String, String)>,
+    #[educe(Debug(ignore))]
+    identity: Option<Arc<dyn AuditIdentity>>,
+}
+
+impl Audit {
+
+    pub fn new() -> Self {
+        Self {
+            excluded_models: vec![],
+            excluded_fields: vec![],
+            identity: None,
+        }
+    }
+
+    /// Don't record mutations of a model, e.g. `"Session"` or `"auth.Session"`.
+    pub fn exclude_model(mut self, model: impl Into<String>) -> Self {
+        self.excluded_models.push(model.into());
+        self
+    }
+
+    /// Don't record values of a field, e.g. `("User", "password")`.
+    pub fn exclude_field(mut self, model: impl Into<String>, field: impl Into<String>) -> Self {
+        self.excluded_fields.push((model.into(), field.into()));
+        self
+    }
+
+    /// Resolve the identity performing a request.
+    pub fn identity<F>(mut self, f: F) -> Self where F: Fn(&Request) -> Option<JsonValue> + Send + Sync + 'static {
+        self.identity = Some(Arc::new(f));
+        self
+    }
+
+    pub(crate) fn audits(&self, model_path: &str) -> bool {
+        !model_path.starts_with("std.") && !self.excluded_models.iter().any(|m| m == model_path)
+    }
+
+    fn excludes_field(&self, model_path: &str, field: &str) -> bool {
+        self.excluded_fields.iter().any(|(m, f)| m == model_path && f == field)
+    }
+}
+
+impl Default for Audit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+}
+
+impl AuditAction {
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+        }
+    }
+}
+
+type Snapshot = Map<String, JsonValue>;
+
+/// Stored values of records being updated, read by `auditBefore`.
+static BEFORE: BeforeSave<Snapshot> = BeforeSave::new();
+
+/// Implement `auditBefore`, `auditSave` and `auditDelete`. Models are
+/// audited by declaring them in their callbacks, e.g.
+///
+/// ```teo
+/// @beforeSave($std.auditBefore)
+/// @afterSave($std.auditSave)
+/// @afterDelete($std.auditDelete)
+/// model Order { ... }
+/// ```
+///
+/// Callbacks run in the transaction of the write, so the stored values are
+/// read and the log is written with it, one record at a time. A failure to
+/// write the log fails the write. Builtin actions, nested writes and saves
+/// of programs are all covered.
+pub(crate) fn define_pipeline_items(namespace: &mut Namespace) {
+    namespace.define_callback_pipeline_item("auditBefore", |_args: Arguments, object: Object| async move {
+        let model_path = object.model().path().join(".");
+        if object.is_new() || !Ctx::audit().is_some_and(|a| a.audits(&model_path)) {
+            return Ok(());
+        }
+        let stored: Option<Object> = object.transaction_ctx().find_unique(object.model(), &teon!({
+            "where": object.identifier()
+        }), None, path![]).await?;
+        if let Some(stored) = stored {
+            BEFORE.insert(&object, snapshot(&stored));
+        }
+        Ok(())
+    });
+    namespace.define_callback_pipeline_item("auditSave", |_args: Arguments, object: Object| async move {
+        let model_path = object.model().path().join(".");
+        let Some(audit) = Ctx::audit().filter(|a| a.audits(&model_path)) else {
+            return Ok(());
+        };
+        let identifier = to_json(&object.identifier());
+        let before = BEFORE.take(&object);
+        let action = if before.is_some() { AuditAction::Update } else { AuditAction::Create };
+        let changes = diff(audit, &model_path, before.as_ref(), Some(&snapshot(&object)));
+        if action == AuditAction::Update && changes.is_empty() {
+            return Ok(());
+        }
+        record(audit, &object, &model_path, &identifier, action, changes).await
+    });
+    namespace.define_callback_pipeline_item("auditDelete", |_args: Arguments, object: Object| async move {
+        let model_path = object.model().path().join(".");
+        let Some(audit) = Ctx::audit().filter(|a| a.audits(&model_path)) else {
+            return Ok(());
+        };
+        let changes = diff(audit, &model_path, Some(&snapshot(&object)), None);
+        record(audit, &object, &model_path, &to_json(&object.identifier()), AuditAction::Delete, changes).await
+    });
+}
+
+/// Write the log in the transaction of the write. The identity and the
+/// request id are known when a request made the write.
+async fn record(audit: &Audit, object: &Object, model_path: &str, identifier: &JsonValue, action: AuditAction, changes: Snapshot) -> Result<()> {
+    let request = object.request_ctx().map(|ctx| ctx.request().clone());
+    let identity = request.as_ref().and_then(|r| audit.identity.as_ref().and_then(|i| i.call(r)));
+    let request_id = request.as_ref().and_then(|r| r.headers().get(REQUEST_ID_HEADER).map(|s| s.to_owned()));
+    let log = AuditLog::new(teon!({
+        "id": uuid::Uuid::new_v4().to_string(),
+        "model": model_path,
+        "identifier": identifier.to_string(),
+        "identity": match identity {
+            Some(identity) => Value::String(identity.to_string()),
+            None => Value::Null,
+        },
+        "requestId": match request_id {
+            Some(request_id) => Value::String(request_id),
+            None => Value::Null,
+        },
+        "action": action.as_str(),
+        "changes": JsonValue::Object(changes).to_string(),
+        "createdAt": Utc::now(),
+    }), object.transaction_ctx()).await?;
+    log.save().await
+}
+
+fn snapshot(object: &Object) -> Snapshot {
+    let mut result = Map::new();
+    for field in object.model().fields.values() {
+        if let Ok(value) = object.get_value(field.name()) {
+            result.insert(field.name().to_owned(), to_json(&value));
+        }
+    }
+    result
+}
+
+/// Field level before and after values. Excluded fields are redacted.
+fn diff(audit: &Audit, model_path: &str, before: Option<&Snapshot>, after: Option<&Snapshot>) -> Snapshot {
+    let mut result = Map::new();
+    let keys = before.iter().chain(after.iter()).flat_map(|s| s.keys()).cloned().collect::<Vec<String>>();
+    for key in keys {
+        if result.contains_key(&key) { continue }
+        let before_value = before.and_then(|b| b.get(&key)).cloned().unwrap_or(JsonValue::Null);
+        let after_value = after.and_then(|a| a.get(&key)).cloned().unwrap_or(JsonValue::Null);
+        if before.is_some() && after.is_some() && before_value == after_value { continue }
+        if audit.excludes_field(model_path, &key) {
+            result.insert(key, json!({ "before": REDACTED, "after": REDACTED }));
+        } else {
+            result.insert(key, json!({ "before": before_value, "after": after_value }));
+        }
+    }
+    result
+}
+
+fn to_json(value: &Value) -> JsonValue {
+    JsonValue::try_from(value).unwrap_or(JsonValue::Null)
+}
+
+/// Make sure the std model backing the audit log is loaded.
+pub(crate) fn check_model(namespace: &Namespace) -> Result<()> {
+    if namespace.model_at_path(&vec!["std", "AuditLog"]).is_none() {
+        Err(Error::new("audit requires the `std.AuditLog` model"))?
+    }
+    Ok(())
+}
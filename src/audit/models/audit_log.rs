@@ -0,0 +1,96 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::borrow::Borrow;
+use chrono::{DateTime, Utc};
+use key_path::path;
+use teo_runtime::connection::transaction;
+use teo_runtime::model;
+use crate::prelude::{Value, Result};
+
+/// Audit log
+pub struct AuditLog {
+    pub(super) inner: model::Object,
+}
+
+impl AuditLog {
+
+    /// Find many audit logs.
+    pub async fn find_many(query: impl Borrow<Value>, ctx: transaction::Ctx) -> Result<Vec<AuditLog>> {
+        let model = ctx.namespace().model_at_path(&vec!["std", "AuditLog"]).unwrap();
+        Ok(ctx.find_many(model, query.borrow(), None, path![]).await?)
+    }
+
+    /// Create a new audit log.
+    pub async fn new(values: impl Borrow<Value>, ctx: transaction::Ctx) -> Result<Self> {
+        let model = ctx.namespace().model_at_path(&vec!["std", "AuditLog"]).unwrap();
+        Ok(ctx.create_object(model, values.borrow(), None).await?.into())
+    }
+
+    /// Save this audit log.
+    pub async fn save(&self) -> Result<()> {
+        self.inner.save().await
+    }
+
+    /// Id
+    pub fn id(&self) -> String {
+        self.inner.get("id").unwrap()
+    }
+
+    /// Model
+    pub fn model(&self) -> String {
+        self.inner.get("model").unwrap()
+    }
+
+    /// Identifier
+    pub fn identifier(&self) -> String {
+        self.inner.get("identifier").unwrap()
+    }
+
+    /// Identity
+    pub fn identity(&self) -> Option<String> {
+        self.inner.get("identity").unwrap()
+    }
+
+    /// Request id
+    pub fn request_id(&self) -> Option<String> {
+        self.inner.get("requestId").unwrap()
+    }
+
+    /// Action
+    pub fn action(&self) -> String {
+        self.inner.get("action").unwrap()
+    }
+
+    /// Changes
+    pub fn changes(&self) -> String {
+        self.inner.get("changes").unwrap()
+    }
+
+    /// Created at
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.inner.get("createdAt").unwrap()
+    }
+}
+
+impl Into<model::Object> for AuditLog {
+    fn into(self) -> model::Object {
+        self.inner.clone()
+    }
+}
+
+impl From<model::Object> for AuditLog {
+    fn from(value: model::Object) -> Self {
+        Self { inner: value }
+    }
+}
+
+impl Debug for AuditLog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.inner, f)
+    }
+}
+
+impl Display for AuditLog {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.inner, f)
+    }
+}
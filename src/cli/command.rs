@@ -1,8 +1,13 @@
+use crate::server::options::ServerOptions;
+
 #[derive(Debug)]
 pub(crate) struct ServeCommand {
     pub(crate) no_migration: bool,
     pub(crate) no_autoseed: bool,
-    pub(crate) env: Option<String>,
+    pub(crate) options: ServerOptions,
+    pub(crate) watch: bool,
+    pub(crate) admin: Option<String>,
+    pub(crate) strict_migrations: bool,
 }
 
 #[derive(Debug)]
@@ -30,6 +35,16 @@ pub(crate) struct GenerateAdminCommand { }
 #[derive(Debug)]
 pub(crate) struct MigrateCommand {
     pub(crate) dry: bool,
+    pub(crate) action: MigrateCommandAction,
+}
+
+#[derive(Debug)]
+pub(crate) enum MigrateCommandAction {
+    Diff,
+    Create(String),
+    Up(Option<String>),
+    Down(usize),
+    Status,
 }
 
 #[derive(Debug)]
@@ -47,28 +62,184 @@ pub(crate) enum SeedCommandAction {
 }
 
 #[derive(Debug)]
-pub(crate) struct PurgeCommand { }
+pub(crate) struct PurgeCommand {
+    pub(crate) namespace: Option<String>,
+    pub(crate) models: Option<Vec<String>>,
+    pub(crate) yes: bool,
+    pub(crate) force: bool,
+    pub(crate) snapshot: Option<String>,
+}
+
+#[derive(Debug)]
+pub(crate) struct LintCommand {
+    pub(crate) format: LintFormat,
+    pub(crate) config: Option<String>,
+    pub(crate) deny_warnings: bool,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum LintFormat {
+    Text,
+    Json,
+    Sarif,
+}
 
 #[derive(Debug)]
-pub(crate) struct LintCommand { }
+pub(crate) struct FmtCommand {
+    pub(crate) check: bool,
+}
 
 #[derive(Debug)]
 pub(crate) struct RunCommand {
     pub(crate) list: bool,
     pub(crate) name: Option<String>,
+    pub(crate) args: Vec<String>,
+}
+
+#[derive(Debug)]
+pub(crate) enum WebhooksCommand {
+    List(WebhooksListCommand),
+    Replay(WebhooksReplayCommand),
+}
+
+#[derive(Debug)]
+pub(crate) struct WebhooksListCommand {
+    pub(crate) status: Option<String>,
+    pub(crate) limit: usize,
+}
+
+#[derive(Debug)]
+pub(crate) struct WebhooksReplayCommand {
+    pub(crate) ids: Option<Vec<String>>,
+    pub(crate) dead: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct AuditCommand {
+    pub(crate) model: Option<String>,
+    pub(crate) identifier: Option<String>,
+    pub(crate) identity: Option<String>,
+    pub(crate) request_id: Option<String>,
+    pub(crate) action: Option<String>,
+    pub(crate) since: Option<String>,
+    pub(crate) limit: usize,
+}
+
+#[derive(Debug)]
+pub(crate) enum DbCommand {
+    Pull(DbPullCommand),
+}
+
+#[derive(Debug)]
+pub(crate) struct DbPullCommand {
+    pub(crate) out: Option<String>,
+}
+
+#[derive(Debug)]
+pub(crate) struct DumpCommand {
+    pub(crate) out: Option<String>,
+    pub(crate) namespace: Option<String>,
+    pub(crate) models: Option<Vec<String>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct RestoreCommand {
+    pub(crate) input: String,
+    pub(crate) purge: bool,
+    pub(crate) force: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct ConsoleCommand {
+    pub(crate) readonly: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct RoutesCommand {
+    pub(crate) r#match: Option<RoutesMatchCommand>,
+}
+
+#[derive(Debug)]
+pub(crate) struct RoutesMatchCommand {
+    pub(crate) method: String,
+    pub(crate) path: String,
+}
+
+#[derive(Debug)]
+pub(crate) struct DoctorCommand { }
+
+#[derive(Debug)]
+pub(crate) struct QueryCommand {
+    pub(crate) target: String,
+    pub(crate) body: Option<String>,
+    pub(crate) method: String,
+    pub(crate) token: Option<String>,
+    pub(crate) identity: Option<String>,
+    pub(crate) headers: Vec<String>,
+}
+
+#[derive(Debug)]
+pub(crate) enum SchemaCommand {
+    Diff(SchemaDiffCommand),
+}
+
+#[derive(Debug)]
+pub(crate) struct SchemaDiffCommand {
+    pub(crate) old: String,
+    pub(crate) new: Option<String>,
+    pub(crate) fail_on_breaking: bool,
+}
+
+#[derive(Debug)]
+pub(crate) enum ConfigCommand {
+    Print,
+}
+
+#[derive(Debug)]
+pub(crate) struct NewCommand {
+    pub(crate) dir: String,
+    pub(crate) template: Option<NewTemplate>,
+    pub(crate) connector: Option<NewConnector>,
+    pub(crate) yes: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum NewTemplate {
+    Rust,
+    Schema,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum NewConnector {
+    SQLite,
+    MySQL,
+    PostgreSQL,
+    MongoDB,
 }
 
 #[derive(Debug)]
 pub struct CLI {
     pub(crate) command: CLICommand,
     pub(crate) schema: Option<String>,
+    pub(crate) env: Option<String>,
+    pub(crate) output: OutputFormat,
     pub(crate) silent: bool,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum OutputFormat {
+    Text,
+    Json,
+}
+
 impl CLI {
     pub(crate) fn main(&self) -> Option<&str> {
         self.schema.as_ref().map(|s| s.as_str())
     }
+
+    pub(crate) fn env(&self) -> Option<&str> {
+        self.env.as_ref().map(|s| s.as_str())
+    }
 }
 
 #[derive(Debug)]
@@ -80,6 +251,19 @@ pub(crate) enum CLICommand {
     Purge(PurgeCommand),
     Lint(LintCommand),
     Run(RunCommand),
+    Webhooks(WebhooksCommand),
+    Audit(AuditCommand),
+    Db(DbCommand),
+    Fmt(FmtCommand),
+    Dump(DumpCommand),
+    Restore(RestoreCommand),
+    Console(ConsoleCommand),
+    Routes(RoutesCommand),
+    Doctor(DoctorCommand),
+    New(NewCommand),
+    Config(ConfigCommand),
+    Query(QueryCommand),
+    Schema(SchemaCommand),
 }
 
 impl CLICommand {
@@ -88,7 +272,43 @@ impl CLICommand {
         match self {
             CLICommand::Generate(_) => true,
             CLICommand::Lint(_) => true,
+            CLICommand::Fmt(_) => true,
+            CLICommand::Doctor(_) => true,
+            CLICommand::Schema(_) => true,
             _ => false,
         }
     }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            CLICommand::Serve(_) => "serve",
+            CLICommand::Generate(_) => "generate",
+            CLICommand::Migrate(_) => "migrate",
+            CLICommand::Seed(_) => "seed",
+            CLICommand::Purge(_) => "purge",
+            CLICommand::Lint(_) => "lint",
+            CLICommand::Run(_) => "run",
+            CLICommand::Webhooks(_) => "webhooks",
+            CLICommand::Audit(_) => "audit",
+            CLICommand::Db(_) => "db",
+            CLICommand::Fmt(_) => "fmt",
+            CLICommand::Dump(_) => "dump",
+            CLICommand::Restore(_) => "restore",
+            CLICommand::Console(_) => "console",
+            CLICommand::Routes(_) => "routes",
+            CLICommand::Doctor(_) => "doctor",
+            CLICommand::New(_) => "new",
+            CLICommand::Config(_) => "config",
+            CLICommand::Query(_) => "query",
+            CLICommand::Schema(_) => "schema",
+        }
+    }
+
+    /// Commands which work without a schema, e.g. before a project exists.
+    pub(crate) fn requires_schema(&self) -> bool {
+        match self {
+            CLICommand::New(_) => false,
+            _ => true,
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,14 @@
+//! Exit codes of the command line, scripts can rely on these.
+
+/// The command succeeded.
+pub const SUCCESS: i32 = 0;
+
+/// The command failed, e.g. a database is unreachable or a program returned
+/// an error.
+pub const FAILURE: i32 = 1;
+
+/// The arguments are invalid, as reported by the argument parser.
+pub const USAGE: i32 = 2;
+
+/// The schema has errors.
+pub const SCHEMA: i32 = 3;
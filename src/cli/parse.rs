@@ -1,8 +1,11 @@
 use std::env;
-use clap::{Arg, ArgAction, Command as ClapCommand};
+use std::time::Duration;
+use clap::{Arg, ArgAction, Command as ClapCommand, value_parser};
+use clap::parser::ValueSource;
 use crate::cli::entrance::Entrance;
 use crate::cli::runtime_version::RuntimeVersion;
-use crate::cli::command::{CLI, CLICommand, GenerateAdminCommand, GenerateClientCommand, GenerateCommand, GenerateEntityCommand, LintCommand, MigrateCommand, PurgeCommand, RunCommand, SeedCommand, SeedCommandAction, ServeCommand};
+use crate::server::options::ServerOptions;
+use crate::cli::command::{AuditCommand, CLI, CLICommand, ConfigCommand, ConsoleCommand, DbCommand, DbPullCommand, DoctorCommand, DumpCommand, FmtCommand, GenerateAdminCommand, GenerateClientCommand, GenerateCommand, GenerateEntityCommand, LintCommand, LintFormat, MigrateCommand, MigrateCommandAction, NewCommand, NewConnector, NewTemplate, OutputFormat, PurgeCommand, QueryCommand, RestoreCommand, RoutesCommand, RoutesMatchCommand, RunCommand, SchemaCommand, SchemaDiffCommand, SeedCommand, SeedCommandAction, ServeCommand, WebhooksCommand, WebhooksListCommand, WebhooksReplayCommand};
 
 pub(crate) fn parse(runtime_version: RuntimeVersion, entrance: Entrance, argv: Option<Vec<String>>) -> CLI {
     let argv = argv.unwrap_or(env::args_os().map(|s| s.to_str().unwrap().to_owned()).collect());
@@ -28,11 +31,19 @@ pub(crate) fn parse(runtime_version: RuntimeVersion, entrance: Entrance, argv: O
         .arg(Arg::new("ENV")
             .short('e')
             .long("env")
-            .help("The environment to use")
+            .help("The environment to use, reads overrides from teo.<ENV>.toml [env: TEO_ENV]")
             .action(ArgAction::Set)
             .required(false)
             .num_args(1)
             .global(true))
+        .arg(Arg::new("OUTPUT_FORMAT")
+            .long("output")
+            .help("Print messages as text, or as JSON lines for scripts")
+            .action(ArgAction::Set)
+            .num_args(1)
+            .default_value("text")
+            .value_parser(["text", "json"])
+            .global(true))
         .arg(Arg::new("version")
             .short('v')
             .long("version")
@@ -55,6 +66,64 @@ pub(crate) fn parse(runtime_version: RuntimeVersion, entrance: Entrance, argv: O
                 .short('S')
                 .long("no-autoseed")
                 .help("Start server without auto seeding autoseed dataset")
+                .action(ArgAction::SetTrue))
+            .arg(Arg::new("workers")
+                .long("workers")
+                .help("Number of worker threads")
+                .action(ArgAction::Set)
+                .num_args(1)
+                .value_parser(value_parser!(usize)))
+            .arg(Arg::new("backlog")
+                .long("backlog")
+                .help("Maximum number of pending connections")
+                .action(ArgAction::Set)
+                .num_args(1)
+                .value_parser(value_parser!(u32)))
+            .arg(Arg::new("max-connections")
+                .long("max-connections")
+                .help("Maximum number of concurrent connections per worker")
+                .action(ArgAction::Set)
+                .num_args(1)
+                .value_parser(value_parser!(usize)))
+            .arg(Arg::new("keep-alive")
+                .long("keep-alive")
+                .help("Keep alive seconds, 0 disables keep alive")
+                .action(ArgAction::Set)
+                .num_args(1)
+                .value_parser(value_parser!(u64)))
+            .arg(Arg::new("client-request-timeout")
+                .long("client-request-timeout")
+                .help("Client request timeout in milliseconds")
+                .action(ArgAction::Set)
+                .num_args(1)
+                .value_parser(value_parser!(u64)))
+            .arg(Arg::new("client-disconnect-timeout")
+                .long("client-disconnect-timeout")
+                .help("Client disconnect timeout in milliseconds")
+                .action(ArgAction::Set)
+                .num_args(1)
+                .value_parser(value_parser!(u64)))
+            .arg(Arg::new("http2")
+                .long("http2")
+                .help("Accept HTTP/2 cleartext connections, `--http2 false` turns it off")
+                .action(ArgAction::Set)
+                .num_args(0..=1)
+                .default_missing_value("true")
+                .value_parser(value_parser!(bool)))
+            .arg(Arg::new("watch")
+                .short('w')
+                .long("watch")
+                .help("Reload the schema when its files change")
+                .action(ArgAction::SetTrue))
+            .arg(Arg::new("admin")
+                .long("admin")
+                .help("Mount the built admin dashboard at this path, the app must configure its guard")
+                .action(ArgAction::Set)
+                .num_args(0..=1)
+                .default_missing_value("/_admin"))
+            .arg(Arg::new("strict-migrations")
+                .long("strict-migrations")
+                .help("Refuse to start when versioned migrations are pending")
                 .action(ArgAction::SetTrue)))
         .subcommand(ClapCommand::new("generate")
             .about("Generate code")
@@ -95,7 +164,32 @@ pub(crate) fn parse(runtime_version: RuntimeVersion, entrance: Entrance, argv: O
                 .short('d')
                 .long("dry")
                 .help("Dry run")
-                .action(ArgAction::SetTrue)))
+                .action(ArgAction::SetTrue))
+            .subcommand(ClapCommand::new("create")
+                .about("Create a versioned migration from the differences between the database and the schema")
+                .arg(Arg::new("NAME")
+                    .help("Name of the migration")
+                    .required(true)
+                    .num_args(1)))
+            .subcommand(ClapCommand::new("up")
+                .about("Apply pending versioned migrations")
+                .arg(Arg::new("to")
+                    .long("to")
+                    .help("Stop after this version")
+                    .action(ArgAction::Set)
+                    .num_args(1)))
+            .subcommand(ClapCommand::new("down")
+                .about("Revert applied versioned migrations")
+                .arg(Arg::new("steps")
+                    .short('n')
+                    .long("steps")
+                    .help("Number of migrations to revert")
+                    .action(ArgAction::Set)
+                    .num_args(1)
+                    .default_value("1")
+                    .value_parser(value_parser!(usize))))
+            .subcommand(ClapCommand::new("status")
+                .about("Show applied and pending versioned migrations")))
         .subcommand(ClapCommand::new("seed")
             .about("Seed data")
             .arg(Arg::new("unseed")
@@ -120,9 +214,59 @@ pub(crate) fn parse(runtime_version: RuntimeVersion, entrance: Entrance, argv: O
                 .help("Data set names to process")
                 .num_args(1..)))
         .subcommand(ClapCommand::new("purge")
-            .about("Purge and clear the database without dropping tables."))
+            .about("Purge and clear the database without dropping tables.")
+            .arg(Arg::new("namespace")
+                .short('n')
+                .long("namespace")
+                .help("Only purge models of this namespace")
+                .action(ArgAction::Set)
+                .num_args(1))
+            .arg(Arg::new("model")
+                .short('m')
+                .long("model")
+                .help("Only purge this model, can be repeated")
+                .action(ArgAction::Append)
+                .num_args(1))
+            .arg(Arg::new("yes")
+                .short('y')
+                .long("yes")
+                .help("Don't ask for confirmation")
+                .action(ArgAction::SetTrue))
+            .arg(Arg::new("force")
+                .long("force")
+                .help("Purge even if the environment is marked production")
+                .action(ArgAction::SetTrue))
+            .arg(Arg::new("snapshot")
+                .long("snapshot")
+                .help("Dump the purged records into this file first")
+                .action(ArgAction::Set)
+                .num_args(1)))
         .subcommand(ClapCommand::new("lint")
-            .about("Lint the schema files"))
+            .about("Lint the schema files")
+            .arg(Arg::new("format")
+                .short('f')
+                .long("format")
+                .help("Output format")
+                .action(ArgAction::Set)
+                .num_args(1)
+                .default_value("text")
+                .value_parser(["text", "json", "sarif"]))
+            .arg(Arg::new("config")
+                .short('c')
+                .long("config")
+                .help("Lint config file, defaults to teo-lint.json if it exists")
+                .action(ArgAction::Set)
+                .num_args(1))
+            .arg(Arg::new("deny-warnings")
+                .long("deny-warnings")
+                .help("Exit with an error on warnings too")
+                .action(ArgAction::SetTrue)))
+        .subcommand(ClapCommand::new("fmt")
+            .about("Format the schema files")
+            .arg(Arg::new("check")
+                .long("check")
+                .help("Fail when schema files are not formatted instead of formatting them")
+                .action(ArgAction::SetTrue)))
         .subcommand(ClapCommand::new("run")
             .about("Run a defined program")
             .arg(Arg::new("list")
@@ -135,7 +279,247 @@ pub(crate) fn parse(runtime_version: RuntimeVersion, entrance: Entrance, argv: O
                 .required(true)
                 .action(ArgAction::Append)
                 .help("Program name to run")
+                .num_args(1))
+            .arg(Arg::new("ARGS")
+                .action(ArgAction::Append)
+                .help("Arguments passed to the program, see `run <NAME> --help`")
+                .num_args(0..)
+                .trailing_var_arg(true)
+                .allow_hyphen_values(true)))
+        .subcommand(ClapCommand::new("webhooks")
+            .about("Inspect and replay webhook deliveries")
+            .arg_required_else_help(true)
+            .subcommand(ClapCommand::new("list")
+                .about("List webhook deliveries")
+                .arg(Arg::new("status")
+                    .long("status")
+                    .help("Only list deliveries with this status")
+                    .action(ArgAction::Set)
+                    .num_args(1)
+                    .value_parser(["pending", "delivered", "dead"]))
+                .arg(Arg::new("limit")
+                    .short('n')
+                    .long("limit")
+                    .help("Maximum number of deliveries to list")
+                    .action(ArgAction::Set)
+                    .num_args(1)
+                    .default_value("20")
+                    .value_parser(value_parser!(usize))))
+            .subcommand(ClapCommand::new("replay")
+                .about("Send webhook deliveries again")
+                .arg_required_else_help(true)
+                .arg(Arg::new("dead")
+                    .long("dead")
+                    .help("Replay all dead-lettered deliveries")
+                    .action(ArgAction::SetTrue)
+                    .conflicts_with("ID"))
+                .arg(Arg::new("ID")
+                    .action(ArgAction::Append)
+                    .conflicts_with("dead")
+                    .help("Delivery ids to replay")
+                    .num_args(1..))))
+        .subcommand(ClapCommand::new("audit")
+            .about("Query the audit log")
+            .arg(Arg::new("model")
+                .short('m')
+                .long("model")
+                .help("Only show changes of this model")
+                .action(ArgAction::Set)
+                .num_args(1))
+            .arg(Arg::new("identifier")
+                .long("identifier")
+                .help("Only show changes of records whose identifier contains this")
+                .action(ArgAction::Set)
+                .num_args(1))
+            .arg(Arg::new("identity")
+                .long("identity")
+                .help("Only show changes made by identities containing this")
+                .action(ArgAction::Set)
+                .num_args(1))
+            .arg(Arg::new("request-id")
+                .long("request-id")
+                .help("Only show changes made by this request")
+                .action(ArgAction::Set)
+                .num_args(1))
+            .arg(Arg::new("action")
+                .long("action")
+                .help("Only show this kind of changes")
+                .action(ArgAction::Set)
+                .num_args(1)
+                .value_parser(["create", "update", "delete"]))
+            .arg(Arg::new("since")
+                .long("since")
+                .help("Only show changes since this RFC 3339 time")
+                .action(ArgAction::Set)
+                .num_args(1))
+            .arg(Arg::new("limit")
+                .short('n')
+                .long("limit")
+                .help("Maximum number of entries to show")
+                .action(ArgAction::Set)
+                .num_args(1)
+                .default_value("50")
+                .value_parser(value_parser!(usize))))
+        .subcommand(ClapCommand::new("db")
+            .about("Work with the database")
+            .arg_required_else_help(true)
+            .subcommand(ClapCommand::new("pull")
+                .about("Generate models from the tables of the connected database")
+                .arg(Arg::new("out")
+                    .short('o')
+                    .long("out")
+                    .help("Write models into this file instead of printing them")
+                    .action(ArgAction::Set)
+                    .num_args(1))))
+        .subcommand(ClapCommand::new("dump")
+            .about("Export records into a portable NDJSON archive")
+            .arg(Arg::new("out")
+                .short('o')
+                .long("out")
+                .help("Write the archive into this file instead of printing it")
+                .action(ArgAction::Set)
+                .num_args(1))
+            .arg(Arg::new("namespace")
+                .short('n')
+                .long("namespace")
+                .help("Only export models of this namespace")
+                .action(ArgAction::Set)
+                .num_args(1))
+            .arg(Arg::new("model")
+                .short('m')
+                .long("model")
+                .help("Only export this model, can be repeated")
+                .action(ArgAction::Append)
+                .num_args(1)))
+        .subcommand(ClapCommand::new("restore")
+            .about("Import records from an archive created by `dump`")
+            .arg_required_else_help(true)
+            .arg(Arg::new("FILE")
+                .required(true)
+                .help("The archive to import")
+                .action(ArgAction::Set)
+                .num_args(1))
+            .arg(Arg::new("purge")
+                .long("purge")
+                .help("Purge the database before importing")
+                .action(ArgAction::SetTrue))
+            .arg(Arg::new("force")
+                .long("force")
+                .help("Purge even if the environment is marked production")
+                .action(ArgAction::SetTrue)
+                .requires("purge")))
+        .subcommand(ClapCommand::new("console")
+            .about("Call actions and handlers interactively")
+            .arg(Arg::new("readonly")
+                .long("readonly")
+                .help("Only allow find, count, aggregate and groupBy actions")
+                .action(ArgAction::SetTrue)))
+        .subcommand(ClapCommand::new("query")
+            .about("Call an action or a handler once and print the response")
+            .arg_required_else_help(true)
+            .arg(Arg::new("TARGET")
+                .required(true)
+                .help("The action or handler, e.g. User.updateMany")
+                .action(ArgAction::Set)
+                .num_args(1))
+            .arg(Arg::new("BODY")
+                .required(false)
+                .help("The request body as JSON, @file to read a file or - to read stdin")
+                .action(ArgAction::Set)
+                .num_args(1))
+            .arg(Arg::new("method")
+                .short('X')
+                .long("method")
+                .help("The http method of a custom handler")
+                .action(ArgAction::Set)
+                .num_args(1)
+                .default_value("POST"))
+            .arg(Arg::new("token")
+                .short('t')
+                .long("token")
+                .help("Call as the identity of this token")
+                .action(ArgAction::Set)
+                .num_args(1))
+            .arg(Arg::new("as")
+                .long("as")
+                .help("Call as this record without a token, e.g. 'User:1'")
+                .action(ArgAction::Set)
+                .num_args(1)
+                .conflicts_with("token"))
+            .arg(Arg::new("header")
+                .short('H')
+                .long("header")
+                .help("An extra request header, e.g. 'X-Tenant: acme'")
+                .action(ArgAction::Append)
                 .num_args(1)))
+        .subcommand(ClapCommand::new("new")
+            .about("Create a new project")
+            .arg_required_else_help(true)
+            .arg(Arg::new("DIR")
+                .required(true)
+                .help("The directory to create the project in")
+                .action(ArgAction::Set)
+                .num_args(1))
+            .arg(Arg::new("template")
+                .short('t')
+                .long("template")
+                .help("Rust app with a main function, or a schema only project")
+                .action(ArgAction::Set)
+                .num_args(1)
+                .value_parser(["rust", "schema"]))
+            .arg(Arg::new("connector")
+                .short('c')
+                .long("connector")
+                .help("The database to connect to")
+                .action(ArgAction::Set)
+                .num_args(1)
+                .value_parser(["sqlite", "mysql", "postgres", "mongo"]))
+            .arg(Arg::new("yes")
+                .short('y')
+                .long("yes")
+                .help("Don't ask, use defaults for options which are not passed")
+                .action(ArgAction::SetTrue)))
+        .subcommand(ClapCommand::new("schema")
+            .about("Work with schema versions")
+            .arg_required_else_help(true)
+            .subcommand(ClapCommand::new("diff")
+                .about("Compare two schemas and report breaking and destructive changes")
+                .arg_required_else_help(true)
+                .arg(Arg::new("OLD")
+                    .required(true)
+                    .help("The old schema file, directory or git revision")
+                    .action(ArgAction::Set)
+                    .num_args(1))
+                .arg(Arg::new("NEW")
+                    .required(false)
+                    .help("The new schema file, directory or git revision, defaults to the loaded schema")
+                    .action(ArgAction::Set)
+                    .num_args(1))
+                .arg(Arg::new("fail-on-breaking")
+                    .long("fail-on-breaking")
+                    .help("Exit with an error on breaking changes")
+                    .action(ArgAction::SetTrue))))
+        .subcommand(ClapCommand::new("config")
+            .about("Inspect the configuration")
+            .arg_required_else_help(true)
+            .subcommand(ClapCommand::new("print")
+                .about("Print the effective configuration of the environment")))
+        .subcommand(ClapCommand::new("doctor")
+            .about("Check the schema, environment, databases and ports"))
+        .subcommand(ClapCommand::new("routes")
+            .about("List the endpoints of the server")
+            .subcommand(ClapCommand::new("match")
+                .about("Explain which handler a request hits")
+                .arg(Arg::new("METHOD")
+                    .required(true)
+                    .help("The HTTP method, e.g. POST")
+                    .action(ArgAction::Set)
+                    .num_args(1))
+                .arg(Arg::new("PATH")
+                    .required(true)
+                    .help("The request path, e.g. /users/findMany")
+                    .action(ArgAction::Set)
+                    .num_args(1))))
         .get_matches_from(match runtime_version {
             RuntimeVersion::Python(_) | RuntimeVersion::NodeJS(_) => {
                 let result = argv.iter().enumerate().filter(|(i, x)| (*i != 1) && !x.as_str().ends_with(".ts")).map(|(_i, x)| x.clone()).collect::<Vec<String>>();
@@ -147,10 +531,23 @@ pub(crate) fn parse(runtime_version: RuntimeVersion, entrance: Entrance, argv: O
         });
     let silent: bool = matches.get_flag("silent");
     let schema: Option<&String> = matches.get_one("SCHEMA_FILE");
+    let output = match matches.get_one::<String>("OUTPUT_FORMAT").map(|s| s.as_str()) {
+        Some("json") => OutputFormat::Json,
+        _ => OutputFormat::Text,
+    };
+    let env = matches.get_one::<String>("ENV").cloned().or_else(|| env::var("TEO_ENV").ok().filter(|e| !e.is_empty()));
     let command = match matches.subcommand() {
         Some(("serve", submatches)) => {
-            let env: Option<&String> = submatches.get_one("ENV");
-            CLICommand::Serve(ServeCommand { no_migration: submatches.get_flag("no-migration"), no_autoseed: submatches.get_flag("no-autoseed"), env: env.cloned() })
+            let options = ServerOptions {
+                workers: submatches.get_one::<usize>("workers").cloned(),
+                backlog: submatches.get_one::<u32>("backlog").cloned(),
+                max_connections: submatches.get_one::<usize>("max-connections").cloned(),
+                keep_alive: submatches.get_one::<u64>("keep-alive").map(|s| Duration::from_secs(*s)),
+                client_request_timeout: submatches.get_one::<u64>("client-request-timeout").map(|m| Duration::from_millis(*m)),
+                client_disconnect_timeout: submatches.get_one::<u64>("client-disconnect-timeout").map(|m| Duration::from_millis(*m)),
+                http2: submatches.get_one::<bool>("http2").cloned(),
+            };
+            CLICommand::Serve(ServeCommand { no_migration: submatches.get_flag("no-migration"), no_autoseed: submatches.get_flag("no-autoseed"), options, watch: submatches.get_flag("watch"), admin: submatches.get_one::<String>("admin").cloned(), strict_migrations: submatches.get_flag("strict-migrations") })
         }
         Some(("generate", submatches)) => {
             match submatches.subcommand() {
@@ -169,7 +566,14 @@ pub(crate) fn parse(runtime_version: RuntimeVersion, entrance: Entrance, argv: O
             }
         }
         Some(("migrate", submatches)) => {
-            CLICommand::Migrate(MigrateCommand { dry: submatches.get_flag("dry") })
+            let action = match submatches.subcommand() {
+                Some(("create", submatches)) => MigrateCommandAction::Create(submatches.get_one::<String>("NAME").cloned().unwrap()),
+                Some(("up", submatches)) => MigrateCommandAction::Up(submatches.get_one::<String>("to").cloned()),
+                Some(("down", submatches)) => MigrateCommandAction::Down(submatches.get_one::<usize>("steps").cloned().unwrap_or(1)),
+                Some(("status", _)) => MigrateCommandAction::Status,
+                _ => MigrateCommandAction::Diff,
+            };
+            CLICommand::Migrate(MigrateCommand { dry: submatches.get_flag("dry"), action })
         }
         Some(("seed", submatches)) => {
             let action = if submatches.get_flag("reseed") {
@@ -186,20 +590,154 @@ pub(crate) fn parse(runtime_version: RuntimeVersion, entrance: Entrance, argv: O
                 names,
             })
         }
-        Some(("purge", _submatches)) => {
-            CLICommand::Purge(PurgeCommand { })
+        Some(("purge", submatches)) => {
+            CLICommand::Purge(PurgeCommand {
+                namespace: submatches.get_one::<String>("namespace").cloned(),
+                models: submatches.get_many::<String>("model").map(|s| s.map(|v| v.to_string()).collect::<Vec<String>>()),
+                yes: submatches.get_flag("yes"),
+                force: submatches.get_flag("force"),
+                snapshot: submatches.get_one::<String>("snapshot").cloned(),
+            })
         }
-        Some(("lint", _submatches)) => {
-            CLICommand::Lint(LintCommand { })
+        Some(("lint", submatches)) => {
+            let format = match submatches.get_one::<String>("format").map(|s| s.as_str()) {
+                Some("json") => LintFormat::Json,
+                Some("sarif") => LintFormat::Sarif,
+                // `--output json` applies unless a format is passed
+                _ if output == OutputFormat::Json && submatches.value_source("format") == Some(ValueSource::DefaultValue) => LintFormat::Json,
+                _ => LintFormat::Text,
+            };
+            CLICommand::Lint(LintCommand {
+                format,
+                config: submatches.get_one::<String>("config").cloned(),
+                deny_warnings: submatches.get_flag("deny-warnings"),
+            })
+        }
+        Some(("fmt", submatches)) => {
+            CLICommand::Fmt(FmtCommand { check: submatches.get_flag("check") })
         }
         Some(("run", submatches)) => {
             let name: Option<String> = submatches.get_one::<String>("NAME").map(|s| s.clone());
+            let args: Vec<String> = submatches.get_many::<String>("ARGS").map(|s| s.map(|v| v.to_string()).collect::<Vec<String>>()).unwrap_or_default();
             CLICommand::Run(RunCommand {
                 list: submatches.get_flag("list"),
                 name,
+                args,
+            })
+        }
+        Some(("webhooks", submatches)) => {
+            match submatches.subcommand() {
+                Some(("list", submatches)) => {
+                    CLICommand::Webhooks(WebhooksCommand::List(WebhooksListCommand {
+                        status: submatches.get_one::<String>("status").cloned(),
+                        limit: submatches.get_one::<usize>("limit").cloned().unwrap_or(20),
+                    }))
+                }
+                Some(("replay", submatches)) => {
+                    let ids: Option<Vec<String>> = submatches.get_many::<String>("ID").map(|s| s.map(|v| v.to_string()).collect::<Vec<String>>());
+                    CLICommand::Webhooks(WebhooksCommand::Replay(WebhooksReplayCommand {
+                        ids,
+                        dead: submatches.get_flag("dead"),
+                    }))
+                }
+                _ => unreachable!()
+            }
+        }
+        Some(("audit", submatches)) => {
+            CLICommand::Audit(AuditCommand {
+                model: submatches.get_one::<String>("model").cloned(),
+                identifier: submatches.get_one::<String>("identifier").cloned(),
+                identity: submatches.get_one::<String>("identity").cloned(),
+                request_id: submatches.get_one::<String>("request-id").cloned(),
+                action: submatches.get_one::<String>("action").cloned(),
+                since: submatches.get_one::<String>("since").cloned(),
+                limit: submatches.get_one::<usize>("limit").cloned().unwrap_or(50),
+            })
+        }
+        Some(("db", submatches)) => {
+            match submatches.subcommand() {
+                Some(("pull", submatches)) => CLICommand::Db(DbCommand::Pull(DbPullCommand {
+                    out: submatches.get_one::<String>("out").cloned(),
+                })),
+                _ => unreachable!()
+            }
+        }
+        Some(("dump", submatches)) => {
+            let models: Option<Vec<String>> = submatches.get_many::<String>("model").map(|s| s.map(|v| v.to_string()).collect::<Vec<String>>());
+            CLICommand::Dump(DumpCommand {
+                out: submatches.get_one::<String>("out").cloned(),
+                namespace: submatches.get_one::<String>("namespace").cloned(),
+                models,
             })
         }
+        Some(("restore", submatches)) => {
+            CLICommand::Restore(RestoreCommand {
+                input: submatches.get_one::<String>("FILE").cloned().unwrap(),
+                purge: submatches.get_flag("purge"),
+                force: submatches.get_flag("force"),
+            })
+        }
+        Some(("console", submatches)) => {
+            CLICommand::Console(ConsoleCommand { readonly: submatches.get_flag("readonly") })
+        }
+        Some(("query", submatches)) => {
+            CLICommand::Query(QueryCommand {
+                target: submatches.get_one::<String>("TARGET").cloned().unwrap(),
+                body: submatches.get_one::<String>("BODY").cloned(),
+                method: submatches.get_one::<String>("method").cloned().unwrap(),
+                token: submatches.get_one::<String>("token").cloned(),
+                identity: submatches.get_one::<String>("as").cloned(),
+                headers: submatches.get_many::<String>("header").map(|h| h.cloned().collect()).unwrap_or_default(),
+            })
+        }
+        Some(("new", submatches)) => {
+            let template = submatches.get_one::<String>("template").map(|t| match t.as_str() {
+                "schema" => NewTemplate::Schema,
+                _ => NewTemplate::Rust,
+            });
+            let connector = submatches.get_one::<String>("connector").map(|c| match c.as_str() {
+                "mysql" => NewConnector::MySQL,
+                "postgres" => NewConnector::PostgreSQL,
+                "mongo" => NewConnector::MongoDB,
+                _ => NewConnector::SQLite,
+            });
+            CLICommand::New(NewCommand {
+                dir: submatches.get_one::<String>("DIR").cloned().unwrap(),
+                template,
+                connector,
+                yes: submatches.get_flag("yes"),
+            })
+        }
+        Some(("schema", submatches)) => {
+            match submatches.subcommand() {
+                Some(("diff", submatches)) => CLICommand::Schema(SchemaCommand::Diff(SchemaDiffCommand {
+                    old: submatches.get_one::<String>("OLD").cloned().unwrap(),
+                    new: submatches.get_one::<String>("NEW").cloned(),
+                    fail_on_breaking: submatches.get_flag("fail-on-breaking"),
+                })),
+                _ => unreachable!()
+            }
+        }
+        Some(("config", submatches)) => {
+            match submatches.subcommand() {
+                Some(("print", _)) => CLICommand::Config(ConfigCommand::Print),
+                _ => unreachable!()
+            }
+        }
+        Some(("doctor", _submatches)) => {
+            CLICommand::Doctor(DoctorCommand { })
+        }
+        Some(("routes", submatches)) => {
+            let r#match = match submatches.subcommand() {
+                Some(("match", submatches)) => Some(RoutesMatchCommand {
+                    method: submatches.get_one::<String>("METHOD").cloned().unwrap(),
+                    path: submatches.get_one::<String>("PATH").cloned().unwrap(),
+                }),
+                _ => None,
+            };
+            CLICommand::Routes(RoutesCommand { r#match })
+        }
         _ => unreachable!()
     };
-    CLI { command, schema: schema.map(|s| s.to_string()), silent }
+    CLI { command, schema: schema.map(|s| s.to_string()), env, output, silent }
 }
\ No newline at end of file
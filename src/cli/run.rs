@@ -1,23 +1,86 @@
+use std::process::exit;
+use std::time::Instant;
+use serde_json::json;
 use teo_parser::diagnostics::diagnostics::Diagnostics;
 use teo_result::{Error, Result};
 use crate::app::ctx::Ctx;
 use crate::app::database::connect_databases;
-use crate::cli::command::{CLI, CLICommand, GenerateCommand, SeedCommandAction};
+use crate::cli::command::{CLI, CLICommand, ConfigCommand, DbCommand, GenerateCommand, MigrateCommandAction, SchemaCommand, SeedCommandAction};
 use crate::server::make::serve;
 use teo_runtime::connection::transaction;
 use teo_runtime::schema::load::load_data_sets::load_data_sets;
 use crate::migrate::migrate;
-use crate::purge::purge;
+use crate::migrate::versioned;
+use crate::message::{event, info_message, json_output};
+use crate::cli::exit_code;
+use crate::purge;
+use crate::stdlib;
 use crate::seeder::seed::seed;
+use crate::webhook;
+use crate::schedule;
+use crate::webhook::command::webhooks;
+use crate::audit;
+use crate::introspect;
+use crate::lint::lint;
+use crate::fmt::fmt;
+use crate::dump::{dump, restore};
+use crate::console::console;
+use crate::routes::routes;
+use crate::doctor::doctor;
+use crate::scaffold::new;
+use crate::profile;
+use crate::query::query;
+use crate::diff::schema_diff;
+use crate::cli::entrance::Entrance;
+use crate::server::watch;
 
 pub async fn run(cli: &CLI) -> Result<()> {
+    let start = Instant::now();
+    let result = run_command(cli).await;
+    done_event(cli.command.name(), start, result.as_ref().err());
+    exit_on_json_error(result.as_ref().err());
+    result
+}
+
+/// The last event of a command with `--output json`. The error is returned
+/// to `main` afterwards, which exits with [`exit_code::FAILURE`], see
+/// [`exit_on_json_error`].
+pub(crate) fn done_event(command: &str, start: Instant, error: Option<&Error>) {
+    let ms = start.elapsed().as_millis() as u64;
+    match error {
+        None => event("done", json!({ "command": command, "result": "success", "ms": ms, "exit": exit_code::SUCCESS })),
+        Some(error) => event("done", json!({ "command": command, "result": "failure", "ms": ms, "message": format!("{}", error), "code": error.code, "exit": exit_code::FAILURE })),
+    }
+}
+
+/// `main` prints a returned error as plain text, with `--output json` a
+/// failed command exits here instead, after its `done` event.
+pub(crate) fn exit_on_json_error(error: Option<&Error>) {
+    if error.is_some() && json_output() {
+        exit(exit_code::FAILURE);
+    }
+}
+
+async fn run_command(cli: &CLI) -> Result<()> {
     match &cli.command {
         CLICommand::Serve(serve_command) => {
             connect_databases(Ctx::main_namespace_mut(), cli.silent).await?;
+            stdlib::check_models(Ctx::main_namespace())?;
             let conn_ctx = Ctx::conn_ctx();
             // migrate
             if !serve_command.no_migration {
-                migrate(false, false, cli.silent).await?;
+                if versioned::is_versioned() {
+                    let pending = versioned::pending().await?;
+                    if pending > 0 {
+                        if serve_command.strict_migrations {
+                            Err(Error::new(format!("{} versioned migration(s) are pending, run `migrate up` first", pending)))?
+                        } else if !cli.silent {
+                            info_message(format!("{} versioned migration(s) are pending, run `migrate up` to apply them", pending));
+                        }
+                    }
+                } else {
+                    migrate(false, false, cli.silent).await?;
+                }
             }
             // seed auto seed data sets
             if !serve_command.no_autoseed {
@@ -25,7 +88,7 @@ pub async fn run(cli: &CLI) -> Result<()> {
                     let mut diagnostics = Diagnostics::new();
                     let data_sets = load_data_sets(Ctx::main_namespace(), None, false, Ctx::schema(), &mut diagnostics)?;
                     let transaction_ctx = transaction::Ctx::new(Ctx::conn_ctx().clone());
-                    seed(SeedCommandAction::Seed, data_sets, transaction_ctx, false).await?;
+                    seed(SeedCommandAction::Seed, data_sets, transaction_ctx).await?;
                 }
             }
             // setup
@@ -33,8 +96,32 @@ pub async fn run(cli: &CLI) -> Result<()> {
                 let transaction_ctx = transaction::Ctx::new(Ctx::conn_ctx().clone());
                 setup.call(transaction_ctx).await?;
             }
+            // deliver webhooks
+            if !Ctx::webhooks().is_empty() {
+                webhook::worker::spawn(cli.silent);
+            }
+            // run scheduled programs
+            if !Ctx::schedules().is_empty() {
+                schedule::worker::spawn(cli.silent);
+            }
+            // admin dashboard
+            if let Some(path) = &serve_command.admin {
+                let admin_dashboard = Ctx::admin_dashboard().cloned().unwrap_or_default().path(path);
+                Ctx::set_admin_dashboard(admin_dashboard);
+            }
+            if let Some(admin_dashboard) = Ctx::admin_dashboard_mut() {
+                admin_dashboard.resolve(Ctx::main_namespace())?;
+            }
+            // reload schema on change
+            if serve_command.watch {
+                if let Entrance::APP = Ctx::get().entrance {
+                    Err(Error::new("--watch requires the CLI, callbacks registered by an app can't be reloaded"))?
+                }
+                watch::spawn(cli.silent);
+            }
             // start server
-            serve(conn_ctx.namespace(), conn_ctx.namespace().server.as_ref().unwrap(), &Ctx::get().runtime_version, &Ctx::get().entrance, cli.silent).await
+            let options = Ctx::server_options().merge(&serve_command.options);
+            serve(conn_ctx.namespace(), conn_ctx.namespace().server.as_ref().unwrap(), options, &Ctx::get().runtime_version, &Ctx::get().entrance, cli.silent).await
         }
         CLICommand::Generate(generate_command) => {
             match generate_command {
@@ -46,13 +133,15 @@ pub async fn run(cli: &CLI) -> Result<()> {
                     } else {
                         match Ctx::main_namespace().clients.len() {
                             0 => Err(Error::new("no clients found"))?,
-                            1 => return teo_generator::client::generate(Ctx::main_namespace(), Ctx::main_namespace().clients.first_key_value().unwrap().1).await,
+                            1 => vec![Ctx::main_namespace().clients.first_key_value().unwrap().0.clone()],
                             _ => Err(Error::new("requires client name"))?,
                         }
                     };
                     for name in names {
                         if let Some(client) = Ctx::main_namespace().clients.get(&name) {
+                            let start = Instant::now();
                             teo_generator::client::generate(Ctx::main_namespace(), client).await?;
+                            event("generate", json!({ "kind": "client", "name": name, "dest": client.dest, "ms": start.elapsed().as_millis() as u64 }));
                         } else {
                             Err(Error::new("client not found"))?
                         }
@@ -67,13 +156,15 @@ pub async fn run(cli: &CLI) -> Result<()> {
                     } else {
                         match Ctx::main_namespace().entities.len() {
                             0 => Err(Error::new("no entities found"))?,
-                            1 => return teo_generator::entity::generate(Ctx::main_namespace(), Ctx::main_namespace().entities.first_key_value().unwrap().1).await,
+                            1 => vec![Ctx::main_namespace().entities.first_key_value().unwrap().0.clone()],
                             _ => Err(Error::new("requires entity name"))?,
                         }
                     };
                     for name in names {
                         if let Some(entity) = Ctx::main_namespace().entities.get(&name) {
+                            let start = Instant::now();
                             teo_generator::entity::generate(Ctx::main_namespace(), entity).await?;
+                            event("generate", json!({ "kind": "entity", "name": name, "dest": entity.dest, "ms": start.elapsed().as_millis() as u64 }));
                         } else {
                             Err(Error::new("entity not found"))?
                         }
@@ -82,7 +173,9 @@ pub async fn run(cli: &CLI) -> Result<()> {
                 }
                 GenerateCommand::GenerateAdminCommand(_) => {
                     if let Some(admin) = &Ctx::main_namespace().admin {
+                        let start = Instant::now();
                         teo_generator::admin::generate(Ctx::main_namespace(), admin, Ctx::main_namespace().server.as_ref().unwrap()).await?;
+                        event("generate", json!({ "kind": "admin", "dest": admin.dest, "ms": start.elapsed().as_millis() as u64 }));
                     }
                     Ok(())
                 }
@@ -90,7 +183,13 @@ pub async fn run(cli: &CLI) -> Result<()> {
         }
         CLICommand::Migrate(migrate_command) => {
             connect_databases(Ctx::main_namespace_mut(), cli.silent).await?;
-            migrate(migrate_command.dry, false, cli.silent).await?;
+            match &migrate_command.action {
+                MigrateCommandAction::Diff => migrate(migrate_command.dry, false, cli.silent).await?,
+                MigrateCommandAction::Create(name) => versioned::create(name).await?,
+                MigrateCommandAction::Up(to) => versioned::up(to.as_ref().map(|t| t.as_str()), cli.silent).await?,
+                MigrateCommandAction::Down(steps) => versioned::down(*steps, cli.silent).await?,
+                MigrateCommandAction::Status => versioned::status().await?,
+            }
             Ok(())
         }
         CLICommand::Seed(seed_command) => {
@@ -98,44 +197,95 @@ pub async fn run(cli: &CLI) -> Result<()> {
             let mut diagnostics = Diagnostics::new();
             let data_sets = load_data_sets(Ctx::main_namespace(), seed_command.names.as_ref(), seed_command.all, Ctx::schema(), &mut diagnostics)?;
             let transaction_ctx = transaction::Ctx::new(Ctx::conn_ctx().clone());
-            seed(seed_command.action, data_sets, transaction_ctx, true).await?;
+            seed(seed_command.action, data_sets, transaction_ctx).await?;
             Ok(())
         }
         CLICommand::Purge(purge_command) => {
             connect_databases(Ctx::main_namespace_mut(), cli.silent).await?;
-            purge().await?;
-            Ok(())
+            purge::purge_command(purge_command, cli.silent).await
         }
-        CLICommand::Lint(lint_command) => Ok(()),
+        CLICommand::Lint(lint_command) => lint(lint_command),
+        CLICommand::Fmt(fmt_command) => fmt(fmt_command),
+        CLICommand::Routes(routes_command) => routes(routes_command),
+        CLICommand::Doctor(_) => doctor().await,
+        CLICommand::New(new_command) => new(new_command),
+        CLICommand::Config(ConfigCommand::Print) => profile::print(cli.env()),
+        CLICommand::Schema(SchemaCommand::Diff(diff_command)) => schema_diff(diff_command).await,
         CLICommand::Run(run_command) => {
-            if run_command.list {
-                println!("+-{:<32}-+-{:<64}-+", "--------------------------------", "----------------------------------------------------------------");
-                println!("| {:^32} | {:^64} |", "Name", "Description");
-                println!("+-{:<32}-+-{:<64}-+", "--------------------------------", "----------------------------------------------------------------");
+            if run_command.list && json_output() {
+                let programs: Vec<serde_json::Value> = Ctx::get().programs.iter().map(|(name, program)| json!({
+                    "name": name,
+                    "arguments": program.signature.usage(),
+                    "description": program.desc,
+                })).collect();
+                event("programs", json!({ "programs": programs }));
+            } else if run_command.list {
+                println!("+-{:<32}-+-{:<40}-+-{:<64}-+", "--------------------------------", "----------------------------------------", "----------------------------------------------------------------");
+                println!("| {:^32} | {:^40} | {:^64} |", "Name", "Arguments", "Description");
+                println!("+-{:<32}-+-{:<40}-+-{:<64}-+", "--------------------------------", "----------------------------------------", "----------------------------------------------------------------");
                 if Ctx::get().programs.is_empty() {
-                    println!("| {:^99} |", "No programs.");
+                    println!("| {:^142} |", "No programs.");
                 } else {
                     for (name, program) in &Ctx::get().programs {
                         let desc_str = program.desc.as_ref().map_or_else(
                             || "(No description)".to_string(),
                             |desc| desc.into()
                         );
-                        println!("| {:<32} | {:<64} |", name, desc_str);
+                        println!("| {:<32} | {:<40} | {:<64} |", name, program.signature.usage(), desc_str);
                     }
                 }
-                println!("+-{:<32}---{:<64}-+", "--------------------------------", "----------------------------------------------------------------");
+                println!("+-{:<32}---{:<40}---{:<64}-+", "--------------------------------", "----------------------------------------", "----------------------------------------------------------------");
             } else {
                 if let Some(name) = &run_command.name {
+                    let program = Ctx::get().programs.get(name).ok_or_else(|| Error::new(format!("Program '{}' is not defined", name)))?;
+                    // parse before connecting, so that `--help` and invalid arguments exit early
+                    let args = program.signature.parse(name, program.desc.as_ref(), &run_command.args);
                     connect_databases(Ctx::main_namespace_mut(), cli.silent).await?;
-                    let program = Ctx::get_mut().programs.get(name).ok_or_else(|| Error::new(format!("Program '{}' is not defined", name)))?;
                     let transaction_ctx = transaction::Ctx::new(Ctx::conn_ctx().clone());
-                    program.func.call(transaction_ctx).await?;
-                    std::process::exit(0);
+                    let start = Instant::now();
+                    program.func.call(transaction_ctx, args).await?;
+                    event("program", json!({ "name": name, "ms": start.elapsed().as_millis() as u64 }));
                 } else {
                     return Err(Error::new("No program name provided"));
                 }
             }
             Ok(())
         },
+        CLICommand::Webhooks(webhooks_command) => {
+            connect_databases(Ctx::main_namespace_mut(), cli.silent).await?;
+            webhook::check_model(Ctx::main_namespace())?;
+            webhooks(webhooks_command).await
+        }
+        CLICommand::Db(db_command) => {
+            connect_databases(Ctx::main_namespace_mut(), true).await?;
+            match db_command {
+                DbCommand::Pull(pull_command) => introspect::pull(pull_command).await,
+            }
+        }
+        CLICommand::Audit(audit_command) => {
+            connect_databases(Ctx::main_namespace_mut(), cli.silent).await?;
+            audit::check_model(Ctx::main_namespace())?;
+            audit::command::audit(audit_command).await
+        }
+        CLICommand::Dump(dump_command) => {
+            // the archive may be printed, keep connection messages out of it
+            connect_databases(Ctx::main_namespace_mut(), cli.silent || dump_command.out.is_none()).await?;
+            dump(dump_command, cli.silent).await
+        }
+        CLICommand::Restore(restore_command) => {
+            connect_databases(Ctx::main_namespace_mut(), cli.silent).await?;
+            restore(restore_command, cli.silent).await
+        }
+        CLICommand::Query(query_command) => {
+            // keep connection messages out of the printed response
+            connect_databases(Ctx::main_namespace_mut(), true).await?;
+            stdlib::check_models(Ctx::main_namespace())?;
+            query(query_command).await
+        }
+        CLICommand::Console(console_command) => {
+            connect_databases(Ctx::main_namespace_mut(), cli.silent).await?;
+            stdlib::check_models(Ctx::main_namespace())?;
+            console(console_command).await
+        }
     }
 }
\ No newline at end of file
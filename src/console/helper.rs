@@ -0,0 +1,91 @@
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+use teo_runtime::namespace::Namespace;
+use teo_runtime::traits::named::Named;
+use crate::console::{BUILTIN_ACTIONS, COMMANDS};
+
+const QUERY_KEYS: [&str; 14] = ["where", "include", "select", "orderBy", "take", "skip", "cursor", "distinct", "create", "update", "by", "having", "_count", "_sum"];
+
+/// Completes namespace, model, handler, action and field names of the loaded
+/// schema.
+pub(super) struct ConsoleHelper {
+    pub(super) namespace: &'static Namespace,
+}
+
+impl ConsoleHelper {
+
+    fn candidates(&self, line: &str, start: usize) -> Vec<String> {
+        let before = &line[..start];
+        if before.trim() == "." {
+            return COMMANDS.iter().map(|c| c.to_string()).collect();
+        }
+        if before.matches('(').count() > before.matches(')').count() {
+            // inside the argument, complete query keys and fields of the model
+            let callee = before.split('(').next().unwrap_or("").trim();
+            let mut segments: Vec<&str> = callee.split('.').collect();
+            segments.pop();
+            let mut result: Vec<String> = QUERY_KEYS.iter().map(|k| k.to_string()).collect();
+            if let Some(model) = self.namespace.model_at_path(&segments) {
+                result.extend(model.fields.keys().cloned());
+                result.extend(model.relations().map(|r| r.name().to_owned()));
+            }
+            return result;
+        }
+        let path = before.rsplit(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.')).next().unwrap_or("");
+        let segments: Vec<&str> = path.split('.').filter(|s| !s.is_empty()).collect();
+        if segments.is_empty() {
+            return self.children(self.namespace);
+        }
+        if self.namespace.model_at_path(&segments).is_some() {
+            let (model_name, namespace_path) = segments.split_last().unwrap();
+            let mut result: Vec<String> = BUILTIN_ACTIONS.iter().map(|a| a.to_string()).collect();
+            if let Some(group) = self.namespace.namespace_at_path(&namespace_path.to_vec()).and_then(|n| n.model_handler_groups.get(*model_name)) {
+                result.extend(group.handlers.keys().cloned());
+            }
+            return result;
+        }
+        if let Some(namespace) = self.namespace.namespace_at_path(&segments) {
+            return self.children(namespace);
+        }
+        let (group_name, namespace_path) = segments.split_last().unwrap();
+        if let Some(group) = self.namespace.namespace_at_path(&namespace_path.to_vec()).and_then(|n| n.handler_groups.get(*group_name)) {
+            return group.handlers.keys().cloned().collect();
+        }
+        vec![]
+    }
+
+    fn children(&self, namespace: &Namespace) -> Vec<String> {
+        namespace.namespaces.keys()
+            .chain(namespace.models.keys())
+            .chain(namespace.handler_groups.keys())
+            .chain(namespace.handlers.keys())
+            .cloned()
+            .collect()
+    }
+}
+
+impl Completer for ConsoleHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let start = line[..pos].rfind(|c: char| !(c.is_alphanumeric() || c == '_')).map_or(0, |i| i + 1);
+        let word = &line[start..pos];
+        let mut candidates: Vec<String> = self.candidates(line, start).into_iter().filter(|c| c.starts_with(word)).collect();
+        candidates.sort();
+        candidates.dedup();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for ConsoleHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ConsoleHelper { }
+
+impl Validator for ConsoleHelper { }
+
+impl Helper for ConsoleHelper { }
@@ -0,0 +1,166 @@
+use serde_json::{Map, Number, Value as JsonValue};
+
+/// Parse a Teon style literal into JSON. Besides JSON, object keys may be
+/// bare identifiers, strings may be single quoted and trailing commas are
+/// allowed, e.g. `{ where: { name: 'Ada' }, take: 5, }`.
+pub(crate) fn parse(source: &str) -> Result<JsonValue, String> {
+    let mut parser = Parser { chars: source.chars().collect(), pos: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos < parser.chars.len() {
+        return Err(format!("unexpected `{}` at {}", parser.chars[parser.pos], parser.pos));
+    }
+    Ok(value)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map_or(false, |c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(c) => Err(format!("expect `{}`, found `{}` at {}", expected, c, self.pos)),
+            None => Err(format!("expect `{}`, found end of input", expected)),
+        }
+    }
+
+    fn value(&mut self) -> Result<JsonValue, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') | Some('\'') => Ok(JsonValue::String(self.string()?)),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) if c.is_alphabetic() || c == '_' => match self.identifier().as_str() {
+                "true" => Ok(JsonValue::Bool(true)),
+                "false" => Ok(JsonValue::Bool(false)),
+                "null" => Ok(JsonValue::Null),
+                other => Err(format!("unexpected `{}`", other)),
+            },
+            Some(c) => Err(format!("unexpected `{}` at {}", c, self.pos)),
+            None => Err("unexpected end of input".to_owned()),
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonValue, String> {
+        self.expect('{')?;
+        let mut result = Map::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(JsonValue::Object(result));
+                }
+                Some('"') | Some('\'') => {
+                    let key = self.string()?;
+                    self.expect(':')?;
+                    result.insert(key, self.value()?);
+                }
+                Some(c) if c.is_alphabetic() || c == '_' => {
+                    let key = self.identifier();
+                    self.expect(':')?;
+                    result.insert(key, self.value()?);
+                }
+                Some(c) => return Err(format!("unexpected `{}` at {}", c, self.pos)),
+                None => return Err("unclosed `{`".to_owned()),
+            }
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => (),
+                _ => return Err(format!("expect `,` or `}}` at {}", self.pos)),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<JsonValue, String> {
+        self.expect('[')?;
+        let mut result = vec![];
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(']') {
+                self.pos += 1;
+                return Ok(JsonValue::Array(result));
+            }
+            result.push(self.value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => (),
+                _ => return Err(format!("expect `,` or `]` at {}", self.pos)),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let quote = self.peek().unwrap();
+        self.pos += 1;
+        let mut result = String::new();
+        loop {
+            match self.peek() {
+                None => return Err("unclosed string".to_owned()),
+                Some(c) if c == quote => {
+                    self.pos += 1;
+                    return Ok(result);
+                }
+                Some('\\') => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some('n') => result.push('\n'),
+                        Some('t') => result.push('\t'),
+                        Some('r') => result.push('\r'),
+                        Some(c) => result.push(c),
+                        None => return Err("unclosed string".to_owned()),
+                    }
+                    self.pos += 1;
+                }
+                Some(c) => {
+                    result.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<JsonValue, String> {
+        let start = self.pos;
+        while self.peek().map_or(false, |c| c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E' || c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        if let Ok(i) = text.parse::<i64>() {
+            return Ok(JsonValue::Number(Number::from(i)));
+        }
+        match text.parse::<f64>().ok().and_then(Number::from_f64) {
+            Some(n) => Ok(JsonValue::Number(n)),
+            None => Err(format!("invalid number `{}`", text)),
+        }
+    }
+
+    fn identifier(&mut self) -> String {
+        let start = self.pos;
+        while self.peek().map_or(false, |c| c.is_alphanumeric() || c == '_') {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+}
@@ -0,0 +1,189 @@
+mod helper;
+pub(crate) mod literal;
+
+use actix_http::HttpMessage;
+use actix_web::test::{call_service, init_service, TestRequest};
+use colored::Colorize;
+use regex::Regex;
+use rustyline::error::ReadlineError;
+use rustyline::history::FileHistory;
+use rustyline::Editor;
+use serde_json::{Map, Value as JsonValue};
+use teo_result::{Error, Result};
+use teo_runtime::connection::transaction;
+use teo_runtime::handler::handler::Method;
+use crate::app::ctx::Ctx;
+use crate::cli::command::ConsoleCommand;
+use crate::console::helper::ConsoleHelper;
+use crate::message::{error_message, info_message};
+use crate::server::make::{make_server_app, resolve_handler, HandlerResolved, SharedTransaction};
+use crate::test::response::TestResponse;
+
+const HISTORY_FILE: &str = ".teo_console_history";
+
+pub(crate) const BUILTIN_ACTIONS: [&str; 15] = ["findMany", "findFirst", "findUnique", "create", "update", "upsert", "delete", "copy", "createMany", "updateMany", "copyMany", "deleteMany", "count", "aggregate", "groupBy"];
+
+const READ_ACTIONS: [&str; 6] = ["findMany", "findFirst", "findUnique", "count", "aggregate", "groupBy"];
+
+pub(crate) const COMMANDS: [&str; 6] = ["help", "models", "begin", "commit", "rollback", "exit"];
+
+/// An interactive console calling actions and handlers of the loaded app.
+///
+/// Every call goes through the same service as `serve`, so middlewares,
+/// pipelines and permissions apply. Input is a call like
+/// `User.findMany({ where: { name: 'Ada' } })` or `.help`. Calls between
+/// `.begin` and `.commit` or `.rollback` share one transaction.
+pub(crate) async fn console(command: &ConsoleCommand) -> Result<()> {
+    let mut editor: Editor<ConsoleHelper, FileHistory> = match Editor::new() {
+        Ok(editor) => editor,
+        Err(e) => Err(Error::new(format!("cannot start console: {}", e)))?,
+    };
+    editor.set_helper(Some(ConsoleHelper { namespace: Ctx::main_namespace() }));
+    let _ = editor.load_history(HISTORY_FILE);
+    let service = init_service(make_server_app(Ctx::conn_ctx().namespace(), Ctx::static_files(), Ctx::admin_dashboard())).await;
+    let call_regex = Regex::new(r"(?s)^([A-Za-z_]\w*(?:\.[A-Za-z_]\w*)*)\s*\((.*)\)\s*;?$").unwrap();
+    println!("Teo console{}, type `.help` for help.", if command.readonly { " (readonly)" } else { "" });
+    let mut transaction: Option<transaction::Ctx> = None;
+    loop {
+        let line = match editor.readline(if transaction.is_some() { "teo*> " } else { "teo> " }) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => Err(Error::new(format!("cannot read input: {}", e)))?,
+        };
+        let line = line.trim();
+        if line.is_empty() { continue }
+        let _ = editor.add_history_entry(line);
+        match line {
+            ".exit" => break,
+            ".help" => {
+                print_help();
+                continue
+            }
+            ".models" => {
+                print_models();
+                continue
+            }
+            ".begin" => {
+                if transaction.is_some() {
+                    error_message("a transaction is already open, `.commit` or `.rollback` it first");
+                } else {
+                    transaction = Some(transaction::Ctx::new(Ctx::conn_ctx().clone()));
+                    info_message("transaction started");
+                }
+                continue
+            }
+            ".commit" | ".rollback" => {
+                let Some(open) = transaction.take() else {
+                    error_message("no transaction is open, `.begin` one first");
+                    continue
+                };
+                let result = if line == ".commit" { open.commit().await } else { open.abort().await };
+                match result {
+                    Ok(()) => info_message(if line == ".commit" { "transaction committed" } else { "transaction rolled back" }),
+                    Err(e) => error_message(format!("{}", e)),
+                }
+                continue
+            }
+            _ => (),
+        }
+        let Some(captures) = call_regex.captures(line) else {
+            error_message(format!("cannot parse `{}`, expect a call like `User.findMany({{}})`", line));
+            continue
+        };
+        let segments: Vec<&str> = captures.get(1).unwrap().as_str().split('.').collect();
+        let argument = captures.get(2).unwrap().as_str().trim();
+        let body = if argument.is_empty() {
+            JsonValue::Object(Map::new())
+        } else {
+            match literal::parse(argument) {
+                Ok(body) => body,
+                Err(message) => {
+                    error_message(format!("invalid argument: {}", message));
+                    continue
+                }
+            }
+        };
+        let uri = uri(&segments);
+        if command.readonly && !is_read(&uri) {
+            error_message("only builtin find, count, aggregate and groupBy actions are allowed in readonly mode");
+            continue
+        }
+        let request = TestRequest::post().uri(&uri).set_json(body).to_request();
+        if let Some(transaction) = &transaction {
+            request.extensions_mut().insert(SharedTransaction(transaction.clone()));
+        }
+        let response = match TestResponse::from_service_response(call_service(&service, request).await).await {
+            Ok(response) => response,
+            Err(e) => {
+                error_message(format!("{}", e));
+                continue
+            }
+        };
+        let output = match response.json() {
+            Ok(json) => serde_json::to_string_pretty(&json).unwrap(),
+            Err(_) => response.text().unwrap_or_default(),
+        };
+        if response.status() < 400 {
+            println!("{}", output);
+        } else {
+            println!("{} {}", response.status().to_string().red(), output.red());
+        }
+    }
+    if let Some(open) = transaction {
+        open.abort().await?;
+        info_message("the open transaction is rolled back");
+    }
+    let _ = editor.save_history(HISTORY_FILE);
+    Ok(())
+}
+
+/// Whether a call reaches a builtin read action. The url is resolved like a
+/// request, so custom handlers are refused, also those mapped to the url of
+/// a builtin action.
+fn is_read(uri: &str) -> bool {
+    let main_namespace = Ctx::main_namespace();
+    let path_prefix = main_namespace.server.as_ref().and_then(|s| s.path_prefix.as_ref());
+    let path = main_namespace.handler_map.remove_path_prefix(uri, path_prefix.map(|s| s.as_str()));
+    let Some(match_result) = main_namespace.handler_map.r#match(Method::Post, path)
+        .or_else(|| main_namespace.handler_map.default_match(Method::Post, path)) else {
+        return false;
+    };
+    match resolve_handler(main_namespace, &match_result) {
+        Ok((_, HandlerResolved::Builtin(_, _))) => READ_ACTIONS.contains(&match_result.handler_name()),
+        _ => false,
+    }
+}
+
+/// The url of a call, under the path prefix of the server.
+pub(crate) fn uri(segments: &Vec<&str>) -> String {
+    let path_prefix = Ctx::main_namespace().server.as_ref().and_then(|s| s.path_prefix.as_ref());
+    match path_prefix {
+        Some(path_prefix) => format!("/{}/{}", path_prefix.trim_matches('/'), segments.join("/")),
+        None => format!("/{}", segments.join("/")),
+    }
+}
+
+fn print_help() {
+    println!("Call a builtin action or a handler with a Teon argument:");
+    println!("    User.findMany({{ where: {{ email: {{ contains: 'example.com' }} }}, include: {{ posts: true }} }})");
+    println!("    blog.Post.count()");
+    println!("    payments.charge({{ amount: 100 }})");
+    println!("Commands:");
+    println!("    .models    list models");
+    println!("    .begin     start a transaction which the following calls share");
+    println!("    .commit    commit the transaction");
+    println!("    .rollback  roll the transaction back");
+    println!("    .help      show this help");
+    println!("    .exit      leave the console");
+}
+
+fn print_models() {
+    let mut namespaces = vec![Ctx::main_namespace()];
+    while let Some(namespace) = namespaces.pop() {
+        namespaces.extend(namespace.namespaces.values());
+        for model in namespace.models.values() {
+            println!("{} {{ {} }}", model.path().join(".").bold(), model.fields.keys().cloned().collect::<Vec<String>>().join(", "));
+        }
+    }
+}
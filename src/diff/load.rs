@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::env::current_dir;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use teo_result::{Error, Result};
+use teo_runtime::namespace::Namespace;
+use teo_runtime::schema::load::load_schema::load_schema;
+use teo_runtime::stdlib::load::{load as load_std};
+use teo_runtime::utils::find_main_schema_file;
+use crate::app::ctx::Ctx;
+use crate::message::diagnostics_message;
+use crate::stdlib;
+
+/// Load a schema from a file, a directory or a git revision.
+///
+/// A revision is read from git without checking it out, the main schema
+/// file is the one of the working tree at the same relative path.
+pub(super) async fn load(source: &str) -> Result<Namespace> {
+    let path = Path::new(source);
+    let (main, unsaved_files) = if path.is_file() {
+        (path.to_path_buf(), None)
+    } else if path.is_dir() {
+        (find_main_schema_file(None, path)?, None)
+    } else {
+        let (main, files) = files_at_revision(source)?;
+        (main, Some(files))
+    };
+    load_files(main.to_str().unwrap(), unsaved_files, source).await
+}
+
+/// Load the schema of `main`, `source` names it in errors.
+pub(super) async fn load_files(main: &str, unsaved_files: Option<HashMap<String, String>>, source: &str) -> Result<Namespace> {
+    let (schema, diagnostics) = stdlib::parse_schema(main, unsaved_files)?;
+    diagnostics_message(&diagnostics);
+    if diagnostics.has_errors() {
+        Err(Error::new(format!("schema of `{}` has errors", source)))?
+    }
+    let mut namespace = Namespace::main();
+    load_std(&mut namespace);
+    // callbacks of the app are not needed for a comparison
+    load_schema(&mut namespace, &schema, true).await?;
+    Ok(namespace)
+}
+
+/// The schema files of a git revision, keyed by their path in the working
+/// tree.
+fn files_at_revision(revision: &str) -> Result<(PathBuf, HashMap<String, String>)> {
+    let root = PathBuf::from(git(&["rev-parse", "--show-toplevel"])?.trim());
+    let current_dir = match current_dir() {
+        Ok(current_dir) => current_dir,
+        Err(e) => Err(Error::new(format!("{}", e)))?,
+    };
+    let main = find_main_schema_file(Ctx::cli().main(), &current_dir)?;
+    let main = main.canonicalize().unwrap_or(main);
+    if !main.starts_with(&root) {
+        Err(Error::new(format!("{} is not in the git repository {}", main.display(), root.display())))?
+    }
+    let mut files = HashMap::new();
+    let tree = git(&["ls-tree", "-r", "--name-only", revision])
+        .map_err(|_| Error::new(format!("`{}` is neither a schema path nor a git revision", revision)))?;
+    for file in tree.lines().filter(|f| f.ends_with(".teo")) {
+        let content = git(&["show", &format!("{}:{}", revision, file)])?;
+        files.insert(root.join(file).to_str().unwrap().to_owned(), content);
+    }
+    if !files.contains_key(main.to_str().unwrap()) {
+        Err(Error::new(format!("{} doesn't exist at `{}`", main.strip_prefix(&root).unwrap().display(), revision)))?
+    }
+    Ok((main, files))
+}
+
+fn git(args: &[&str]) -> Result<String> {
+    let output = match Command::new("git").args(args).output() {
+        Ok(output) => output,
+        Err(e) => Err(Error::new(format!("cannot run git: {}", e)))?,
+    };
+    if !output.status.success() {
+        Err(Error::new(format!("`git {}` failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim())))?
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,387 @@
+mod load;
+
+use std::collections::BTreeMap;
+use colored::Colorize;
+use serde_json::{json, Value as JsonValue};
+use teo_result::{Error, Result};
+use teo_parser::ast::handler::HandlerInputFormat;
+use teo_runtime::handler::handler::Method;
+use teo_runtime::handler::Handler;
+use teo_runtime::model::field::is_optional::IsOptional;
+use teo_runtime::model::field::typed::Typed;
+use teo_runtime::model::index::Type as IndexType;
+use teo_runtime::model::Model;
+use teo_runtime::namespace::Namespace;
+use teo_runtime::traits::named::Named;
+use crate::app::ctx::Ctx;
+use crate::cli::command::SchemaDiffCommand;
+use crate::message::json_output;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl ChangeKind {
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Changed => "changed",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Change {
+    kind: ChangeKind,
+    /// `model`, `field`, `relation`, `index`, `enum`, `member` or `handler`.
+    target: &'static str,
+    path: String,
+    detail: Option<String>,
+    /// Generated clients stop compiling or requests start failing.
+    breaking: bool,
+    /// Migrating the database drops or rewrites existing data.
+    destructive: bool,
+}
+
+impl Change {
+
+    fn new(kind: ChangeKind, target: &'static str, path: String) -> Self {
+        Self { kind, target, path, detail: None, breaking: false, destructive: false }
+    }
+
+    fn detail(mut self, detail: String) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+
+    fn breaking(mut self) -> Self {
+        self.breaking = true;
+        self
+    }
+
+    fn destructive(mut self) -> Self {
+        self.destructive = true;
+        self
+    }
+
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "kind": self.kind.as_str(),
+            "target": self.target,
+            "path": self.path,
+            "detail": self.detail,
+            "breaking": self.breaking,
+            "destructive": self.destructive,
+        })
+    }
+
+    fn print(&self) {
+        let mark = match self.kind {
+            ChangeKind::Added => "+".green(),
+            ChangeKind::Removed => "-".red(),
+            ChangeKind::Changed => "~".yellow(),
+        };
+        let mut flags = vec![];
+        if self.breaking { flags.push("breaking".red().bold().to_string()) }
+        if self.destructive { flags.push("destructive".red().bold().to_string()) }
+        let detail = self.detail.as_ref().map(|d| format!(": {}", d)).unwrap_or_default();
+        let flags = if flags.is_empty() { String::new() } else { format!(" ({})", flags.join(", ")) };
+        println!("{} {:<8} {}{}{}", mark, self.target, self.path.bold(), detail, flags);
+    }
+}
+
+/// Compare two schemas and report what changes for clients and databases.
+///
+/// `old` and `new` are schema files, directories or git revisions, `new`
+/// defaults to the loaded schema.
+pub(crate) async fn schema_diff(command: &SchemaDiffCommand) -> Result<()> {
+    let old = load::load(&command.old).await?;
+    let new_loaded;
+    let new = match &command.new {
+        Some(new) => {
+            new_loaded = load::load(new).await?;
+            &new_loaded
+        }
+        None => Ctx::main_namespace(),
+    };
+    let changes = diff(&old, new);
+    let breaking = changes.iter().filter(|c| c.breaking).count();
+    let destructive = changes.iter().filter(|c| c.destructive).count();
+    if json_output() {
+        println!("{}", json!({
+            "changes": changes.iter().map(|c| c.to_json()).collect::<Vec<JsonValue>>(),
+            "breaking": breaking,
+            "destructive": destructive,
+        }));
+    } else {
+        for change in &changes {
+            change.print();
+        }
+        if changes.is_empty() {
+            println!("{}", "no changes".green());
+        } else {
+            println!("\n{} change(s), {} breaking, {} destructive", changes.len(), breaking, destructive);
+        }
+    }
+    if command.fail_on_breaking && breaking > 0 {
+        Err(Error::new(format!("{} breaking change(s)", breaking)))?
+    }
+    Ok(())
+}
+
+fn diff(old: &Namespace, new: &Namespace) -> Vec<Change> {
+    let mut changes = vec![];
+    let (old_models, new_models) = (models(old), models(new));
+    for (path, model) in &old_models {
+        match new_models.get(path) {
+            Some(new_model) => diff_model(path, model, new_model, &mut changes),
+            None => changes.push(Change::new(ChangeKind::Removed, "model", path.clone()).breaking().destructive()),
+        }
+    }
+    for path in new_models.keys().filter(|p| !old_models.contains_key(*p)) {
+        changes.push(Change::new(ChangeKind::Added, "model", path.clone()));
+    }
+    let (old_enums, new_enums) = (enums(old), enums(new));
+    for (path, members) in &old_enums {
+        let Some(new_members) = new_enums.get(path) else {
+            changes.push(Change::new(ChangeKind::Removed, "enum", path.clone()).breaking().destructive());
+            continue
+        };
+        for member in members.iter().filter(|m| !new_members.contains(m)) {
+            // stored values of the member are no longer valid
+            changes.push(Change::new(ChangeKind::Removed, "member", format!("{}.{}", path, member)).breaking().destructive());
+        }
+        for member in new_members.iter().filter(|m| !members.contains(m)) {
+            changes.push(Change::new(ChangeKind::Added, "member", format!("{}.{}", path, member)));
+        }
+    }
+    for path in new_enums.keys().filter(|p| !old_enums.contains_key(*p)) {
+        changes.push(Change::new(ChangeKind::Added, "enum", path.clone()));
+    }
+    let (old_handlers, new_handlers) = (handlers(old), handlers(new));
+    for (path, signature) in &old_handlers {
+        match new_handlers.get(path) {
+            Some(new_signature) if new_signature != signature => changes.push(Change::new(ChangeKind::Changed, "handler", path.clone()).detail(format!("{} -> {}", signature, new_signature)).breaking()),
+            Some(_) => (),
+            None => changes.push(Change::new(ChangeKind::Removed, "handler", path.clone()).breaking()),
+        }
+    }
+    for path in new_handlers.keys().filter(|p| !old_handlers.contains_key(*p)) {
+        changes.push(Change::new(ChangeKind::Added, "handler", path.clone()));
+    }
+    changes
+}
+
+fn diff_model(model_path: &str, old: &Model, new: &Model, changes: &mut Vec<Change>) {
+    if old.table_name != new.table_name {
+        // migrations create the new table, records of the old one are left behind
+        changes.push(Change::new(ChangeKind::Changed, "model", model_path.to_owned()).detail(format!("table {} -> {}", old.table_name, new.table_name)).destructive());
+    }
+    for (name, field) in &old.fields {
+        let path = format!("{}.{}", model_path, name);
+        let Some(new_field) = new.fields.get(name) else {
+            changes.push(Change::new(ChangeKind::Removed, "field", path).breaking().destructive());
+            continue
+        };
+        let (old_type, new_type) = (field.r#type().unwrap_optional().to_string(), new_field.r#type().unwrap_optional().to_string());
+        if old_type != new_type {
+            changes.push(Change::new(ChangeKind::Changed, "field", path.clone()).detail(format!("type {} -> {}", old_type, new_type)).breaking().destructive());
+        }
+        match (field.is_optional(), new_field.is_optional()) {
+            (true, false) => changes.push(Change::new(ChangeKind::Changed, "field", path.clone()).detail("optional -> required".to_owned()).breaking().destructive()),
+            // clients receive null where a value was promised
+            (false, true) => changes.push(Change::new(ChangeKind::Changed, "field", path.clone()).detail("required -> optional".to_owned()).breaking()),
+            _ => (),
+        }
+        if field.column_name != new_field.column_name {
+            changes.push(Change::new(ChangeKind::Changed, "field", path).detail(format!("column {} -> {}", field.column_name, new_field.column_name)).destructive());
+        }
+    }
+    for (name, field) in new.fields.iter().filter(|(name, _)| !old.fields.contains_key(*name)) {
+        let change = Change::new(ChangeKind::Added, "field", format!("{}.{}", model_path, name));
+        // existing rows and create calls of clients have no value for it,
+        // unless the database fills one in
+        changes.push(if field.is_optional() || field.default.is_some() || field.auto_increment { change } else { change.detail("required".to_owned()).breaking().destructive() });
+    }
+    for relation in old.relations() {
+        let path = format!("{}.{}", model_path, relation.name());
+        let Some(new_relation) = new.relations().into_iter().find(|r| r.name() == relation.name()) else {
+            changes.push(Change::new(ChangeKind::Removed, "relation", path).breaking());
+            continue
+        };
+        let (old_target, new_target) = (relation_signature(relation.model_path().join("."), relation.is_vec), relation_signature(new_relation.model_path().join("."), new_relation.is_vec));
+        if old_target != new_target {
+            changes.push(Change::new(ChangeKind::Changed, "relation", path).detail(format!("{} -> {}", old_target, new_target)).breaking());
+        }
+    }
+    for relation in new.relations().into_iter().filter(|r| !old.relations().iter().any(|o| o.name() == r.name())) {
+        changes.push(Change::new(ChangeKind::Added, "relation", format!("{}.{}", model_path, relation.name())));
+    }
+    let (old_indexes, new_indexes) = (indexes(old), indexes(new));
+    for (keys, unique) in &old_indexes {
+        let path = format!("{}({})", model_path, keys);
+        match new_indexes.get(keys) {
+            // `findUnique` by these keys is refused
+            None if *unique => changes.push(Change::new(ChangeKind::Removed, "index", path).detail("unique".to_owned()).breaking()),
+            None => changes.push(Change::new(ChangeKind::Removed, "index", path)),
+            Some(false) if *unique => changes.push(Change::new(ChangeKind::Changed, "index", path).detail("unique -> non-unique".to_owned()).breaking()),
+            // fails to migrate while duplicates exist
+            Some(true) if !*unique => changes.push(Change::new(ChangeKind::Changed, "index", path).detail("non-unique -> unique".to_owned()).destructive()),
+            Some(_) => (),
+        }
+    }
+    for (keys, unique) in new_indexes.iter().filter(|(k, _)| !old_indexes.contains_key(*k)) {
+        let change = Change::new(ChangeKind::Added, "index", format!("{}({})", model_path, keys));
+        changes.push(if *unique { change.detail("unique".to_owned()).destructive() } else { change });
+    }
+}
+
+/// Keys of each index and whether it's unique. A primary key is unique.
+fn indexes(model: &Model) -> BTreeMap<String, bool> {
+    model.indexes().iter().map(|i| (i.keys().join(", "), matches!(i.r#type(), IndexType::Unique | IndexType::Primary))).collect()
+}
+
+fn relation_signature(model_path: String, is_vec: bool) -> String {
+    if is_vec { format!("{}[]", model_path) } else { model_path }
+}
+
+/// User namespaces, the standard library is the same on both sides.
+fn namespaces(main: &Namespace) -> Vec<&Namespace> {
+    let mut result = vec![];
+    let mut namespaces = vec![main];
+    while let Some(namespace) = namespaces.pop() {
+        if namespace.path.first().map(|s| s.as_str()) == Some("std") { continue }
+        namespaces.extend(namespace.namespaces.values());
+        result.push(namespace);
+    }
+    result
+}
+
+fn models(main: &Namespace) -> BTreeMap<String, &Model> {
+    namespaces(main).into_iter().flat_map(|n| n.models.values()).map(|m| (m.path().join("."), m)).collect()
+}
+
+fn enums(main: &Namespace) -> BTreeMap<String, Vec<String>> {
+    namespaces(main).into_iter()
+        .flat_map(|n| n.enums.iter().map(move |(name, e)| (qualified(n, name), e.members.iter().map(|m| m.name.clone()).collect())))
+        .collect()
+}
+
+/// Custom handlers and their signature.
+fn handlers(main: &Namespace) -> BTreeMap<String, String> {
+    let mut result = BTreeMap::new();
+    for namespace in namespaces(main) {
+        let groups = namespace.model_handler_groups.iter().chain(namespace.handler_groups.iter());
+        for (group_name, group) in groups {
+            for (name, handler) in &group.handlers {
+                result.insert(qualified(namespace, &format!("{}.{}", group_name, name)), handler_signature(handler));
+            }
+        }
+        for (name, handler) in &namespace.handlers {
+            result.insert(qualified(namespace, name), handler_signature(handler));
+        }
+    }
+    result
+}
+
+/// The method, url, input format and types a client calls a handler with,
+/// e.g. `POST /hello (json) HelloInput -> HelloOutput`.
+fn handler_signature(handler: &Handler) -> String {
+    let method = match handler.method {
+        Method::Get => "GET",
+        Method::Post => "POST",
+        Method::Patch => "PATCH",
+        Method::Put => "PUT",
+        Method::Delete => "DELETE",
+        Method::Options => "OPTIONS",
+    };
+    let format = match handler.format {
+        HandlerInputFormat::Json => "json",
+        HandlerInputFormat::Form => "form",
+    };
+    match &handler.url {
+        Some(url) => format!("{} {} ({}) {} -> {}", method, url, format, handler.input_type, handler.output_type),
+        None => format!("{} ({}) {} -> {}", method, format, handler.input_type, handler.output_type),
+    }
+}
+
+fn qualified(namespace: &Namespace, name: &str) -> String {
+    if namespace.path.is_empty() { name.to_owned() } else { format!("{}.{}", namespace.path.join("."), name) }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use super::*;
+
+    const HEADER: &str = "connector {\n  provider .sqlite\n  url \"sqlite::memory:\"\n}\n";
+
+    async fn changes(old: &str, new: &str) -> Vec<Change> {
+        let main = "/diff/schema.teo";
+        let namespace = |source: &str| load::load_files(main, Some(HashMap::from([(main.to_owned(), format!("{}{}", HEADER, source))])), "test");
+        diff(&namespace(old).await.unwrap(), &namespace(new).await.unwrap())
+    }
+
+    fn find<'a>(changes: &'a Vec<Change>, target: &str, path: &str) -> &'a Change {
+        changes.iter().find(|c| c.target == target && c.path == path).unwrap()
+    }
+
+    #[tokio::test]
+    async fn added_required_fields_with_defaults_are_compatible() {
+        let old = "model User {\n  @id @autoIncrement\n  id: Int\n}\n";
+        let new = "model User {\n  @id @autoIncrement\n  id: Int\n  @default(0)\n  score: Int\n  name: String\n  nickname: String?\n}\n";
+        let changes = changes(old, new).await;
+        let score = find(&changes, "field", "User.score");
+        assert!(!score.breaking && !score.destructive);
+        let nickname = find(&changes, "field", "User.nickname");
+        assert!(!nickname.breaking && !nickname.destructive);
+        let name = find(&changes, "field", "User.name");
+        assert!(name.breaking && name.destructive);
+    }
+
+    #[tokio::test]
+    async fn renamed_tables_are_destructive() {
+        let old = "model User {\n  @id @autoIncrement\n  id: Int\n}\n";
+        let new = "@map(\"people\")\nmodel User {\n  @id @autoIncrement\n  id: Int\n}\n";
+        let changes = changes(old, new).await;
+        let user = find(&changes, "model", "User");
+        assert_eq!(user.kind, ChangeKind::Changed);
+        assert!(user.destructive && !user.breaking);
+    }
+
+    #[tokio::test]
+    async fn unique_changes_of_indexes() {
+        let old = "model User {\n  @id @autoIncrement\n  id: Int\n  @unique\n  email: String\n  @index\n  name: String\n}\n";
+        let new = "model User {\n  @id @autoIncrement\n  id: Int\n  @index\n  email: String\n  @unique\n  name: String\n}\n";
+        let changes = changes(old, new).await;
+        let email = find(&changes, "index", "User(email)");
+        assert_eq!(email.detail.as_deref(), Some("unique -> non-unique"));
+        assert!(email.breaking && !email.destructive);
+        let name = find(&changes, "index", "User(name)");
+        assert_eq!(name.detail.as_deref(), Some("non-unique -> unique"));
+        assert!(name.destructive && !name.breaking);
+    }
+
+    #[tokio::test]
+    async fn changed_handler_types_are_breaking() {
+        let interfaces = "interface HelloInput {\n  name: String\n}\n\ninterface HelloOutput {\n  greeting: String\n}\n\n";
+        let old = format!("{}declare handler group Greeting {{\n  declare handler hello(HelloInput): HelloOutput\n}}\n", interfaces);
+        let new = format!("{}declare handler group Greeting {{\n  declare handler hello(HelloInput): String\n}}\n", interfaces);
+        let changes = changes(&old, &new).await;
+        let hello = find(&changes, "handler", "Greeting.hello");
+        assert_eq!(hello.kind, ChangeKind::Changed);
+        assert!(hello.breaking);
+        assert!(hello.detail.as_deref().unwrap().ends_with("-> POST (json) HelloInput -> String"));
+    }
+
+    #[tokio::test]
+    async fn identical_schemas_have_no_changes() {
+        let source = "enum Role {\n  admin\n  user\n}\n\nmodel User {\n  @id @autoIncrement\n  id: Int\n  role: Role\n}\n";
+        assert!(changes(source, source).await.is_empty());
+    }
+}
@@ -0,0 +1,264 @@
+use std::env::{current_dir, temp_dir};
+use std::fs;
+use std::net::TcpListener;
+use std::path::Path;
+use std::time::Duration;
+use colored::Colorize;
+use regex::Regex;
+use teo_result::{Error, Result};
+use teo_runtime::database::database::Database;
+use teo_runtime::utils::find_main_schema_file;
+use teo_runtime::Value;
+use crate::app::ctx::Ctx;
+use crate::app::database::{connect_databases, connection_for_connector};
+use crate::message::namespace_name;
+use crate::migrate::versioned;
+use crate::profile::mask_password;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug)]
+struct Check {
+    name: String,
+    status: Status,
+    message: String,
+    /// What to do about a warning or failure.
+    hint: Option<String>,
+}
+
+impl Check {
+
+    fn pass(name: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { name: name.into(), status: Status::Pass, message: message.into(), hint: None }
+    }
+
+    fn warn(name: impl Into<String>, message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self { name: name.into(), status: Status::Warn, message: message.into(), hint: Some(hint.into()) }
+    }
+
+    fn fail(name: impl Into<String>, message: impl Into<String>, hint: impl Into<String>) -> Self {
+        Self { name: name.into(), status: Status::Fail, message: message.into(), hint: Some(hint.into()) }
+    }
+
+    fn print(&self) {
+        let mark = match self.status {
+            Status::Pass => "✔".green(),
+            Status::Warn => "!".yellow(),
+            Status::Fail => "✘".red(),
+        };
+        println!("{} {:<24} {}", mark, self.name, self.message);
+        if let Some(hint) = &self.hint {
+            println!("  {:<24} {}", "", hint.dimmed());
+        }
+    }
+}
+
+/// Check the environment an app runs in, and print what to fix.
+pub(crate) async fn doctor() -> Result<()> {
+    let mut checks = vec![];
+    checks.push(schema());
+    checks.push(dot_env());
+    checks.extend(env_vars());
+    checks.push(temp_dir_writable());
+    checks.extend(upload_dirs());
+    if !Ctx::schema_has_errors() {
+        let connector_checks = connectors().await;
+        let connected = connector_checks.iter().all(|c| c.status == Status::Pass);
+        checks.extend(connector_checks);
+        if connected {
+            checks.push(migrations().await);
+        }
+        checks.extend(port());
+        checks.extend(output_dirs());
+    }
+    for check in &checks {
+        check.print();
+    }
+    let failures = checks.iter().filter(|c| c.status == Status::Fail).count();
+    let warnings = checks.iter().filter(|c| c.status == Status::Warn).count();
+    println!();
+    if failures > 0 {
+        Err(Error::new(format!("{} check(s) failed, {} warning(s)", failures, warnings)))?
+    }
+    println!("all checks passed, {} warning(s)", warnings);
+    Ok(())
+}
+
+fn schema() -> Check {
+    let current_dir = current_dir().unwrap_or_default();
+    let path = find_main_schema_file(Ctx::cli().main(), &current_dir).map(|p| p.display().to_string()).unwrap_or_default();
+    if Ctx::schema_has_errors() {
+        Check::fail("schema", format!("{} has errors", path), "fix the diagnostics printed above, other checks are skipped")
+    } else {
+        Check::pass("schema", format!("{} parsed, {} file(s)", path, Ctx::schema().sources().len()))
+    }
+}
+
+fn dot_env() -> Check {
+    if !Path::new(".env").exists() {
+        return Check::pass(".env", "no .env file, using the process environment");
+    }
+    match dotenvy::from_path_iter(".env") {
+        Ok(iter) => {
+            let mut count = 0;
+            for item in iter {
+                if let Err(e) = item {
+                    return Check::fail(".env", format!("cannot parse: {}", e), "fix the line, values with spaces must be quoted");
+                }
+                count += 1;
+            }
+            Check::pass(".env", format!("{} variable(s) loaded", count))
+        }
+        Err(e) => Check::fail(".env", format!("cannot read: {}", e), "check the file permissions"),
+    }
+}
+
+/// Variables read with `env("...")` anywhere in the schema.
+fn env_vars() -> Vec<Check> {
+    let regex = Regex::new(r#"env\(\s*"([^"]+)""#).unwrap();
+    let mut names: Vec<String> = vec![];
+    for source in Ctx::schema().sources() {
+        let Ok(content) = fs::read_to_string(&source.file_path) else { continue };
+        for captures in regex.captures_iter(&content) {
+            let name = captures.get(1).unwrap().as_str().to_owned();
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names.into_iter().map(|name| match std::env::var(&name) {
+        Ok(value) if !value.is_empty() => Check::pass(format!("env {}", name), "set"),
+        Ok(_) => Check::warn(format!("env {}", name), "set but empty", format!("give `{}` a value in .env or the environment", name)),
+        Err(_) => Check::fail(format!("env {}", name), "not set", format!("add `{}=...` to .env or export it", name)),
+    }).collect()
+}
+
+fn temp_dir_writable() -> Check {
+    let dir = temp_dir();
+    match probe_writable(&dir) {
+        Ok(()) => Check::pass("temp dir", format!("{} is writable, uploads are stored here", dir.display())),
+        Err(e) => Check::fail("temp dir", format!("{} is not writable: {}", dir.display(), e), "set TMPDIR to a writable directory"),
+    }
+}
+
+/// Directories served as static files, which apps usually move uploads
+/// into. Serving only needs them readable, so not writable is a warning.
+fn upload_dirs() -> Vec<Check> {
+    Ctx::static_files().iter().map(|(prefix, static_files)| {
+        let name = format!("static files {}", prefix);
+        let dir = static_files.base();
+        if !dir.is_dir() {
+            return Check::fail(name, format!("{} doesn't exist", dir.display()), "create it or change the directory passed to `StaticFiles::new`");
+        }
+        match probe_writable(dir) {
+            Ok(()) => Check::pass(name, format!("{} is writable", dir.display())),
+            Err(e) => Check::warn(name, format!("{} is not writable: {}", dir.display(), e), "fix the directory permissions if uploads are moved here"),
+        }
+    }).collect()
+}
+
+/// Write and remove a file, permission bits don't tell about read-only
+/// mounts and ACLs.
+fn probe_writable(dir: &Path) -> std::io::Result<()> {
+    let probe = dir.join(format!(".teo-doctor-{}", std::process::id()));
+    fs::write(&probe, b"")?;
+    fs::remove_file(&probe)
+}
+
+async fn connectors() -> Vec<Check> {
+    let mut result = vec![];
+    let mut namespaces = vec![Ctx::main_namespace()];
+    while let Some(namespace) = namespaces.pop() {
+        namespaces.extend(namespace.namespaces.values());
+        let Some(connector) = namespace.connector.as_ref() else { continue };
+        let name = format!("connector {}", namespace_name(&namespace.path));
+        let url = mask_password(&connector.url);
+        // connectors panic on unreachable databases, keep that from stopping the checks
+        let mut connecting = tokio::spawn(async move {
+            let connection = connection_for_connector(connector).await;
+            let transaction = connection.no_transaction().await?;
+            let sql = match connector.provider {
+                Database::MongoDB => return Ok(None),
+                Database::SQLite => "SELECT sqlite_version() AS version",
+                Database::MySQL | Database::PostgreSQL => "SELECT version() AS version",
+            };
+            let rows = transaction.query_raw(&Value::String(sql.to_owned())).await?;
+            Ok::<Option<String>, Error>(rows.as_array()
+                .and_then(|rows| rows.first())
+                .and_then(|row| row.as_dictionary())
+                .and_then(|row| row.get("version"))
+                .and_then(|version| version.as_str())
+                .map(|version| version.split(',').next().unwrap_or(version).to_owned()))
+        });
+        let outcome = tokio::time::timeout(CONNECT_TIMEOUT, &mut connecting).await;
+        if outcome.is_err() {
+            // don't leave the attempt running after it's reported
+            connecting.abort();
+        }
+        result.push(match outcome {
+            Ok(Ok(Ok(version))) => Check::pass(name, format!("{} at {}{}", connector.provider.lowercase_desc(), url, version.map(|v| format!(", server {}", v)).unwrap_or_default())),
+            Ok(Ok(Err(e))) => Check::fail(name, format!("cannot query {}: {}", url, e), "check the database user's permissions"),
+            Ok(Err(_)) => Check::fail(name, format!("cannot connect to {}", url), "check the url and that the database server is running"),
+            Err(_) => Check::fail(name, format!("connecting to {} timed out", url), "check the host, port and firewall"),
+        });
+    }
+    result
+}
+
+async fn migrations() -> Check {
+    if !versioned::is_versioned() {
+        return Check::pass("migrations", "automatic, the schema is migrated on serve");
+    }
+    if let Err(e) = connect_databases(Ctx::main_namespace_mut(), true).await {
+        return Check::fail("migrations", format!("cannot connect: {}", e), "fix the connectors above");
+    }
+    match versioned::pending().await {
+        Ok(0) => Check::pass("migrations", "versioned, all applied"),
+        Ok(pending) => Check::warn("migrations", format!("versioned, {} pending", pending), "run `migrate up`"),
+        Err(e) => Check::fail("migrations", format!("{}", e), "see `migrate status`"),
+    }
+}
+
+fn port() -> Option<Check> {
+    let server = Ctx::main_namespace().server.as_ref()?;
+    let (host, port) = (server.bind.0.clone(), server.bind.1);
+    Some(match TcpListener::bind((host.as_str(), port as u16)) {
+        Ok(_) => Check::pass("server bind", format!("{}:{} is available", host, port)),
+        Err(e) => Check::fail("server bind", format!("cannot bind {}:{}: {}", host, port, e), "stop the process using the port or change `bind` in the server config"),
+    })
+}
+
+fn output_dirs() -> Vec<Check> {
+    let namespace = Ctx::main_namespace();
+    let clients = namespace.clients.iter().map(|(name, client)| (format!("client {}", name), client.dest.clone()));
+    let entities = namespace.entities.iter().map(|(name, entity)| (format!("entity {}", name), entity.dest.clone()));
+    clients.chain(entities).map(|(name, dest)| {
+        let path = Path::new(&dest);
+        // generators create the directory when its parent exists
+        let existing = if path.exists() { Some(path) } else { path.parent().filter(|p| p.as_os_str().is_empty() || p.exists()) };
+        match existing.map(|dir| probe_writable(if dir.as_os_str().is_empty() { Path::new(".") } else { dir })) {
+            Some(Ok(())) => Check::pass(name, format!("{} is writable", dest)),
+            Some(Err(e)) => Check::fail(name, format!("{} is not writable: {}", dest, e), "fix the directory permissions"),
+            None => Check::fail(name, format!("parent directory of {} doesn't exist", dest), "create it or change `dest`"),
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probes_by_writing() {
+        assert!(probe_writable(&temp_dir()).is_ok());
+        assert!(fs::read_dir(temp_dir()).unwrap().all(|e| !e.unwrap().file_name().to_string_lossy().starts_with(&format!(".teo-doctor-{}", std::process::id()))));
+        assert!(probe_writable(&temp_dir().join("teo-doctor-missing").join("dir")).is_err());
+    }
+}
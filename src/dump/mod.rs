@@ -0,0 +1,511 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::str::FromStr;
+use bigdecimal::BigDecimal;
+use bson::{Bson, Document};
+use bson::oid::ObjectId;
+use chrono::{DateTime, NaiveDate, Utc};
+use indexmap::IndexMap;
+use key_path::path;
+use mongodb::Client;
+use serde_json::{json, Map, Value as JsonValue};
+use teo_parser::r#type::Type;
+use teo_result::{Error, Result};
+use teo_runtime::connection::transaction;
+use teo_runtime::database::database::Database;
+use teo_runtime::model::{Field, Model, Object};
+use teo_runtime::model::field::typed::Typed;
+use teo_runtime::teon;
+use teo_runtime::traits::named::Named;
+use teo_runtime::Value;
+use crate::app::ctx::Ctx;
+use crate::cli::command::{DumpCommand, RestoreCommand};
+use crate::introspect::quote;
+use crate::message::info_message;
+use crate::migrate::ddl::{identifier, string_literal};
+use crate::migrate::versioned::run_in_transaction;
+use crate::purge::{self, purge};
+
+const FORMAT: &str = "teo-dump";
+const VERSION: i64 = 1;
+const PAGE_SIZE: i64 = 1000;
+
+/// Write the records of every model into a NDJSON archive.
+///
+/// The first line is a header, every following line is a single record:
+/// `{"model": "blog.Post", "record": {...}}`. Values are stored as JSON and
+/// converted back with the field types of the schema on restore, so that an
+/// archive taken from one connector can be restored into another one.
+pub(crate) async fn dump(command: &DumpCommand, silent: bool) -> Result<()> {
+    let models = models(command.namespace.as_deref(), command.models.as_ref())?;
+    let writer: Box<dyn Write> = match &command.out {
+        Some(out) => match File::create(out) {
+            Ok(file) => Box::new(BufWriter::new(file)),
+            Err(e) => Err(Error::new(format!("cannot write {}: {}", out, e)))?,
+        },
+        None => Box::new(BufWriter::new(std::io::stdout())),
+    };
+    write_archive(&models, writer, silent || command.out.is_none()).await
+}
+
+/// Write the records of `models` into an archive file, e.g. before they are
+/// purged.
+pub(crate) async fn snapshot(models: &Vec<&'static Model>, output: &str, silent: bool) -> Result<()> {
+    let writer = match File::create(output) {
+        Ok(file) => BufWriter::new(file),
+        Err(e) => Err(Error::new(format!("cannot write {}: {}", output, e)))?,
+    };
+    write_archive(models, Box::new(writer), silent).await
+}
+
+async fn write_archive(models: &Vec<&'static Model>, mut writer: Box<dyn Write>, silent: bool) -> Result<()> {
+    let write_error = |e: std::io::Error| Error::new(format!("cannot write dump: {}", e));
+    writeln!(writer, "{}", json!({
+        "format": FORMAT,
+        "version": VERSION,
+        "createdAt": Utc::now().to_rfc3339(),
+        "models": models.iter().map(|m| m.path().join(".")).collect::<Vec<String>>(),
+    })).map_err(write_error)?;
+    let ctx = transaction::Ctx::new(Ctx::conn_ctx().clone());
+    for model in models.iter().copied() {
+        let model_path = model.path().join(".");
+        // page in the order of the primary key, so that no record is skipped
+        let order_by = Value::Array(model.primary_index().map(|index| index.keys().iter().map(|key| {
+            let mut order = teon!({});
+            order.as_dictionary_mut().unwrap().insert(key.clone(), Value::String("asc".to_owned()));
+            order
+        }).collect()).unwrap_or_default());
+        let mut count = 0;
+        loop {
+            let page: Vec<Object> = ctx.find_many(model, &teon!({
+                "orderBy": order_by.clone(),
+                "skip": count as i64,
+                "take": PAGE_SIZE,
+            }), None, path![]).await?;
+            for object in &page {
+                writeln!(writer, "{}", json!({ "model": model_path, "record": record(object) })).map_err(write_error)?;
+            }
+            count += page.len();
+            if (page.len() as i64) < PAGE_SIZE { break }
+        }
+        if !silent {
+            info_message(format!("dumped {} record(s) of {}", count, model_path));
+        }
+    }
+    writer.flush().map_err(write_error)?;
+    Ok(())
+}
+
+/// Insert the records of an archive into the connected databases.
+pub(crate) async fn restore(command: &RestoreCommand, silent: bool) -> Result<()> {
+    if command.purge {
+        purge::guard("restore --purge", command.force)?;
+    }
+    restore_archive(&command.input, command.purge, silent).await
+}
+
+/// Insert the records of the archive at `input` as they are, without
+/// running pipelines, defaults or callbacks.
+///
+/// Models are inserted in the order of their required relations, like seed
+/// data sets. Optional foreign keys are set after every record is inserted,
+/// so that records referencing each other can be restored. SQL rows are
+/// inserted in one transaction per connector, and PostgreSQL sequences are
+/// moved past the restored ids.
+pub(crate) async fn restore_archive(input: &str, purge_first: bool, silent: bool) -> Result<()> {
+    let read_error = |e: std::io::Error| Error::new(format!("cannot read {}: {}", input, e));
+    let reader = BufReader::new(File::open(input).map_err(read_error)?);
+    let mut lines = reader.lines();
+    let header: JsonValue = match lines.next() {
+        Some(line) => serde_json::from_str(&line.map_err(read_error)?).unwrap_or(JsonValue::Null),
+        None => Err(Error::new(format!("`{}` is empty", input)))?,
+    };
+    if header.get("format").and_then(|f| f.as_str()) != Some(FORMAT) {
+        Err(Error::new(format!("`{}` is not a teo dump", input)))?
+    }
+    if header.get("version").and_then(|v| v.as_i64()).map_or(true, |v| v > VERSION) {
+        Err(Error::new(format!("`{}` is written by a newer version of teo", input)))?
+    }
+    let mut groups: IndexMap<String, Vec<Map<String, JsonValue>>> = IndexMap::new();
+    for (index, line) in lines.enumerate() {
+        let line = line.map_err(read_error)?;
+        if line.trim().is_empty() { continue }
+        let entry: JsonValue = serde_json::from_str(&line).unwrap_or(JsonValue::Null);
+        let (Some(model_path), Some(record)) = (entry.get("model").and_then(|m| m.as_str()), entry.get("record").and_then(|r| r.as_object())) else {
+            Err(Error::new(format!("invalid record on line {}", index + 2)))?
+        };
+        groups.entry(model_path.to_owned()).or_default().push(record.clone());
+    }
+    let namespace = Ctx::main_namespace();
+    let mut models = vec![];
+    for model_path in groups.keys() {
+        match namespace.model_at_path(&model_path.split('.').collect()) {
+            Some(model) => models.push(model),
+            None => Err(Error::new(format!("model `{}` is not defined in the schema", model_path)))?,
+        }
+    }
+    if purge_first {
+        purge().await?;
+    }
+    let transaction_ctx = transaction::Ctx::new(Ctx::conn_ctx().clone());
+    for model in &models {
+        let existing: Vec<Object> = transaction_ctx.find_many(model, &teon!({ "take": 1 }), None, path![]).await?;
+        if !existing.is_empty() {
+            Err(Error::new(format!("`{}` already has records, restore into an empty database or pass `--purge`", model.path().join("."))))?
+        }
+    }
+    let ordered = ordered_models(&models)?;
+    let ctx = Ctx::conn_ctx();
+    for (namespace_path, connection) in ctx.connections_iter() {
+        let namespace = ctx.namespace().namespace_at_path(&namespace_path.iter().map(AsRef::as_ref).collect()).unwrap();
+        let under_connector = namespace.models_under_connector();
+        let selected: Vec<&'static Model> = ordered.iter().copied()
+            .filter(|m| under_connector.iter().any(|model| model.path() == m.path()))
+            .collect();
+        if selected.is_empty() { continue }
+        let connector = namespace.connector.as_ref().unwrap();
+        if connector.provider.is_mongo() {
+            insert_documents(&connector.url, &selected, &groups).await?;
+        } else {
+            run_in_transaction(connection, insert_statements(&selected, &groups, &connector.provider)?).await?;
+        }
+        if !silent {
+            for model in selected {
+                let model_path = model.path().join(".");
+                info_message(format!("restored {} record(s) of {}", groups.get(&model_path).unwrap().len(), model_path));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Models selected by a namespace and model names, every model by default.
+pub(crate) fn models(namespace_path: Option<&str>, names: Option<&Vec<String>>) -> Result<Vec<&'static Model>> {
+    let main = Ctx::main_namespace();
+    if let Some(names) = names {
+        let mut result = vec![];
+        for name in names {
+            let full_name = match namespace_path {
+                Some(namespace_path) if !name.contains('.') => format!("{}.{}", namespace_path, name),
+                _ => name.clone(),
+            };
+            match main.model_at_path(&full_name.split('.').collect()) {
+                Some(model) => result.push(model),
+                None => Err(Error::new(format!("model `{}` is not found", full_name)))?,
+            }
+        }
+        return Ok(result);
+    }
+    let root = match namespace_path {
+        Some(namespace_path) => main.namespace_at_path(&namespace_path.split('.').collect())
+            .ok_or_else(|| Error::new(format!("namespace `{}` is not found", namespace_path)))?,
+        None => main,
+    };
+    let mut result = vec![];
+    let mut namespaces = vec![root];
+    while let Some(namespace) = namespaces.pop() {
+        if namespace.path.first().map(|s| s.as_str()) == Some("std") { continue }
+        namespaces.extend(namespace.namespaces.values());
+        result.extend(namespace.models.values());
+    }
+    Ok(result)
+}
+
+fn record(object: &Object) -> Map<String, JsonValue> {
+    let mut result = Map::new();
+    for field in object.model().fields.values() {
+        if let Ok(value) = object.get_value(field.name()) {
+            result.insert(field.name().to_owned(), JsonValue::try_from(&value).unwrap_or(JsonValue::Null));
+        }
+    }
+    result
+}
+
+/// The stored fields of a record with values of their field types.
+fn row(model: &'static Model, record: &Map<String, JsonValue>) -> Result<Vec<(&'static Field, Value)>> {
+    let mut row = vec![];
+    for (key, value) in record {
+        // fields dropped from the schema since the dump are skipped
+        let Some(field) = model.field(key) else { continue };
+        if field.r#virtual { continue }
+        let value = value_for_type(value, field.r#type()).map_err(|message| {
+            Error::new(format!("{}.{}: {}", model.path().join("."), key, message))
+        })?;
+        row.push((field, value));
+    }
+    Ok(row)
+}
+
+/// Inserts of the records of `models` and updates of their optional foreign
+/// keys afterwards.
+fn insert_statements(models: &Vec<&'static Model>, groups: &IndexMap<String, Vec<Map<String, JsonValue>>>, provider: &Database) -> Result<Vec<String>> {
+    let mut inserts = vec![];
+    let mut updates = vec![];
+    for model in models {
+        let table = identifier(&model.table_name, provider);
+        let optional_keys = optional_foreign_keys(model);
+        let primary_key = model.primary_index().map(|index| index.keys().clone()).unwrap_or_default();
+        for record in groups.get(&model.path().join(".")).unwrap() {
+            let row = row(model, record)?;
+            let (later, now): (Vec<&(&Field, Value)>, Vec<&(&Field, Value)>) = row.iter()
+                .partition(|(field, value)| !value.is_null() && optional_keys.contains(&field.name().to_owned()));
+            inserts.push(format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                table,
+                now.iter().map(|(field, _)| identifier(&field.column_name, provider)).collect::<Vec<String>>().join(", "),
+                now.iter().map(|(_, value)| sql_literal(value, provider)).collect::<Result<Vec<String>>>()?.join(", "),
+            ));
+            if later.is_empty() { continue }
+            let mut condition = vec![];
+            for key in &primary_key {
+                let Some((field, value)) = row.iter().find(|(field, _)| field.name() == key) else {
+                    Err(Error::new(format!("a record of `{}` has no `{}`", model.path().join("."), key)))?
+                };
+                condition.push(format!("{} = {}", identifier(&field.column_name, provider), sql_literal(value, provider)?));
+            }
+            updates.push(format!(
+                "UPDATE {} SET {} WHERE {}",
+                table,
+                later.iter().map(|(field, value)| -> Result<String> { Ok(format!("{} = {}", identifier(&field.column_name, provider), sql_literal(value, provider)?)) }).collect::<Result<Vec<String>>>()?.join(", "),
+                condition.join(" AND "),
+            ));
+        }
+    }
+    let mut statements = inserts;
+    statements.extend(updates);
+    if let Database::PostgreSQL = provider {
+        for model in models {
+            let table = identifier(&model.table_name, provider);
+            for field in model.fields.values().filter(|f| f.auto_increment) {
+                let column = identifier(&field.column_name, provider);
+                statements.push(format!(
+                    "SELECT setval(pg_get_serial_sequence({}, {}), COALESCE(MAX({}), 1), MAX({}) IS NOT NULL) FROM {}",
+                    quote(&table), quote(&field.column_name), column, column, table,
+                ));
+            }
+        }
+    }
+    Ok(statements)
+}
+
+fn sql_literal(value: &Value, provider: &Database) -> Result<String> {
+    Ok(match value {
+        Value::Null => "NULL".to_owned(),
+        Value::Bool(b) => match provider {
+            Database::SQLite => if *b { "1" } else { "0" }.to_owned(),
+            _ => if *b { "TRUE" } else { "FALSE" }.to_owned(),
+        },
+        Value::Int(i) => i.to_string(),
+        Value::Int64(i) => i.to_string(),
+        Value::Float32(f) => f.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Decimal(d) => d.to_string(),
+        Value::String(s) => string_literal(s, provider),
+        Value::Date(d) => quote(&d.format("%Y-%m-%d").to_string()),
+        Value::DateTime(d) => match provider {
+            Database::MySQL => quote(&d.format("%Y-%m-%d %H:%M:%S%.3f").to_string()),
+            _ => quote(&d.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)),
+        },
+        Value::Array(values) if matches!(provider, Database::PostgreSQL) => if values.is_empty() {
+            "'{}'".to_owned()
+        } else {
+            format!("ARRAY[{}]", values.iter().map(|v| sql_literal(v, provider)).collect::<Result<Vec<String>>>()?.join(", "))
+        },
+        _ => Err(Error::new(format!("cannot restore {} into {:?}", value, provider)))?,
+    })
+}
+
+/// Insert the records of `models` into MongoDB collections.
+async fn insert_documents(url: &str, models: &Vec<&'static Model>, groups: &IndexMap<String, Vec<Map<String, JsonValue>>>) -> Result<()> {
+    let mongo_error = |e: mongodb::error::Error| Error::new(format!("cannot restore into MongoDB: {}", e));
+    let client = Client::with_uri_str(url).await.map_err(mongo_error)?;
+    let Some(database) = client.default_database() else {
+        Err(Error::new("the MongoDB url has no database"))?
+    };
+    for model in models {
+        let mut documents = vec![];
+        for record in groups.get(&model.path().join(".")).unwrap() {
+            let mut document = Document::new();
+            for (field, value) in row(model, record)? {
+                document.insert(field.column_name.clone(), bson_value(&value)?);
+            }
+            documents.push(document);
+        }
+        if !documents.is_empty() {
+            database.collection::<Document>(&model.table_name).insert_many(documents, None).await.map_err(mongo_error)?;
+        }
+    }
+    Ok(())
+}
+
+fn bson_value(value: &Value) -> Result<Bson> {
+    Ok(match value {
+        Value::Null => Bson::Null,
+        Value::Bool(b) => Bson::Boolean(*b),
+        Value::Int(i) => Bson::Int32(*i),
+        Value::Int64(i) => Bson::Int64(*i),
+        Value::Float32(f) => Bson::Double(*f as f64),
+        Value::Float(f) => Bson::Double(*f),
+        Value::Decimal(d) => Bson::String(d.to_string()),
+        Value::String(s) => Bson::String(s.clone()),
+        Value::ObjectId(o) => Bson::ObjectId(*o),
+        Value::Date(d) => Bson::DateTime(bson::DateTime::from_chrono(d.and_hms_opt(0, 0, 0).unwrap().and_utc())),
+        Value::DateTime(d) => Bson::DateTime(bson::DateTime::from_chrono(*d)),
+        Value::Array(values) => Bson::Array(values.iter().map(bson_value).collect::<Result<Vec<Bson>>>()?),
+        _ => Err(Error::new(format!("cannot restore {} into MongoDB", value)))?,
+    })
+}
+
+pub(crate) fn value_for_type(value: &JsonValue, r#type: &Type) -> std::result::Result<Value, String> {
+    if value.is_null() {
+        return Ok(Value::Null);
+    }
+    let mismatch = || format!("expect {}, found {}", r#type, value);
+    Ok(match r#type {
+        Type::Optional(inner) => value_for_type(value, inner)?,
+        Type::Bool => Value::Bool(value.as_bool().ok_or_else(mismatch)?),
+        Type::Int => Value::Int(value.as_i64().ok_or_else(mismatch)? as i32),
+        Type::Int64 => Value::Int64(value.as_i64().ok_or_else(mismatch)?),
+        Type::Float32 => Value::Float32(value.as_f64().ok_or_else(mismatch)? as f32),
+        Type::Float => Value::Float(value.as_f64().ok_or_else(mismatch)?),
+        Type::Decimal => Value::Decimal(match value {
+            JsonValue::String(s) => BigDecimal::from_str(s).map_err(|_| mismatch())?,
+            JsonValue::Number(n) => BigDecimal::from_str(&n.to_string()).map_err(|_| mismatch())?,
+            _ => Err(mismatch())?,
+        }),
+        Type::String | Type::EnumVariant(_) => Value::String(value.as_str().ok_or_else(mismatch)?.to_owned()),
+        Type::ObjectId => Value::ObjectId(ObjectId::parse_str(value.as_str().ok_or_else(mismatch)?).map_err(|_| mismatch())?),
+        Type::Date => Value::Date(NaiveDate::parse_from_str(value.as_str().ok_or_else(mismatch)?, "%Y-%m-%d").map_err(|_| mismatch())?),
+        Type::DateTime => Value::DateTime(DateTime::parse_from_rfc3339(value.as_str().ok_or_else(mismatch)?).map_err(|_| mismatch())?.with_timezone(&Utc)),
+        Type::Array(inner) => Value::Array(value.as_array().ok_or_else(mismatch)?.iter().map(|v| value_for_type(v, inner)).collect::<std::result::Result<Vec<Value>, String>>()?),
+        _ => Err(format!("cannot restore values of type {}", r#type))?,
+    })
+}
+
+fn optional_foreign_keys(model: &'static Model) -> Vec<String> {
+    let mut result = vec![];
+    for relation in model.relations() {
+        if relation.has_foreign_key && !relation.is_required() {
+            result.extend(relation.iter().map(|(local, _)| local.to_owned()));
+        }
+    }
+    result
+}
+
+fn ordered_models(models: &Vec<&'static Model>) -> Result<Vec<&'static Model>> {
+    let mut deps: IndexMap<String, Vec<String>> = IndexMap::new();
+    for model in models {
+        let mut model_deps = vec![];
+        for relation in model.relations() {
+            let dep = relation.model_path().join(".");
+            // records referencing their own model are ordered by the database, not by us
+            if relation.has_foreign_key && relation.is_required() && dep != model.path().join(".") && models.iter().any(|m| m.path().join(".") == dep) {
+                model_deps.push(dep);
+            }
+        }
+        deps.insert(model.path().join("."), model_deps);
+    }
+    Ok(dependency_order(deps)?.iter().map(|name| *models.iter().find(|m| &m.path().join(".") == name).unwrap()).collect())
+}
+
+/// Names ordered after the names they depend on.
+fn dependency_order(mut deps: IndexMap<String, Vec<String>>) -> Result<Vec<String>> {
+    let mut result = vec![];
+    while !deps.is_empty() {
+        let ready: Vec<String> = deps.iter().filter(|(_, d)| d.is_empty()).map(|(n, _)| n.clone()).collect();
+        if ready.is_empty() {
+            Err(Error::new(format!("circular required relationship between these models: `{}`", deps.keys().cloned().collect::<Vec<String>>().join(","))))?
+        }
+        for name in &ready {
+            deps.shift_remove(name);
+        }
+        for model_deps in deps.values_mut() {
+            model_deps.retain(|d| !ready.contains(d));
+        }
+        result.extend(ready);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use teo_parser::parse;
+    use teo_runtime::namespace::Namespace;
+    use teo_runtime::schema::load::load_schema::load_schema;
+    use teo_runtime::stdlib::load::{load as load_std};
+    use super::*;
+
+    const SCHEMA: &str = r#"connector {
+  provider .sqlite
+  url "sqlite::memory:"
+}
+
+model Comment {
+  @id @autoIncrement
+  id: Int
+  postId: Int
+  @relation(fields: .postId, references: .id)
+  post: Post
+}
+
+model Post {
+  @id @autoIncrement
+  id: Int
+  authorId: Int
+  @relation(fields: .authorId, references: .id)
+  author: User
+  @relation(fields: .id, references: .postId)
+  comments: Comment[]
+}
+
+model User {
+  @id @autoIncrement
+  id: Int
+  @relation(fields: .id, references: .authorId)
+  posts: Post[]
+}
+"#;
+
+    #[test]
+    fn converts_values_with_field_types() {
+        assert_eq!(value_for_type(&json!(3), &Type::Int64), Ok(Value::Int64(3)));
+        assert_eq!(value_for_type(&json!(null), &Type::Int), Ok(Value::Null));
+        assert_eq!(value_for_type(&json!(true), &Type::Optional(Box::new(Type::Bool))), Ok(Value::Bool(true)));
+        assert_eq!(value_for_type(&json!("2024-02-29"), &Type::Date), Ok(Value::Date(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap())));
+        assert_eq!(value_for_type(&json!([1, 2]), &Type::Array(Box::new(Type::Int))), Ok(Value::Array(vec![Value::Int(1), Value::Int(2)])));
+        assert!(value_for_type(&json!("a"), &Type::Bool).is_err());
+        assert!(value_for_type(&json!("2024-02-30"), &Type::Date).is_err());
+    }
+
+    #[test]
+    fn escapes_literals_per_provider() {
+        let value = Value::String("it's C:\\temp".to_owned());
+        assert_eq!(sql_literal(&value, &Database::MySQL).unwrap(), "'it''s C:\\\\temp'");
+        assert_eq!(sql_literal(&value, &Database::PostgreSQL).unwrap(), "'it''s C:\\temp'");
+        assert_eq!(sql_literal(&Value::Bool(true), &Database::SQLite).unwrap(), "1");
+        assert_eq!(sql_literal(&Value::Bool(true), &Database::MySQL).unwrap(), "TRUE");
+        assert_eq!(sql_literal(&Value::Array(vec![]), &Database::PostgreSQL).unwrap(), "'{}'");
+        assert_eq!(sql_literal(&Value::Array(vec![Value::Int(1), Value::Int(2)]), &Database::PostgreSQL).unwrap(), "ARRAY[1, 2]");
+        assert!(sql_literal(&Value::Array(vec![]), &Database::MySQL).is_err());
+    }
+
+    #[tokio::test]
+    async fn orders_models_after_required_relations() {
+        let (schema, diagnostics) = parse("/dump/schema.teo", None, Some(HashMap::from([("/dump/schema.teo".to_owned(), SCHEMA.to_owned())])));
+        assert!(!diagnostics.has_errors());
+        let namespace = Box::leak(Box::new(Namespace::main()));
+        load_std(namespace);
+        load_schema(namespace, &schema, false).await.unwrap();
+        let namespace: &'static Namespace = namespace;
+        let models: Vec<&'static Model> = namespace.models.values().collect();
+        let ordered = ordered_models(&models).unwrap();
+        assert_eq!(ordered.iter().map(|m| m.path().join(".")).collect::<Vec<String>>(), vec!["User", "Post", "Comment"]);
+    }
+
+    #[test]
+    fn refuses_circular_relations() {
+        let deps = IndexMap::from([("A".to_owned(), vec!["B".to_owned()]), ("B".to_owned(), vec!["A".to_owned()])]);
+        assert!(format!("{}", dependency_order(deps).unwrap_err()).contains("circular required relationship"));
+    }
+}
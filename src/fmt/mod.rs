@@ -0,0 +1,332 @@
+use std::collections::BTreeSet;
+use std::fs;
+use teo_result::{Error, Result};
+use crate::app::ctx::Ctx;
+use crate::cli::command::FmtCommand;
+use crate::message::info_message;
+
+const INDENT: &str = "  ";
+
+/// Decorators are sorted by this order, others keep their order after these.
+const DECORATOR_ORDER: [&str; 10] = ["map", "id", "autoIncrement", "default", "unique", "index", "relation", "foreignKey", "readonly", "writeonly"];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Bracket {
+    Block,
+    Dictionary,
+    Array,
+    Paren,
+}
+
+/// Format every schema file reachable from the main schema.
+pub(crate) fn fmt(command: &FmtCommand) -> Result<()> {
+    let mut unformatted = vec![];
+    for source in Ctx::schema().sources().iter().filter(|s| !s.builtin) {
+        let Ok(content) = fs::read_to_string(&source.file_path) else { continue };
+        let formatted = format_source(&content);
+        if formatted == content { continue }
+        if command.check {
+            println!("{}", source.file_path);
+        } else if let Err(e) = fs::write(&source.file_path, &formatted) {
+            Err(Error::new(format!("cannot write {}: {}", source.file_path, e)))?
+        } else {
+            info_message(format!("formatted {}", source.file_path));
+        }
+        unformatted.push(source.file_path.clone());
+    }
+    if command.check && !unformatted.is_empty() {
+        Err(Error::new(format!("{} schema file(s) are not formatted", unformatted.len())))?
+    }
+    Ok(())
+}
+
+/// Format schema source code in the canonical style: two space indentation,
+/// at most one blank line, `name: Type` spacing with the types of adjacent
+/// fields aligned, sorted decorators and trailing commas in multiline
+/// dictionary and array literals. Comments are kept as they are.
+pub fn format_source(source: &str) -> String {
+    let mut lines: Vec<String> = vec![];
+    // lines declaring a `name: Type` field directly in a block
+    let mut fields: BTreeSet<usize> = BTreeSet::new();
+    // open brackets with the line they are opened on
+    let mut stack: Vec<(Bracket, usize)> = vec![];
+    // the last code character, a `{` opening a line in an array is a dictionary
+    let mut previous = ' ';
+    let mut in_block_comment = false;
+    for (line_number, raw_line) in source.lines().enumerate() {
+        if in_block_comment {
+            lines.push(raw_line.trim_end().to_owned());
+            in_block_comment = !raw_line.contains("*/");
+            continue
+        }
+        let line = raw_line.trim();
+        if line.is_empty() {
+            // at most one blank line, none at the start of a block
+            if !lines.is_empty() && !lines.last().unwrap().is_empty() && !lines.last().unwrap().ends_with(['{', '[', '(']) {
+                lines.push(String::new());
+            }
+            continue
+        }
+        let (code, comment) = split_comment(line);
+        let continuation = is_continuation(code);
+        let closes_first = code.starts_with(['}', ']', ')']);
+        let leading_closers = code.chars().take_while(|c| matches!(c, '}' | ']' | ')')).count();
+        let depth = indentation(&stack[..stack.len().saturating_sub(leading_closers)]) + if continuation { 1 } else { 0 };
+        if closes_first {
+            // no blank line at the end of a block
+            while lines.last().map_or(false, |l| l.is_empty()) {
+                lines.pop();
+            }
+            if let Some((Bracket::Dictionary | Bracket::Array, _)) = stack.last() {
+                add_trailing_comma(&mut lines);
+            }
+        } else if let Some((Bracket::Dictionary | Bracket::Array, _)) = stack.last() {
+            // a line continuing the previous element doesn't end it
+            if !continuation && !last_code(&lines).map_or(true, |l| l.ends_with(['{', '[', '(']) || ends_with_operator(l)) {
+                add_trailing_comma(&mut lines);
+                previous = ',';
+            }
+        }
+        let code = format_code(code.trim_end());
+        if !closes_first && !continuation && matches!(stack.last(), Some((Bracket::Block, _))) && field_name(&code).is_some() {
+            fields.insert(lines.len());
+        }
+        let formatted = match (code.is_empty(), comment) {
+            (true, Some(comment)) => comment.to_owned(),
+            (false, Some(comment)) => format!("{} {}", code, comment),
+            (_, None) => code.clone(),
+        };
+        lines.push(format!("{}{}", INDENT.repeat(depth), formatted));
+        update_stack(&code, line_number, &mut stack, &mut previous);
+        if code.contains("/*") && !code.contains("*/") {
+            in_block_comment = true;
+        }
+    }
+    while lines.last().map_or(false, |l| l.is_empty()) {
+        lines.pop();
+    }
+    align_fields(&mut lines, &fields);
+    let mut result = lines.join("\n");
+    result.push('\n');
+    result
+}
+
+fn format_code(code: &str) -> String {
+    if code.starts_with('@') {
+        if let Some(decorators) = split_decorators(code) {
+            return sort_decorators(decorators).join(" ");
+        }
+        return code.to_owned();
+    }
+    // `name:Type` and `name : Type` become `name: Type`
+    if let Some((name, r#type)) = code.split_once(':') {
+        let name = name.trim_end();
+        if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_') && !r#type.trim_start().starts_with(':') {
+            return format!("{}: {}", name, r#type.trim_start());
+        }
+    }
+    code.to_owned()
+}
+
+/// The name of a `name: Type` line.
+fn field_name(code: &str) -> Option<&str> {
+    let (name, r#type) = code.split_once(": ")?;
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') || r#type.is_empty() {
+        return None;
+    }
+    Some(name)
+}
+
+/// Align the types of fields declared on adjacent lines.
+fn align_fields(lines: &mut Vec<String>, fields: &BTreeSet<usize>) {
+    let mut runs: Vec<Vec<usize>> = vec![];
+    for index in fields.iter().cloned().filter(|i| *i < lines.len()) {
+        match runs.last_mut() {
+            Some(run) if *run.last().unwrap() + 1 == index && indent_of(&lines[index]) == indent_of(&lines[index - 1]) => run.push(index),
+            _ => runs.push(vec![index]),
+        }
+    }
+    for run in runs.into_iter().filter(|r| r.len() > 1) {
+        let width = run.iter().map(|i| field_name(lines[*i].trim_start()).unwrap().len()).max().unwrap();
+        for index in run {
+            let indent = indent_of(&lines[index]).to_owned();
+            let (name, rest) = lines[index].trim_start().split_once(": ").unwrap();
+            lines[index] = format!("{}{}: {}{}", indent, name, " ".repeat(width - name.len()), rest);
+        }
+    }
+}
+
+fn indent_of(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Whether a line continues the expression of the line above, e.g. the
+/// `.toDate` in a pipeline split over lines.
+fn is_continuation(code: &str) -> bool {
+    code.starts_with(['.', '|', '&', '*', '?']) && !code.starts_with("...")
+}
+
+fn ends_with_operator(code: &str) -> bool {
+    code.ends_with(['.', '|', '&', '+', '-', '*', '=', ':'])
+}
+
+/// The code of the last line which has code, without its comment.
+fn last_code(lines: &Vec<String>) -> Option<&str> {
+    lines.iter().rev().map(|l| split_comment(l.trim()).0.trim_end()).find(|c| !c.is_empty())
+}
+
+/// Split a line of decorators, or `None` if it's not a complete one.
+fn split_decorators(code: &str) -> Option<Vec<String>> {
+    let mut result = vec![];
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in code.chars() {
+        if in_string {
+            current.push(c);
+            if escaped { escaped = false } else if c == '\\' { escaped = true } else if c == '"' { in_string = false }
+            continue
+        }
+        match c {
+            '"' => { in_string = true; current.push(c) }
+            '(' | '[' | '{' => { depth += 1; current.push(c) }
+            ')' | ']' | '}' => { depth -= 1; current.push(c) }
+            ' ' | '\t' if depth == 0 => {
+                if !current.is_empty() { result.push(std::mem::take(&mut current)) }
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() { result.push(current) }
+    if depth != 0 || in_string || !result.iter().all(|d| d.starts_with('@')) {
+        return None;
+    }
+    Some(result)
+}
+
+fn sort_decorators(mut decorators: Vec<String>) -> Vec<String> {
+    let rank = |decorator: &String| {
+        let name = decorator.trim_start_matches('@').split(['(', '.']).next().unwrap_or("");
+        DECORATOR_ORDER.iter().position(|n| *n == name).unwrap_or(DECORATOR_ORDER.len())
+    };
+    decorators.sort_by_key(rank);
+    decorators
+}
+
+/// Separate a trailing `//` comment that is not inside a string.
+fn split_comment(line: &str) -> (&str, Option<&str>) {
+    let mut in_string = false;
+    let mut escaped = false;
+    let bytes = line.as_bytes();
+    for (i, c) in line.char_indices() {
+        if in_string {
+            if escaped { escaped = false } else if c == '\\' { escaped = true } else if c == '"' { in_string = false }
+            continue
+        }
+        if c == '"' {
+            in_string = true;
+        } else if c == '/' && bytes.get(i + 1) == Some(&b'/') {
+            return (&line[..i], Some(&line[i..]));
+        }
+    }
+    (line, None)
+}
+
+fn add_trailing_comma(lines: &mut Vec<String>) {
+    // skip blank and comment lines
+    let Some(last) = lines.iter_mut().rev().find(|l| !split_comment(l.trim()).0.is_empty()) else { return };
+    let (code, comment) = split_comment(last);
+    let code = code.trim_end();
+    if code.is_empty() || code.ends_with([',', '{', '[', '(']) {
+        return;
+    }
+    *last = match comment {
+        Some(comment) => format!("{}, {}", code, comment),
+        None => format!("{},", code),
+    };
+}
+
+/// Brackets opened on the same line add one level of indentation.
+fn indentation(stack: &[(Bracket, usize)]) -> usize {
+    let mut lines = stack.iter().map(|(_, line)| *line).collect::<Vec<usize>>();
+    lines.dedup();
+    lines.len()
+}
+
+fn update_stack(code: &str, line_number: usize, stack: &mut Vec<(Bracket, usize)>, previous: &mut char) {
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut in_regex = false;
+    for c in code.chars() {
+        if in_string {
+            if escaped { escaped = false } else if c == '\\' { escaped = true } else if c == '"' { in_string = false }
+            continue
+        }
+        if in_regex {
+            if escaped { escaped = false } else if c == '\\' { escaped = true } else if c == '/' { in_regex = false }
+            continue
+        }
+        match c {
+            '"' => in_string = true,
+            '/' if matches!(*previous, '(' | ',' | '[' | ':' | '=') => in_regex = true,
+            '{' => stack.push((if matches!(*previous, '(' | ',' | '[' | ':' | '=') { Bracket::Dictionary } else { Bracket::Block }, line_number)),
+            '[' => stack.push((Bracket::Array, line_number)),
+            '(' => stack.push((Bracket::Paren, line_number)),
+            '}' | ']' | ')' => { stack.pop(); }
+            _ => (),
+        }
+        if !c.is_whitespace() {
+            *previous = c;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indents_and_sorts_decorators() {
+        let source = "model User {\n@unique @map(\"mail\")\n    email:String\n\n\n@id\nid : Int\n}\n";
+        assert_eq!(format_source(source), "model User {\n  @map(\"mail\") @unique\n  email: String\n\n  @id\n  id: Int\n}\n");
+    }
+
+    #[test]
+    fn aligns_adjacent_fields() {
+        let source = "interface Address {\n  city: String\n  postalCode: String? // optional\n\n  id: Int\n}\n";
+        assert_eq!(format_source(source), "interface Address {\n  city:       String\n  postalCode: String? // optional\n\n  id: Int\n}\n");
+    }
+
+    #[test]
+    fn adds_trailing_commas_to_dictionaries() {
+        let source = "model Post {\n  @data({\n    \"tags\": [\n      \"a\"\n    ]\n  })\n  title: String\n}\n";
+        assert_eq!(format_source(source), "model Post {\n  @data({\n    \"tags\": [\n      \"a\",\n    ],\n  })\n  title: String\n}\n");
+    }
+
+    #[test]
+    fn keeps_multiline_pipeline_arguments_intact() {
+        let source = "model Post {\n  @onSave($when([\n    $isTrue,\n    $presents\n      .isString\n  ]))\n  title: String\n}\n";
+        assert_eq!(format_source(source), "model Post {\n  @onSave($when([\n    $isTrue,\n    $presents\n      .isString,\n  ]))\n  title: String\n}\n");
+    }
+
+    #[test]
+    fn adds_trailing_commas_to_dictionaries_opening_lines() {
+        let source = "@default([\n{\n\"a\": 1\n}\n{\n\"a\": 2\n}\n])\n";
+        assert_eq!(format_source(source), "@default([\n  {\n    \"a\": 1,\n  },\n  {\n    \"a\": 2,\n  },\n])\n");
+    }
+
+    #[test]
+    fn keeps_comments() {
+        let source = "// users\nmodel User {\n  /// the id\n  @id\n  id: Int // primary\n}\n";
+        assert_eq!(format_source(source), source);
+        let source = "// users\nconnector {\n  url \"http://a//b\" // comment\n}\n";
+        assert_eq!(format_source(source), source);
+    }
+
+    #[test]
+    fn is_idempotent() {
+        let source = "model Post {\n  @relation(fields: .authorId, references: .id)\n  author: User\n}\n";
+        assert_eq!(format_source(&format_source(source)), format_source(source));
+    }
+}
@@ -0,0 +1,237 @@
+pub mod models;
+
+use std::time::Duration;
+use actix_web::{HttpRequest, HttpResponse};
+use chrono::Utc;
+use ring::digest;
+use serde_json::{Map, Value as JsonValue};
+use teo_result::{Error, Result};
+use teo_runtime::connection::transaction;
+use teo_runtime::handler::r#match::HandlerMatch;
+use teo_runtime::namespace::Namespace;
+use teo_runtime::response::body::BodyInner;
+use teo_runtime::response::Response;
+use teo_runtime::teon;
+use crate::idempotency::models::idempotency_record::IdempotencyRecord;
+use crate::message::error_message;
+use crate::server::watch::RELOAD_LOCK;
+
+pub(crate) const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+const BUILTIN_MUTATIONS: [&str; 9] = ["create", "update", "upsert", "delete", "copy", "createMany", "updateMany", "copyMany", "deleteMany"];
+
+/// Opt-in idempotency for mutation endpoints.
+///
+/// When a request carries an `Idempotency-Key` header, its response is stored
+/// and replayed for repeats within `ttl`. Keys are scoped to the
+/// `Authorization` header, a response is only replayed to the same caller.
+/// Reusing a key with a different request is rejected with 422. Builtin
+/// mutation actions are covered by default, custom handlers are added by
+/// their path, e.g. `payments.charge`.
+#[derive(Debug, Clone)]
+pub struct Idempotency {
+    ttl: Duration,
+    builtin_mutations: bool,
+    handlers: Vec<String>,
+}
+
+impl Idempotency {
+
+    pub fn new() -> Self {
+        Self {
+            ttl: Duration::from_secs(24 * 60 * 60),
+            builtin_mutations: true,
+            handlers: vec![],
+        }
+    }
+
+    /// How long a stored response is replayed.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Whether builtin mutation actions like `create` are covered.
+    pub fn builtin_mutations(mut self, builtin_mutations: bool) -> Self {
+        self.builtin_mutations = builtin_mutations;
+        self
+    }
+
+    /// Cover a custom handler.
+    pub fn handler(mut self, path: impl Into<String>) -> Self {
+        self.handlers.push(path.into());
+        self
+    }
+
+    pub(crate) fn applies_to(&self, handler_match: &HandlerMatch, builtin: bool) -> bool {
+        if builtin {
+            self.builtin_mutations && BUILTIN_MUTATIONS.contains(&handler_match.name.as_str())
+        } else {
+            let full_path = handler_match.path.iter().chain(std::iter::once(&handler_match.name)).map(|s| s.as_str()).collect::<Vec<&str>>().join(".");
+            self.handlers.iter().any(|h| h == &full_path)
+        }
+    }
+}
+
+impl Default for Idempotency {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub(crate) enum IdempotencyBegin {
+    Proceed(IdempotencyClaim),
+    Replay(HttpResponse),
+}
+
+/// A claimed key. Unless the response is stored with
+/// [`complete`](IdempotencyClaim::complete), the key is released on drop,
+/// also when the handler panics, so that the client can retry.
+pub(crate) struct IdempotencyClaim {
+    record: Option<IdempotencyRecord>,
+}
+
+impl IdempotencyClaim {
+
+    /// Store the response for later replays. A failure is logged, the
+    /// request itself succeeded.
+    pub(crate) async fn complete(mut self, response: &Response) {
+        let record = self.record.take().unwrap();
+        let key = record.key();
+        if let Err(error) = complete(record, response).await {
+            error_message(format!("cannot store response of idempotency key {}: {}", key, error));
+        }
+    }
+}
+
+impl Drop for IdempotencyClaim {
+    fn drop(&mut self) {
+        if let Some(record) = self.record.take() {
+            actix_web::rt::spawn(async move {
+                let _guard = RELOAD_LOCK.read().await;
+                let _ = record.delete().await;
+            });
+        }
+    }
+}
+
+/// Make sure the std model backing idempotency is loaded.
+pub(crate) fn check_model(namespace: &Namespace) -> Result<()> {
+    if namespace.model_at_path(&vec!["std", "IdempotencyRecord"]).is_none() {
+        Err(Error::new("idempotency requires the `std.IdempotencyRecord` model"))?
+    }
+    Ok(())
+}
+
+pub(crate) fn idempotency_key(http_request: &HttpRequest) -> Option<String> {
+    http_request.headers().get(IDEMPOTENCY_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(|k| k.trim().to_owned())
+        .filter(|k| !k.is_empty())
+}
+
+/// Claim `key` for this request, or decide that the stored response should be
+/// replayed.
+pub(crate) async fn begin(idempotency: &Idempotency, key: String, http_request: &HttpRequest, body: &JsonValue, ctx: transaction::Ctx) -> Result<IdempotencyBegin> {
+    let key = format!("{}:{}", caller(http_request), key);
+    let fingerprint = fingerprint(http_request, body);
+    if let Some(record) = IdempotencyRecord::find_unique(teon!({
+        "where": { "key": key.as_str() }
+    }), ctx.clone()).await? {
+        if record.expires_at() < Utc::now() {
+            record.delete().await?;
+        } else if record.fingerprint() != fingerprint {
+            Err(error_with_code("this idempotency key is used by a different request", 422))?
+        } else if !record.completed() {
+            Err(error_with_code("a request with this idempotency key is in progress", 409))?
+        } else {
+            return Ok(IdempotencyBegin::Replay(replay(&record)));
+        }
+    }
+    let expires_at = Utc::now() + chrono::Duration::from_std(idempotency.ttl).unwrap_or(chrono::Duration::days(1));
+    let record = IdempotencyRecord::new(teon!({
+        "key": key.as_str(),
+        "fingerprint": fingerprint.as_str(),
+        "completed": false,
+        "expiresAt": expires_at,
+    }), ctx.clone()).await?;
+    // the unique key is the lock, a concurrent request loses here
+    if let Err(error) = record.save().await {
+        return match IdempotencyRecord::find_unique(teon!({ "where": { "key": key.as_str() } }), ctx).await? {
+            Some(_) => Err(error_with_code("a request with this idempotency key is in progress", 409)),
+            None => Err(error),
+        };
+    }
+    Ok(IdempotencyBegin::Proceed(IdempotencyClaim { record: Some(record) }))
+}
+
+/// Store the response for later replays. File responses are not stored.
+async fn complete(record: IdempotencyRecord, response: &Response) -> Result<()> {
+    let body = match response.body().inner.as_ref() {
+        BodyInner::Empty => String::new(),
+        BodyInner::String(content) => content.to_string(),
+        BodyInner::Teon(value) => match JsonValue::try_from(value) {
+            Ok(json_value) => json_value.to_string(),
+            Err(_) => return record.delete().await,
+        },
+        BodyInner::File(_) => return record.delete().await,
+    };
+    let mut headers = Map::new();
+    for key in response.headers().keys() {
+        if let Some(value) = response.headers().get(&key) {
+            headers.insert(key.to_string(), JsonValue::String(value.to_string()));
+        }
+    }
+    if let BodyInner::Teon(_) = response.body().inner.as_ref() {
+        headers.insert("content-type".to_owned(), JsonValue::String("application/json".to_owned()));
+    }
+    record.set_response_code(response.code() as i32);
+    record.set_response_headers(JsonValue::Object(headers).to_string());
+    record.set_response_body(body);
+    record.set_completed(true);
+    record.save().await
+}
+
+fn replay(record: &IdempotencyRecord) -> HttpResponse {
+    let code = record.response_code().and_then(|c| u16::try_from(c).ok()).unwrap_or(200);
+    let mut builder = HttpResponse::Ok();
+    builder.status(actix_web::http::StatusCode::from_u16(code).unwrap_or(actix_web::http::StatusCode::OK));
+    if let Some(headers) = record.response_headers() {
+        if let Ok(JsonValue::Object(headers)) = serde_json::from_str::<JsonValue>(&headers) {
+            for (key, value) in headers {
+                if let Some(value) = value.as_str() {
+                    builder.insert_header((key, value.to_owned()));
+                }
+            }
+        }
+    }
+    builder.insert_header(("idempotent-replayed", "true"));
+    builder.body(record.response_body().unwrap_or_default())
+}
+
+fn fingerprint(http_request: &HttpRequest, body: &JsonValue) -> String {
+    let mut context = digest::Context::new(&digest::SHA256);
+    context.update(http_request.method().as_str().as_bytes());
+    context.update(b" ");
+    context.update(http_request.path().as_bytes());
+    context.update(b"\n");
+    context.update(body.to_string().as_bytes());
+    hex(context.finish().as_ref())
+}
+
+/// Hash of the credentials of the request, the same key of two callers
+/// doesn't collide.
+fn caller(http_request: &HttpRequest) -> String {
+    let authorization = http_request.headers().get("authorization").map(|v| v.as_bytes()).unwrap_or_default();
+    hex(digest::digest(&digest::SHA256, authorization).as_ref())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn error_with_code(message: &str, code: u16) -> Error {
+    let mut error = Error::new(message);
+    error.code = code;
+    error
+}
@@ -0,0 +1,118 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::borrow::Borrow;
+use chrono::{DateTime, Utc};
+use key_path::path;
+use teo_runtime::connection::transaction;
+use teo_runtime::model;
+use crate::prelude::{Value, Result};
+
+/// Idempotency record
+pub struct IdempotencyRecord {
+    pub(super) inner: model::Object,
+}
+
+impl IdempotencyRecord {
+
+    /// Find a unique idempotency record.
+    pub async fn find_unique(query: impl Borrow<Value>, ctx: transaction::Ctx) -> Result<Option<IdempotencyRecord>> {
+        let model = ctx.namespace().model_at_path(&vec!["std", "IdempotencyRecord"]).unwrap();
+        Ok(ctx.find_unique(model, query.borrow(), None, path![]).await?)
+    }
+
+    /// Find many idempotency records.
+    pub async fn find_many(query: impl Borrow<Value>, ctx: transaction::Ctx) -> Result<Vec<IdempotencyRecord>> {
+        let model = ctx.namespace().model_at_path(&vec!["std", "IdempotencyRecord"]).unwrap();
+        Ok(ctx.find_many(model, query.borrow(), None, path![]).await?)
+    }
+
+    /// Create a new idempotency record.
+    pub async fn new(values: impl Borrow<Value>, ctx: transaction::Ctx) -> Result<Self> {
+        let model = ctx.namespace().model_at_path(&vec!["std", "IdempotencyRecord"]).unwrap();
+        Ok(ctx.create_object(model, values.borrow(), None).await?.into())
+    }
+
+    /// Save this idempotency record.
+    pub async fn save(&self) -> Result<()> {
+        self.inner.save().await
+    }
+
+    /// Delete this idempotency record.
+    pub async fn delete(&self) -> Result<()> {
+        self.inner.delete().await
+    }
+
+    /// Key
+    pub fn key(&self) -> String {
+        self.inner.get("key").unwrap()
+    }
+
+    /// Fingerprint
+    pub fn fingerprint(&self) -> String {
+        self.inner.get("fingerprint").unwrap()
+    }
+
+    /// Completed
+    pub fn completed(&self) -> bool {
+        self.inner.get("completed").unwrap()
+    }
+
+    pub fn set_completed(&self, new_value: bool) {
+        self.inner.set("completed", new_value).unwrap();
+    }
+
+    /// Response code
+    pub fn response_code(&self) -> Option<i32> {
+        self.inner.get("responseCode").unwrap()
+    }
+
+    pub fn set_response_code(&self, new_value: i32) {
+        self.inner.set("responseCode", new_value).unwrap();
+    }
+
+    /// Response headers
+    pub fn response_headers(&self) -> Option<String> {
+        self.inner.get("responseHeaders").unwrap()
+    }
+
+    pub fn set_response_headers(&self, new_value: impl Into<String>) {
+        self.inner.set("responseHeaders", new_value.into()).unwrap();
+    }
+
+    /// Response body
+    pub fn response_body(&self) -> Option<String> {
+        self.inner.get("responseBody").unwrap()
+    }
+
+    pub fn set_response_body(&self, new_value: impl Into<String>) {
+        self.inner.set("responseBody", new_value.into()).unwrap();
+    }
+
+    /// Expires at
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.inner.get("expiresAt").unwrap()
+    }
+}
+
+impl Into<model::Object> for IdempotencyRecord {
+    fn into(self) -> model::Object {
+        self.inner.clone()
+    }
+}
+
+impl From<model::Object> for IdempotencyRecord {
+    fn from(value: model::Object) -> Self {
+        Self { inner: value }
+    }
+}
+
+impl Debug for IdempotencyRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.inner, f)
+    }
+}
+
+impl Display for IdempotencyRecord {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.inner, f)
+    }
+}
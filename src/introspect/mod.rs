@@ -0,0 +1,118 @@
+mod sqlite;
+mod mysql;
+mod postgres;
+mod mongo;
+mod render;
+
+use std::fs;
+use std::sync::Arc;
+use teo_result::{Error, Result};
+use teo_runtime::connection::transaction::Transaction;
+use teo_runtime::database::database::Database;
+use teo_runtime::Value;
+use crate::app::ctx::Ctx;
+use crate::cli::command::DbPullCommand;
+use crate::message::info_message;
+
+/// A table read from the database.
+#[derive(Debug)]
+pub(crate) struct Table {
+    pub(crate) name: String,
+    pub(crate) columns: Vec<Column>,
+    pub(crate) primary_key: Vec<String>,
+    pub(crate) indexes: Vec<Index>,
+    pub(crate) foreign_keys: Vec<ForeignKey>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Column {
+    pub(crate) name: String,
+    /// The database type in lowercase, e.g. `varchar(255)`.
+    pub(crate) db_type: String,
+    pub(crate) nullable: bool,
+    pub(crate) auto_increment: bool,
+    /// The default as an SQL expression. Only columns of models have it,
+    /// introspected defaults aren't read.
+    pub(crate) default: Option<String>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Index {
+    pub(crate) name: String,
+    pub(crate) columns: Vec<String>,
+    pub(crate) unique: bool,
+}
+
+#[derive(Debug)]
+pub(crate) struct ForeignKey {
+    pub(crate) columns: Vec<String>,
+    pub(crate) referenced_table: String,
+    pub(crate) referenced_columns: Vec<String>,
+}
+
+/// Introspect the database of the main connector and write models for it.
+pub(crate) async fn pull(command: &DbPullCommand) -> Result<()> {
+    let ctx = Ctx::conn_ctx();
+    let Some(connector) = ctx.namespace().connector.as_ref() else {
+        Err(Error::new("db pull requires a `connector` config"))?
+    };
+    let Some(connection) = ctx.namespace().connection.as_ref() else {
+        Err(Error::new("db pull requires a connected database"))?
+    };
+    let tables = if connector.provider.is_mongo() {
+        mongo::tables(&connector.url).await?
+    } else {
+        tables(&connector.provider, &connection.no_transaction().await?).await?
+    };
+    let output = render::render(&tables, connector.provider);
+    match &command.out {
+        Some(path) => {
+            if let Err(e) = fs::write(path, output) {
+                Err(Error::new(format!("cannot write {}: {}", path, e)))?
+            }
+            info_message(format!("wrote {} model(s) into {}", tables.len(), path));
+        }
+        None => print!("{}", output),
+    }
+    Ok(())
+}
+
+/// The tables of a SQL database, without the ones Teo keeps for itself.
+pub(crate) async fn tables(provider: &Database, transaction: &Arc<dyn Transaction>) -> Result<Vec<Table>> {
+    let tables = match provider {
+        Database::SQLite => sqlite::tables(transaction).await?,
+        Database::MySQL => mysql::tables(transaction).await?,
+        Database::PostgreSQL => postgres::tables(transaction).await?,
+        Database::MongoDB => Err(Error::new("MongoDB has no tables, its collections are sampled"))?,
+    };
+    Ok(tables.into_iter().filter(|t| !t.name.starts_with("_teo_")).collect())
+}
+
+pub(super) async fn rows(transaction: &Arc<dyn Transaction>, sql: impl Into<String>) -> Result<Vec<Value>> {
+    let result = transaction.query_raw(&Value::String(sql.into())).await?;
+    Ok(result.as_array().cloned().unwrap_or_default())
+}
+
+pub(super) fn string(row: &Value, key: &str) -> String {
+    match row.as_dictionary().and_then(|d| d.get(key)) {
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Int(i)) => i.to_string(),
+        Some(Value::Int64(i)) => i.to_string(),
+        _ => String::new(),
+    }
+}
+
+pub(super) fn int(row: &Value, key: &str) -> i64 {
+    match row.as_dictionary().and_then(|d| d.get(key)) {
+        Some(Value::Int(i)) => *i as i64,
+        Some(Value::Int64(i)) => *i,
+        Some(Value::Bool(b)) => *b as i64,
+        Some(Value::String(s)) => s.parse().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Quote a string literal for introspection and history queries.
+pub(crate) fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
@@ -0,0 +1,124 @@
+use std::collections::BTreeMap;
+use bson::{doc, Bson, Document};
+use futures_util::TryStreamExt;
+use mongodb::Client;
+use teo_result::{Error, Result};
+use crate::introspect::{Column, Index, Table};
+
+/// Documents read from every collection to guess its fields.
+const SAMPLE_SIZE: i64 = 1000;
+
+/// Guess the fields of every collection from a sample of its documents. A
+/// field is optional when some sampled documents lack it or hold `null`,
+/// and has the type names of all values seen, e.g. `int|string`.
+pub(super) async fn tables(url: &str) -> Result<Vec<Table>> {
+    let client = Client::with_uri_str(url).await.map_err(error)?;
+    let Some(database) = client.default_database() else {
+        Err(Error::new("the MongoDB url has no database"))?
+    };
+    let mut names = database.list_collection_names(None).await.map_err(error)?;
+    names.retain(|n| !n.starts_with("system."));
+    names.sort();
+    let mut result = vec![];
+    for name in names {
+        let collection = database.collection::<Document>(&name);
+        let mut cursor = collection.aggregate(vec![doc! { "$sample": { "size": SAMPLE_SIZE } }], None).await.map_err(error)?;
+        let mut documents = vec![];
+        while let Some(document) = cursor.try_next().await.map_err(error)? {
+            documents.push(document);
+        }
+        let mut indexes = vec![];
+        let mut cursor = collection.list_indexes(None).await.map_err(error)?;
+        while let Some(index) = cursor.try_next().await.map_err(error)? {
+            let options = index.options.unwrap_or_default();
+            let name = options.name.unwrap_or_default();
+            if name == "_id_" { continue }
+            indexes.push(Index {
+                name,
+                columns: index.keys.keys().cloned().collect(),
+                unique: options.unique.unwrap_or(false),
+            });
+        }
+        result.push(Table {
+            name,
+            columns: columns(&documents),
+            primary_key: vec!["_id".to_owned()],
+            indexes,
+            foreign_keys: vec![],
+        });
+    }
+    Ok(result)
+}
+
+/// Fields in the order they're first seen, `_id` first.
+fn columns(documents: &[Document]) -> Vec<Column> {
+    let mut order: Vec<String> = vec!["_id".to_owned()];
+    let mut seen: BTreeMap<String, (Vec<&'static str>, usize)> = BTreeMap::new();
+    for document in documents {
+        for (key, value) in document {
+            if !order.contains(key) {
+                order.push(key.clone());
+            }
+            let (types, count) = seen.entry(key.clone()).or_default();
+            *count += 1;
+            let type_name = type_name(value);
+            if !types.contains(&type_name) {
+                types.push(type_name);
+            }
+        }
+    }
+    order.into_iter().filter_map(|name| {
+        let (types, count) = seen.remove(&name)?;
+        let nullable = count < documents.len() || types.contains(&"null");
+        let types: Vec<&str> = types.into_iter().filter(|t| *t != "null").collect();
+        Some(Column {
+            name,
+            db_type: if types.is_empty() { "null".to_owned() } else { types.join("|") },
+            nullable,
+            auto_increment: false,
+            default: None,
+        })
+    }).collect()
+}
+
+fn type_name(value: &Bson) -> &'static str {
+    match value {
+        Bson::ObjectId(_) => "objectId",
+        Bson::String(_) => "string",
+        Bson::Int32(_) => "int",
+        Bson::Int64(_) => "long",
+        Bson::Double(_) => "double",
+        Bson::Decimal128(_) => "decimal",
+        Bson::Boolean(_) => "bool",
+        Bson::DateTime(_) => "date",
+        Bson::Null => "null",
+        Bson::Array(_) => "array",
+        Bson::Document(_) => "object",
+        _ => "other",
+    }
+}
+
+fn error(error: mongodb::error::Error) -> Error {
+    Error::new(format!("{}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guesses_fields_from_sampled_documents() {
+        let documents = vec![
+            doc! { "_id": bson::oid::ObjectId::new(), "name": "a", "age": 1 },
+            doc! { "_id": bson::oid::ObjectId::new(), "name": "b", "age": "unknown", "email": Bson::Null },
+        ];
+        let columns = columns(&documents);
+        let summary: Vec<(&str, &str, bool)> = columns.iter().map(|c| (c.name.as_str(), c.db_type.as_str(), c.nullable)).collect();
+        assert_eq!(summary, vec![
+            ("_id", "objectId", false),
+            ("name", "string", false),
+            ("age", "int|string", false),
+            ("email", "null", true),
+        ]);
+    }
+}
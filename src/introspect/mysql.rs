@@ -0,0 +1,62 @@
+use std::sync::Arc;
+use teo_result::Result;
+use teo_runtime::connection::transaction::Transaction;
+use crate::introspect::{Column, ForeignKey, Index, int, quote, rows, string, Table};
+
+pub(super) async fn tables(transaction: &Arc<dyn Transaction>) -> Result<Vec<Table>> {
+    let mut result = vec![];
+    let names = rows(transaction, "SELECT table_name AS name FROM information_schema.tables WHERE table_schema = DATABASE() AND table_type = 'BASE TABLE' ORDER BY table_name").await?;
+    for name in names.iter().map(|r| string(r, "name")) {
+        let columns = rows(transaction, format!(
+            "SELECT column_name AS name, column_type AS type, is_nullable AS nullable, extra AS extra FROM information_schema.columns WHERE table_schema = DATABASE() AND table_name = {} ORDER BY ordinal_position",
+            quote(&name),
+        )).await?.iter().map(|row| Column {
+            name: string(row, "name"),
+            db_type: string(row, "type").to_lowercase(),
+            nullable: string(row, "nullable") == "YES",
+            auto_increment: string(row, "extra").contains("auto_increment"),
+            default: None,
+        }).collect();
+        let mut primary_key = vec![];
+        let mut indexes: Vec<Index> = vec![];
+        for row in rows(transaction, format!(
+            "SELECT index_name AS name, non_unique AS non_unique, column_name AS column_name FROM information_schema.statistics WHERE table_schema = DATABASE() AND table_name = {} ORDER BY index_name, seq_in_index",
+            quote(&name),
+        )).await? {
+            let index_name = string(&row, "name");
+            let column_name = string(&row, "column_name");
+            if index_name == "PRIMARY" {
+                primary_key.push(column_name);
+            } else if let Some(index) = indexes.iter_mut().find(|i| i.name == index_name) {
+                index.columns.push(column_name);
+            } else {
+                indexes.push(Index { name: index_name, columns: vec![column_name], unique: int(&row, "non_unique") == 0 });
+            }
+        }
+        let mut foreign_keys: Vec<(String, ForeignKey)> = vec![];
+        for row in rows(transaction, format!(
+            "SELECT constraint_name AS name, column_name AS column_name, referenced_table_name AS referenced_table, referenced_column_name AS referenced_column FROM information_schema.key_column_usage WHERE table_schema = DATABASE() AND table_name = {} AND referenced_table_name IS NOT NULL ORDER BY constraint_name, ordinal_position",
+            quote(&name),
+        )).await? {
+            let constraint_name = string(&row, "name");
+            if let Some((_, foreign_key)) = foreign_keys.iter_mut().find(|(n, _)| n == &constraint_name) {
+                foreign_key.columns.push(string(&row, "column_name"));
+                foreign_key.referenced_columns.push(string(&row, "referenced_column"));
+            } else {
+                foreign_keys.push((constraint_name, ForeignKey {
+                    columns: vec![string(&row, "column_name")],
+                    referenced_table: string(&row, "referenced_table"),
+                    referenced_columns: vec![string(&row, "referenced_column")],
+                }));
+            }
+        }
+        result.push(Table {
+            name,
+            columns,
+            primary_key,
+            indexes,
+            foreign_keys: foreign_keys.into_iter().map(|(_, f)| f).collect(),
+        });
+    }
+    Ok(result)
+}
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+use teo_result::Result;
+use teo_runtime::connection::transaction::Transaction;
+use teo_runtime::Value;
+use crate::introspect::{Column, ForeignKey, Index, quote, rows, string, Table};
+
+pub(super) async fn tables(transaction: &Arc<dyn Transaction>) -> Result<Vec<Table>> {
+    let mut result = vec![];
+    let names = rows(transaction, "SELECT table_name AS name FROM information_schema.tables WHERE table_schema = current_schema() AND table_type = 'BASE TABLE' ORDER BY table_name").await?;
+    for name in names.iter().map(|r| string(r, "name")) {
+        let columns = rows(transaction, format!(
+            "SELECT column_name AS name, CASE WHEN character_maximum_length IS NULL THEN data_type ELSE data_type || '(' || character_maximum_length || ')' END AS type, is_nullable AS nullable, COALESCE(column_default, '') AS default_value, is_identity FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = {} ORDER BY ordinal_position",
+            quote(&name),
+        )).await?.iter().map(|row| Column {
+            name: string(row, "name"),
+            db_type: string(row, "type").to_lowercase(),
+            nullable: string(row, "nullable") == "YES",
+            auto_increment: string(row, "default_value").starts_with("nextval(") || string(row, "is_identity") == "YES",
+            default: None,
+        }).collect();
+        let primary_key = rows(transaction, format!(
+            "SELECT kcu.column_name AS name FROM information_schema.table_constraints tc JOIN information_schema.key_column_usage kcu ON tc.constraint_name = kcu.constraint_name AND tc.table_schema = kcu.table_schema WHERE tc.table_schema = current_schema() AND tc.table_name = {} AND tc.constraint_type = 'PRIMARY KEY' ORDER BY kcu.ordinal_position",
+            quote(&name),
+        )).await?.iter().map(|row| string(row, "name")).collect();
+        let mut indexes: Vec<Index> = vec![];
+        for row in rows(transaction, format!(
+            "SELECT i.relname AS name, ix.indisunique AS is_unique, a.attname AS column_name FROM pg_class t JOIN pg_index ix ON t.oid = ix.indrelid JOIN pg_class i ON i.oid = ix.indexrelid JOIN LATERAL unnest(ix.indkey) WITH ORDINALITY AS k(attnum, ord) ON true JOIN pg_attribute a ON a.attrelid = t.oid AND a.attnum = k.attnum WHERE t.relname = {} AND t.relnamespace = current_schema()::regnamespace AND NOT ix.indisprimary ORDER BY i.relname, k.ord",
+            quote(&name),
+        )).await? {
+            let index_name = string(&row, "name");
+            let column_name = string(&row, "column_name");
+            let unique = matches!(row.as_dictionary().and_then(|d| d.get("is_unique")), Some(Value::Bool(true)));
+            match indexes.iter_mut().find(|i| i.name == index_name) {
+                Some(index) => index.columns.push(column_name),
+                None => indexes.push(Index { name: index_name, columns: vec![column_name], unique }),
+            }
+        }
+        let mut foreign_keys: Vec<(String, ForeignKey)> = vec![];
+        for row in rows(transaction, format!(
+            "SELECT c.conname AS name, a.attname AS column_name, rt.relname AS referenced_table, ra.attname AS referenced_column FROM pg_constraint c JOIN pg_class t ON t.oid = c.conrelid JOIN pg_class rt ON rt.oid = c.confrelid JOIN LATERAL unnest(c.conkey, c.confkey) WITH ORDINALITY AS k(attnum, referenced_attnum, ord) ON true JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = k.attnum JOIN pg_attribute ra ON ra.attrelid = c.confrelid AND ra.attnum = k.referenced_attnum WHERE c.contype = 'f' AND t.relname = {} AND t.relnamespace = current_schema()::regnamespace ORDER BY c.conname, k.ord",
+            quote(&name),
+        )).await? {
+            let constraint_name = string(&row, "name");
+            if let Some((_, foreign_key)) = foreign_keys.iter_mut().find(|(n, _)| n == &constraint_name) {
+                foreign_key.columns.push(string(&row, "column_name"));
+                foreign_key.referenced_columns.push(string(&row, "referenced_column"));
+            } else {
+                foreign_keys.push((constraint_name, ForeignKey {
+                    columns: vec![string(&row, "column_name")],
+                    referenced_table: string(&row, "referenced_table"),
+                    referenced_columns: vec![string(&row, "referenced_column")],
+                }));
+            }
+        }
+        result.push(Table {
+            name,
+            columns,
+            primary_key,
+            indexes,
+            foreign_keys: foreign_keys.into_iter().map(|(_, f)| f).collect(),
+        });
+    }
+    Ok(result)
+}
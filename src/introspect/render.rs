@@ -0,0 +1,305 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use teo_runtime::database::database::Database;
+use crate::introspect::{Column, Table};
+
+/// Render tables as `.teo` model declarations.
+pub(super) fn render(tables: &Vec<Table>, provider: Database) -> String {
+    let mut output = String::new();
+    for table in tables {
+        let mut used_names: BTreeSet<String> = table.columns.iter().map(|c| field_name(&c.name)).collect();
+        let _ = writeln!(output);
+        if model_name(&table.name) != table.name {
+            let _ = writeln!(output, "@map(\"{}\")", table.name);
+        }
+        if table.primary_key.len() > 1 {
+            let _ = writeln!(output, "@id([{}])", references(&table.primary_key));
+        }
+        for index in table.indexes.iter().filter(|i| i.columns.len() > 1) {
+            let _ = writeln!(output, "@{}([{}])", if index.unique { "unique" } else { "index" }, references(&index.columns));
+        }
+        let _ = writeln!(output, "model {} {{", model_name(&table.name));
+        for column in &table.columns {
+            let mut decorators = vec![];
+            if field_name(&column.name) != column.name {
+                decorators.push(format!("@map(\"{}\")", column.name));
+            }
+            if table.primary_key.len() == 1 && table.primary_key[0] == column.name {
+                decorators.push("@id".to_owned());
+            }
+            if column.auto_increment {
+                decorators.push("@autoIncrement".to_owned());
+            }
+            if provider == Database::MongoDB && column.name == "_id" && column.db_type == "objectId" {
+                decorators.push("@auto".to_owned());
+            }
+            for index in table.indexes.iter().filter(|i| i.columns.len() == 1 && i.columns[0] == column.name) {
+                decorators.push(if index.unique { "@unique".to_owned() } else { "@index".to_owned() });
+            }
+            let r#type = field_type(column, provider);
+            if let Some(db) = db_type_decorator(column, provider) {
+                decorators.push(db);
+            }
+            if !decorators.is_empty() {
+                let _ = writeln!(output, "  {}", decorators.join(" "));
+            }
+            match r#type {
+                Some(r#type) => { let _ = writeln!(output, "  {}: {}{}", field_name(&column.name), r#type, if column.nullable { "?" } else { "" }); }
+                None => { let _ = writeln!(output, "  // unsupported type `{}`\n  {}: String{}", column.db_type, field_name(&column.name), if column.nullable { "?" } else { "" }); }
+            }
+        }
+        // relations to referenced tables
+        for foreign_key in &table.foreign_keys {
+            let name = unique_name(relation_name(&foreign_key.columns, &foreign_key.referenced_table), &mut used_names);
+            let optional = table.columns.iter().any(|c| foreign_key.columns.contains(&c.name) && c.nullable);
+            let _ = writeln!(output, "  @relation(fields: {}, references: {})", relation_references(&foreign_key.columns), relation_references(&foreign_key.referenced_columns));
+            let _ = writeln!(output, "  {}: {}{}", name, model_name(&foreign_key.referenced_table), if optional { "?" } else { "" });
+        }
+        // relations from referencing tables
+        for other in tables {
+            for foreign_key in other.foreign_keys.iter().filter(|f| f.referenced_table == table.name) {
+                let one_to_one = other.indexes.iter().any(|i| i.unique && i.columns == foreign_key.columns) || other.primary_key == foreign_key.columns;
+                let base_name = field_name(&other.name);
+                let name = unique_name(if one_to_one || base_name.ends_with('s') { base_name } else { format!("{}s", base_name) }, &mut used_names);
+                let _ = writeln!(output, "  @relation(fields: {}, references: {})", relation_references(&foreign_key.referenced_columns), relation_references(&foreign_key.columns));
+                let _ = writeln!(output, "  {}: {}{}", name, model_name(&other.name), if one_to_one { "?" } else { "[]" });
+            }
+        }
+        let _ = writeln!(output, "}}");
+    }
+    output
+}
+
+fn field_type(column: &Column, provider: Database) -> Option<&'static str> {
+    if provider == Database::MongoDB {
+        return mongo_field_type(column);
+    }
+    let db_type = column.db_type.as_str();
+    let base = db_type.split('(').next().unwrap_or("").trim();
+    let base = base.trim_end_matches(" unsigned");
+    Some(match base {
+        "tinyint" if provider == Database::MySQL && db_type.starts_with("tinyint(1)") => "Bool",
+        "bool" | "boolean" | "bit" => "Bool",
+        "int" | "integer" | "int4" | "int2" | "smallint" | "mediumint" | "tinyint" | "serial" | "smallserial" => "Int",
+        "bigint" | "int8" | "bigserial" => "Int64",
+        "real" | "float" | "float4" => "Float32",
+        "double" | "double precision" | "float8" => "Float",
+        "decimal" | "numeric" => "Decimal",
+        "date" => "Date",
+        "datetime" | "timestamp" | "timestamptz" | "timestamp without time zone" | "timestamp with time zone" => "DateTime",
+        "char" | "varchar" | "character" | "character varying" | "text" | "tinytext" | "mediumtext" | "longtext" | "uuid" | "citext" | "clob" => "String",
+        _ => return None,
+    })
+}
+
+/// Fields of sampled documents with values of a single type.
+fn mongo_field_type(column: &Column) -> Option<&'static str> {
+    Some(match column.db_type.as_str() {
+        "objectId" => "ObjectId",
+        "string" => "String",
+        "int" => "Int",
+        "long" => "Int64",
+        "double" => "Float",
+        "decimal" => "Decimal",
+        "bool" => "Bool",
+        "date" => "DateTime",
+        _ => return None,
+    })
+}
+
+fn db_type_decorator(column: &Column, provider: Database) -> Option<String> {
+    if provider == Database::SQLite || provider == Database::MongoDB {
+        return None;
+    }
+    let db_type = column.db_type.as_str();
+    if let Some(length) = db_type.strip_prefix("varchar(").or(db_type.strip_prefix("character varying(")).and_then(|s| s.strip_suffix(')')) {
+        return Some(format!("@db(.varChar({}))", length));
+    }
+    match db_type {
+        "text" => Some("@db(.text)".to_owned()),
+        "mediumtext" => Some("@db(.mediumText)".to_owned()),
+        "longtext" => Some("@db(.longText)".to_owned()),
+        _ => None,
+    }
+}
+
+fn relation_name(columns: &Vec<String>, referenced_table: &str) -> String {
+    if columns.len() == 1 {
+        let column = columns[0].as_str();
+        if let Some(stripped) = column.strip_suffix("_id").or(column.strip_suffix("Id")) {
+            if !stripped.is_empty() {
+                return field_name(stripped);
+            }
+        }
+    }
+    field_name(referenced_table)
+}
+
+fn unique_name(name: String, used_names: &mut BTreeSet<String>) -> String {
+    let mut result = name.clone();
+    let mut suffix = 2;
+    while used_names.contains(&result) {
+        result = format!("{}{}", name, suffix);
+        suffix += 1;
+    }
+    used_names.insert(result.clone());
+    result
+}
+
+fn references(columns: &Vec<String>) -> String {
+    columns.iter().map(|c| format!(".{}", field_name(c))).collect::<Vec<String>>().join(", ")
+}
+
+fn relation_references(columns: &Vec<String>) -> String {
+    if columns.len() == 1 {
+        format!(".{}", field_name(&columns[0]))
+    } else {
+        format!("[{}]", references(columns))
+    }
+}
+
+fn model_name(table_name: &str) -> String {
+    let camel = field_name(table_name);
+    let mut chars = camel.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn field_name(column_name: &str) -> String {
+    let mut result = String::new();
+    let mut upper_next = false;
+    for (i, c) in column_name.chars().enumerate() {
+        if c == '_' || c == '-' || c == ' ' {
+            upper_next = !result.is_empty();
+        } else if upper_next {
+            result.extend(c.to_uppercase());
+            upper_next = false;
+        } else if i == 0 || result.is_empty() {
+            result.extend(c.to_lowercase());
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use teo_parser::parse;
+    use super::*;
+    use crate::introspect::{ForeignKey, Index};
+
+    fn column(name: &str, db_type: &str, nullable: bool) -> Column {
+        Column { name: name.to_owned(), db_type: db_type.to_owned(), nullable, auto_increment: false, default: None }
+    }
+
+    #[test]
+    fn renders_columns_keys_and_indexes() {
+        let tables = vec![Table {
+            name: "blog_posts".to_owned(),
+            columns: vec![
+                Column { auto_increment: true, ..column("id", "integer", false) },
+                column("title", "varchar(255)", false),
+                column("published_at", "timestamp", true),
+                column("location", "point", true),
+            ],
+            primary_key: vec!["id".to_owned()],
+            indexes: vec![Index { name: "blog_posts_title".to_owned(), columns: vec!["title".to_owned()], unique: true }],
+            foreign_keys: vec![],
+        }];
+        assert_eq!(render(&tables, Database::PostgreSQL), r#"
+@map("blog_posts")
+model BlogPosts {
+  @id @autoIncrement
+  id: Int
+  @unique @db(.varChar(255))
+  title: String
+  @map("published_at")
+  publishedAt: DateTime?
+  // unsupported type `point`
+  location: String?
+}
+"#);
+    }
+
+    #[test]
+    fn renders_relations_on_both_sides() {
+        let tables = vec![
+            Table {
+                name: "User".to_owned(),
+                columns: vec![column("id", "int", false)],
+                primary_key: vec!["id".to_owned()],
+                indexes: vec![],
+                foreign_keys: vec![],
+            },
+            Table {
+                name: "Post".to_owned(),
+                columns: vec![column("id", "int", false), column("authorId", "int", true)],
+                primary_key: vec!["id".to_owned()],
+                indexes: vec![],
+                foreign_keys: vec![ForeignKey { columns: vec!["authorId".to_owned()], referenced_table: "User".to_owned(), referenced_columns: vec!["id".to_owned()] }],
+            },
+        ];
+        let output = render(&tables, Database::MySQL);
+        assert!(output.contains("model User {\n  @id\n  id: Int\n  @relation(fields: .id, references: .authorId)\n  posts: Post[]\n}"));
+        assert!(output.contains("  @relation(fields: .authorId, references: .id)\n  author: User?\n}"));
+    }
+
+    #[test]
+    fn renders_composite_keys_and_mysql_booleans() {
+        let tables = vec![Table {
+            name: "Membership".to_owned(),
+            columns: vec![column("userId", "int", false), column("groupId", "int", false), column("active", "tinyint(1)", false)],
+            primary_key: vec!["userId".to_owned(), "groupId".to_owned()],
+            indexes: vec![Index { name: "by_group".to_owned(), columns: vec!["groupId".to_owned(), "active".to_owned()], unique: false }],
+            foreign_keys: vec![],
+        }];
+        let output = render(&tables, Database::MySQL);
+        assert!(output.starts_with("\n@id([.userId, .groupId])\n@index([.groupId, .active])\nmodel Membership {"));
+        assert!(output.contains("  active: Bool\n"));
+    }
+
+    #[test]
+    fn rendered_models_parse() {
+        let tables = vec![
+            Table {
+                name: "users".to_owned(),
+                columns: vec![Column { auto_increment: true, ..column("id", "int", false) }, column("email", "varchar(191)", false)],
+                primary_key: vec!["id".to_owned()],
+                indexes: vec![Index { name: "users_email".to_owned(), columns: vec!["email".to_owned()], unique: true }],
+                foreign_keys: vec![],
+            },
+            Table {
+                name: "memberships".to_owned(),
+                columns: vec![column("user_id", "int", false), column("group_id", "int", false), column("active", "tinyint(1)", false)],
+                primary_key: vec!["user_id".to_owned(), "group_id".to_owned()],
+                indexes: vec![
+                    Index { name: "by_group".to_owned(), columns: vec!["group_id".to_owned(), "active".to_owned()], unique: false },
+                    Index { name: "by_user".to_owned(), columns: vec!["user_id".to_owned(), "active".to_owned()], unique: true },
+                ],
+                foreign_keys: vec![ForeignKey { columns: vec!["user_id".to_owned()], referenced_table: "users".to_owned(), referenced_columns: vec!["id".to_owned()] }],
+            },
+        ];
+        let schema = format!("connector {{\n  provider .mysql\n  url \"mysql://127.0.0.1:3306/test\"\n}}\n{}", render(&tables, Database::MySQL));
+        let (_, diagnostics) = parse("/pull/schema.teo", None, Some(HashMap::from([("/pull/schema.teo".to_owned(), schema)])));
+        assert!(!diagnostics.has_errors());
+    }
+
+    #[test]
+    fn renders_sampled_collections() {
+        let tables = vec![Table {
+            name: "users".to_owned(),
+            columns: vec![column("_id", "objectId", false), column("age", "int|string", true), column("joined", "date", false)],
+            primary_key: vec!["_id".to_owned()],
+            indexes: vec![],
+            foreign_keys: vec![],
+        }];
+        let output = render(&tables, Database::MongoDB);
+        assert!(output.contains("  @map(\"_id\") @id @auto\n  id: ObjectId\n"));
+        assert!(output.contains("  // unsupported type `int|string`\n  age: String?\n"));
+        assert!(output.contains("  joined: DateTime\n"));
+    }
+}
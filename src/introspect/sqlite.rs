@@ -0,0 +1,66 @@
+use std::sync::Arc;
+use teo_result::Result;
+use teo_runtime::connection::transaction::Transaction;
+use crate::introspect::{Column, ForeignKey, Index, int, quote, rows, string, Table};
+
+pub(super) async fn tables(transaction: &Arc<dyn Transaction>) -> Result<Vec<Table>> {
+    let mut result = vec![];
+    let names = rows(transaction, "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name").await?;
+    for name in names.iter().map(|r| string(r, "name")) {
+        let mut columns = vec![];
+        let mut primary_key = vec![];
+        for row in rows(transaction, format!("PRAGMA table_info({})", quote(&name))).await? {
+            let column_name = string(&row, "name");
+            let pk = int(&row, "pk");
+            let db_type = string(&row, "type").to_lowercase();
+            if pk > 0 {
+                primary_key.push((pk, column_name.clone()));
+            }
+            columns.push(Column {
+                // an integer primary key is an alias of the rowid
+                auto_increment: pk == 1 && db_type == "integer",
+                nullable: int(&row, "notnull") == 0 && pk == 0,
+                db_type,
+                name: column_name,
+                default: None,
+            });
+        }
+        primary_key.sort();
+        if primary_key.len() > 1 {
+            columns.iter_mut().for_each(|c| c.auto_increment = false);
+        }
+        let mut indexes = vec![];
+        for row in rows(transaction, format!("PRAGMA index_list({})", quote(&name))).await? {
+            if string(&row, "origin") == "pk" { continue }
+            let index_name = string(&row, "name");
+            let index_columns = rows(transaction, format!("PRAGMA index_info({})", quote(&index_name))).await?;
+            indexes.push(Index {
+                name: index_name,
+                columns: index_columns.iter().map(|c| string(c, "name")).collect(),
+                unique: int(&row, "unique") == 1,
+            });
+        }
+        let mut foreign_keys: Vec<(i64, ForeignKey)> = vec![];
+        for row in rows(transaction, format!("PRAGMA foreign_key_list({})", quote(&name))).await? {
+            let id = int(&row, "id");
+            if let Some((_, foreign_key)) = foreign_keys.iter_mut().find(|(i, _)| *i == id) {
+                foreign_key.columns.push(string(&row, "from"));
+                foreign_key.referenced_columns.push(string(&row, "to"));
+            } else {
+                foreign_keys.push((id, ForeignKey {
+                    columns: vec![string(&row, "from")],
+                    referenced_table: string(&row, "table"),
+                    referenced_columns: vec![string(&row, "to")],
+                }));
+            }
+        }
+        result.push(Table {
+            name,
+            columns,
+            primary_key: primary_key.into_iter().map(|(_, c)| c).collect(),
+            indexes,
+            foreign_keys: foreign_keys.into_iter().map(|(_, f)| f).collect(),
+        });
+    }
+    Ok(result)
+}
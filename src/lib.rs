@@ -4,14 +4,39 @@ pub mod server;
 pub mod migrate;
 pub mod purge;
 pub mod seeder;
+pub mod idempotency;
+pub mod webhook;
+pub mod schedule;
+pub mod audit;
+pub mod test;
+pub(crate) mod introspect;
+pub(crate) mod lint;
+pub(crate) mod dump;
+pub(crate) mod console;
+pub(crate) mod routes;
+pub(crate) mod doctor;
+pub(crate) mod scaffold;
+pub(crate) mod profile;
+pub(crate) mod query;
+pub(crate) mod diff;
+pub(crate) mod stdlib;
+pub mod fmt;
 mod message;
 
 pub mod prelude {
     pub use crate::app::App;
     pub use crate::app;
+    pub use crate::app::program::{ProgramArg, ProgramArgs, ProgramArgType, ProgramSignature};
     pub use crate::cli::entrance::Entrance;
     pub use crate::cli::runtime_version::RuntimeVersion;
-    pub use crate::server::static_files::serve_static_files;
+    pub use crate::server::static_files::{serve_static_files, StaticFiles};
+    pub use crate::server::options::ServerOptions;
+    pub use crate::server::panic::PanicReport;
+    pub use crate::server::admin::AdminDashboard;
+    pub use crate::idempotency::Idempotency;
+    pub use crate::webhook::{Webhook, WebhookEvent};
+    pub use crate::schedule::Schedule;
+    pub use crate::audit::{Audit, AuditAction};
     pub use teo_runtime::namespace::Namespace;
     pub extern crate teo_result;
     pub use teo_result::{Error, Result, ResultExt};
@@ -0,0 +1,222 @@
+mod rules;
+mod output;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use serde_json::{Value as JsonValue};
+use teo_result::{Error, Result};
+use crate::cli::command::{LintCommand, LintFormat};
+
+pub(crate) const DEFAULT_CONFIG_FILE: &str = "teo-lint.json";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Severity {
+    Off,
+    Warning,
+    Error,
+}
+
+impl Severity {
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Off => "off",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+
+    fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "off" => Some(Severity::Off),
+            "warning" | "warn" => Some(Severity::Warning),
+            "error" => Some(Severity::Error),
+            _ => None,
+        }
+    }
+}
+
+/// A lint rule. Codes are stable, configure rules by code or by name.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Rule {
+    ForeignKeyWithoutIndex,
+    RelationWithoutInverse,
+    SensitiveFieldReadable,
+    ModelNaming,
+    FieldNaming,
+    UnusedEnum,
+    UnusedInterface,
+    DataSetMissingRecord,
+}
+
+impl Rule {
+
+    pub(crate) const ALL: [Rule; 8] = [
+        Rule::ForeignKeyWithoutIndex,
+        Rule::RelationWithoutInverse,
+        Rule::SensitiveFieldReadable,
+        Rule::ModelNaming,
+        Rule::FieldNaming,
+        Rule::UnusedEnum,
+        Rule::UnusedInterface,
+        Rule::DataSetMissingRecord,
+    ];
+
+    pub(crate) fn code(&self) -> &'static str {
+        match self {
+            Rule::ForeignKeyWithoutIndex => "TEO001",
+            Rule::RelationWithoutInverse => "TEO002",
+            Rule::SensitiveFieldReadable => "TEO003",
+            Rule::ModelNaming => "TEO004",
+            Rule::FieldNaming => "TEO005",
+            Rule::UnusedEnum => "TEO006",
+            Rule::UnusedInterface => "TEO007",
+            Rule::DataSetMissingRecord => "TEO008",
+        }
+    }
+
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Rule::ForeignKeyWithoutIndex => "foreign-key-without-index",
+            Rule::RelationWithoutInverse => "relation-without-inverse",
+            Rule::SensitiveFieldReadable => "sensitive-field-readable",
+            Rule::ModelNaming => "model-naming",
+            Rule::FieldNaming => "field-naming",
+            Rule::UnusedEnum => "unused-enum",
+            Rule::UnusedInterface => "unused-interface",
+            Rule::DataSetMissingRecord => "data-set-missing-record",
+        }
+    }
+
+    pub(crate) fn description(&self) -> &'static str {
+        match self {
+            Rule::ForeignKeyWithoutIndex => "Foreign key fields should be indexed",
+            Rule::RelationWithoutInverse => "Relations should have an inverse relation",
+            Rule::SensitiveFieldReadable => "Sensitive fields should not be readable by everyone",
+            Rule::ModelNaming => "Model names should be PascalCase",
+            Rule::FieldNaming => "Field names should be camelCase",
+            Rule::UnusedEnum => "Enums should be used",
+            Rule::UnusedInterface => "Interfaces should be used",
+            Rule::DataSetMissingRecord => "Data set records should only reference existing records",
+        }
+    }
+
+    fn default_severity(&self) -> Severity {
+        match self {
+            Rule::DataSetMissingRecord => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+/// A problem found by a rule.
+#[derive(Debug, Clone)]
+pub(crate) struct LintDiagnostic {
+    pub(crate) rule: Rule,
+    pub(crate) severity: Severity,
+    /// Where the problem is, e.g. `blog.Post.authorId`.
+    pub(crate) location: String,
+    pub(crate) message: String,
+}
+
+/// Severity overrides read from `teo-lint.json`, e.g.
+/// `{ "rules": { "TEO002": "off", "field-naming": "error" } }`.
+#[derive(Debug, Default)]
+pub(crate) struct LintConfig {
+    severities: BTreeMap<Rule, Severity>,
+}
+
+impl LintConfig {
+
+    fn load(path: Option<&str>) -> Result<Self> {
+        let path = match path {
+            Some(path) => Path::new(path).to_owned(),
+            None => {
+                let path = Path::new(DEFAULT_CONFIG_FILE).to_owned();
+                if !path.is_file() {
+                    return Ok(Self::default());
+                }
+                path
+            }
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => Err(Error::new(format!("cannot read {}: {}", path.display(), e)))?,
+        };
+        let json: JsonValue = match serde_json::from_str(&content) {
+            Ok(json) => json,
+            Err(e) => Err(Error::new(format!("invalid lint config {}: {}", path.display(), e)))?,
+        };
+        let mut severities = BTreeMap::new();
+        if let Some(rules) = json.get("rules").and_then(|r| r.as_object()) {
+            for (key, value) in rules {
+                let Some(rule) = Rule::ALL.iter().find(|r| r.code() == key || r.name() == key) else {
+                    Err(Error::new(format!("unknown lint rule `{}`", key)))?
+                };
+                let Some(severity) = value.as_str().and_then(Severity::from_str) else {
+                    Err(Error::new(format!("invalid severity of lint rule `{}`, expect off, warning or error", key)))?
+                };
+                severities.insert(*rule, severity);
+            }
+        }
+        Ok(Self { severities })
+    }
+
+    pub(crate) fn severity(&self, rule: Rule) -> Severity {
+        self.severities.get(&rule).cloned().unwrap_or(rule.default_severity())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_file(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("teo-lint-{}-{}.json", name, std::process::id()));
+        fs::write(&path, content).unwrap();
+        path.to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn sensitive_fields_warn_by_default() {
+        let config = LintConfig::default();
+        assert_eq!(config.severity(Rule::SensitiveFieldReadable), Severity::Warning);
+        assert_eq!(config.severity(Rule::DataSetMissingRecord), Severity::Error);
+    }
+
+    #[test]
+    fn overrides_severities_by_code_or_name() {
+        let path = config_file("overrides", r#"{ "rules": { "TEO002": "off", "field-naming": "error" } }"#);
+        let config = LintConfig::load(Some(&path)).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(config.severity(Rule::RelationWithoutInverse), Severity::Off);
+        assert_eq!(config.severity(Rule::FieldNaming), Severity::Error);
+        assert_eq!(config.severity(Rule::ModelNaming), Severity::Warning);
+    }
+
+    #[test]
+    fn rejects_unknown_rules() {
+        let path = config_file("unknown", r#"{ "rules": { "TEO999": "off" } }"#);
+        assert!(LintConfig::load(Some(&path)).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
+
+/// Run every rule and print the diagnostics. Errors fail the command, and so
+/// do warnings with `--deny-warnings`.
+pub(crate) fn lint(command: &LintCommand) -> Result<()> {
+    let config = LintConfig::load(command.config.as_ref().map(|c| c.as_str()))?;
+    let diagnostics = rules::check(&config)?;
+    match command.format {
+        LintFormat::Text => output::print_text(&diagnostics),
+        LintFormat::Json => output::print_json(&diagnostics),
+        LintFormat::Sarif => output::print_sarif(&diagnostics),
+    }
+    let errors = diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+    let warnings = diagnostics.iter().filter(|d| d.severity == Severity::Warning).count();
+    if errors > 0 || (command.deny_warnings && warnings > 0) {
+        Err(Error::new(format!("lint found {} error(s) and {} warning(s)", errors, warnings)))?
+    }
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use colored::Colorize;
+use serde_json::{json, Value as JsonValue};
+use crate::lint::{LintDiagnostic, Rule, Severity};
+
+pub(super) fn print_text(diagnostics: &Vec<LintDiagnostic>) {
+    for diagnostic in diagnostics {
+        let severity = match diagnostic.severity {
+            Severity::Error => diagnostic.severity.as_str().red().bold(),
+            _ => diagnostic.severity.as_str().yellow().bold(),
+        };
+        println!("{} [{}] {}: {}", severity, diagnostic.rule.code(), diagnostic.location.bold(), diagnostic.message);
+    }
+    if diagnostics.is_empty() {
+        println!("{}", "no lint problems found".green());
+    }
+}
+
+pub(super) fn print_json(diagnostics: &Vec<LintDiagnostic>) {
+    let result: Vec<JsonValue> = diagnostics.iter().map(|d| json!({
+        "code": d.rule.code(),
+        "rule": d.rule.name(),
+        "severity": d.severity.as_str(),
+        "location": d.location,
+        "message": d.message,
+    })).collect();
+    println!("{}", JsonValue::Array(result));
+}
+
+/// SARIF 2.1.0 for code scanning services.
+pub(super) fn print_sarif(diagnostics: &Vec<LintDiagnostic>) {
+    let rules: Vec<JsonValue> = Rule::ALL.iter().map(|r| json!({
+        "id": r.code(),
+        "name": r.name(),
+        "shortDescription": { "text": r.description() },
+    })).collect();
+    let results: Vec<JsonValue> = diagnostics.iter().map(|d| json!({
+        "ruleId": d.rule.code(),
+        "level": match d.severity { Severity::Error => "error", _ => "warning" },
+        "message": { "text": d.message },
+        "locations": [{
+            "logicalLocations": [{ "fullyQualifiedName": d.location }],
+        }],
+    })).collect();
+    println!("{}", json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "teo",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://teodev.io",
+                    "rules": rules,
+                },
+            },
+            "results": results,
+        }],
+    }));
+}
@@ -0,0 +1,234 @@
+use std::collections::BTreeSet;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use teo_parser::ast::schema::Schema;
+use teo_parser::diagnostics::diagnostics::Diagnostics;
+use teo_parser::r#type::Type;
+use teo_result::Result;
+use teo_runtime::model::{Field, Model};
+use teo_runtime::model::permission::Permission;
+use teo_runtime::namespace::Namespace;
+use teo_runtime::readwrite::read::Read;
+use teo_runtime::schema::load::load_data_sets::load_data_sets;
+use teo_runtime::traits::named::Named;
+use crate::app::ctx::Ctx;
+use crate::lint::{LintConfig, LintDiagnostic, Rule, Severity};
+
+pub(super) fn check(config: &LintConfig) -> Result<Vec<LintDiagnostic>> {
+    let mut diagnostics = vec![];
+    let mut report = |rule: Rule, location: String, message: String| {
+        let severity = config.severity(rule);
+        if severity != Severity::Off {
+            diagnostics.push(LintDiagnostic { rule, severity, location, message });
+        }
+    };
+    let referenced = referenced_paths(Ctx::schema());
+    let mut namespaces = vec![Ctx::main_namespace()];
+    while let Some(namespace) = namespaces.pop() {
+        // std is declared by teo and this crate, not by the user
+        namespaces.extend(namespace.namespaces.values().filter(|n| !is_std(&n.path)));
+        for model in namespace.models.values() {
+            check_model(model, &mut report);
+        }
+        for name in namespace.enums.keys() {
+            if !referenced.contains(&path_of(namespace, name)) {
+                report(Rule::UnusedEnum, qualified(namespace, name), format!("enum `{}` is never used", name));
+            }
+        }
+        for name in namespace.interfaces.keys() {
+            if !referenced.contains(&path_of(namespace, name)) {
+                report(Rule::UnusedInterface, qualified(namespace, name), format!("interface `{}` is never used", name));
+            }
+        }
+    }
+    check_data_sets(&mut report)?;
+    diagnostics.sort_by(|a, b| b.severity.cmp(&a.severity).then(a.rule.cmp(&b.rule)).then(a.location.cmp(&b.location)));
+    Ok(diagnostics)
+}
+
+fn check_model(model: &Model, report: &mut impl FnMut(Rule, String, String)) {
+    let model_path = model.path().join(".");
+    let name = model.name();
+    if !name.chars().next().map_or(false, |c| c.is_ascii_uppercase()) || name.contains('_') {
+        report(Rule::ModelNaming, model_path.clone(), format!("model `{}` should be PascalCase", name));
+    }
+    for field in model.fields.values() {
+        let field_name = field.name();
+        if !field_name.chars().next().map_or(false, |c| c.is_ascii_lowercase()) || field_name.contains('_') {
+            report(Rule::FieldNaming, format!("{}.{}", model_path, field_name), format!("field `{}` should be camelCase", field_name));
+        }
+        if is_sensitive(field_name) && readable_by_everyone(model, field) {
+            report(Rule::SensitiveFieldReadable, format!("{}.{}", model_path, field_name), format!("field `{}` looks sensitive but can be read, add `@writeonly`, `@readIf` or `@canRead`", field_name));
+        }
+    }
+    for relation in model.relations() {
+        let location = format!("{}.{}", model_path, relation.name());
+        if relation.has_foreign_key {
+            let local_fields: Vec<&str> = relation.iter().map(|(local, _)| local).collect();
+            let indexed = model.indexes().iter().any(|index| {
+                let keys = index.keys();
+                keys.len() >= local_fields.len() && keys.iter().zip(local_fields.iter()).all(|(k, f)| k.as_str() == *f)
+            });
+            if !indexed {
+                report(Rule::ForeignKeyWithoutIndex, location.clone(), format!("foreign key `{}` of relation `{}` is not indexed", local_fields.join(", "), relation.name()));
+            }
+        }
+        let Some(other) = Ctx::main_namespace().model_at_path(&relation.model_path()) else {
+            continue
+        };
+        if !other.relations().iter().any(|r| r.model_path().join(".") == model_path) {
+            report(Rule::RelationWithoutInverse, location, format!("relation `{}` has no inverse relation on `{}`", relation.name(), relation.model_path().join(".")));
+        }
+    }
+}
+
+fn check_data_sets(report: &mut impl FnMut(Rule, String, String)) -> Result<()> {
+    let mut diagnostics = Diagnostics::new();
+    let data_sets = load_data_sets(Ctx::main_namespace(), None, true, Ctx::schema(), &mut diagnostics)?;
+    for data_set in &data_sets {
+        for group in &data_set.groups {
+            let Some(model) = Ctx::main_namespace().model_at_path(&group.model_path()) else { continue };
+            for record in &group.records {
+                let Some(value) = record.value.as_dictionary() else { continue };
+                for relation in model.relations() {
+                    let Some(reference) = value.get(relation.name()) else { continue };
+                    let names = match reference.as_array() {
+                        Some(references) => references.iter().filter_map(|r| r.as_str()).collect::<Vec<&str>>(),
+                        None => reference.as_str().into_iter().collect(),
+                    };
+                    let target = data_set.groups.iter().find(|g| g.name == relation.model_path());
+                    for name in names {
+                        if !target.map_or(false, |g| g.records.iter().any(|r| r.name == name)) {
+                            report(
+                                Rule::DataSetMissingRecord,
+                                format!("{}.{}.{}", data_set.name.join("."), group.name.join("."), record.name),
+                                format!("`{}` references `{}` which is not a record of `{}`", relation.name(), name, relation.model_path().join(".")),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn qualified(namespace: &Namespace, name: &str) -> String {
+    if namespace.path.is_empty() { name.to_owned() } else { format!("{}.{}", namespace.path.join("."), name) }
+}
+
+fn path_of(namespace: &Namespace, name: &str) -> Vec<String> {
+    namespace.path.iter().cloned().chain(std::iter::once(name.to_owned())).collect()
+}
+
+fn is_std(path: &Vec<String>) -> bool {
+    path.first().map_or(false, |p| p == "std")
+}
+
+/// Whether a field name has a word like `password` or `apiKey`, e.g.
+/// `passwordHash` but not `hashtag`.
+fn is_sensitive(name: &str) -> bool {
+    static SENSITIVE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(^|_)(password|passwd|secret|token|api_key|salt|hash)(_|$)").unwrap());
+    static WORD_BOUNDARY: Lazy<Regex> = Lazy::new(|| Regex::new(r"([a-z0-9])([A-Z])").unwrap());
+    let words = WORD_BOUNDARY.replace_all(name, "${1}_${2}").to_lowercase();
+    SENSITIVE.is_match(&words)
+}
+
+/// Whether nothing restricts reading the field, neither `@writeonly` nor
+/// `@readIf` nor `@canRead` on the field or its model.
+fn readable_by_everyone(model: &Model, field: &Field) -> bool {
+    let Read::Read = field.read else {
+        return false;
+    };
+    let can_read = |permission: &Option<Permission>| permission.as_ref().map_or(false, |p| p.can_read.is_some());
+    !can_read(&field.permission) && !can_read(&model.permission)
+}
+
+/// Paths of the enums and interfaces which the types of fields, interface
+/// fields and extends, and handler inputs and outputs refer to.
+fn referenced_paths(schema: &Schema) -> BTreeSet<Vec<String>> {
+    let mut paths = BTreeSet::new();
+    for model in schema.models() {
+        for field in model.fields() {
+            collect_references(field.type_expr.resolved(), &mut paths);
+        }
+    }
+    for interface in schema.interfaces() {
+        for field in interface.fields() {
+            collect_references(field.type_expr.resolved(), &mut paths);
+        }
+        for extend in interface.extends() {
+            collect_references(extend.resolved(), &mut paths);
+        }
+    }
+    for handler in schema.handler_declarations() {
+        collect_references(handler.input_type().resolved(), &mut paths);
+        collect_references(handler.output_type().resolved(), &mut paths);
+    }
+    paths
+}
+
+fn collect_references(r#type: &Type, paths: &mut BTreeSet<Vec<String>>) {
+    match r#type {
+        Type::EnumVariant(reference) => { paths.insert(reference.string_path().clone()); }
+        Type::InterfaceObject(reference, generics) => {
+            paths.insert(reference.string_path().clone());
+            generics.iter().for_each(|t| collect_references(t, paths));
+        }
+        Type::Optional(inner) | Type::Array(inner) | Type::Dictionary(inner) | Type::Enumerable(inner) | Type::Range(inner) => collect_references(inner, paths),
+        Type::Tuple(types) | Type::Union(types) => types.iter().for_each(|t| collect_references(t, paths)),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use teo_parser::parse;
+    use super::*;
+
+    #[test]
+    fn matches_sensitive_words() {
+        assert!(is_sensitive("password"));
+        assert!(is_sensitive("passwordHash"));
+        assert!(is_sensitive("apiKey"));
+        assert!(is_sensitive("refreshToken"));
+        assert!(is_sensitive("client_secret"));
+        assert!(!is_sensitive("hashtag"));
+        assert!(!is_sensitive("tokenizer"));
+        assert!(!is_sensitive("passwordless"));
+        assert!(!is_sensitive("secretary"));
+    }
+
+    #[test]
+    fn collects_referenced_enums_and_interfaces() {
+        let source = r#"
+        enum Role { admin user }
+        enum Unused { a b }
+        interface Address { city: String }
+        interface Profile { address: Address? }
+        interface Lonely { name: String }
+        model User {
+          @id @default(1)
+          id: Int
+          roles: Role[]
+        }
+        "#;
+        let (schema, diagnostics) = parse("/lint/schema.teo", None, Some(HashMap::from([("/lint/schema.teo".to_owned(), source.to_owned())])));
+        assert!(!diagnostics.has_errors());
+        let paths = referenced_paths(&schema);
+        assert!(paths.contains(&vec!["Role".to_owned()]));
+        assert!(paths.contains(&vec!["Address".to_owned()]));
+        assert!(!paths.contains(&vec!["Unused".to_owned()]));
+        assert!(!paths.contains(&vec!["Lonely".to_owned()]));
+        assert!(!paths.contains(&vec!["Profile".to_owned()]));
+    }
+
+    #[test]
+    fn skips_std_namespace() {
+        assert!(is_std(&vec!["std".to_owned()]));
+        assert!(is_std(&vec!["std".to_owned(), "inner".to_owned()]));
+        assert!(!is_std(&vec![]));
+        assert!(!is_std(&vec!["standard".to_owned()]));
+    }
+}
@@ -1,7 +1,75 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use chrono::{DateTime, Local};
 use colored::{ColoredString, Colorize};
 use array_tool::vec::Join;
+use serde_json::{json, Map, Value as JsonValue};
+use teo_parser::diagnostics::diagnostics::{Diagnostics, DiagnosticsLog};
+use teo_parser::diagnostics::printer::print_diagnostics;
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// Print messages as JSON lines for scripts, see `--output json`.
+pub(crate) fn set_json_output(json_output: bool) {
+    JSON_OUTPUT.store(json_output, Ordering::Relaxed);
+    if json_output {
+        // messages are colored before they are printed, keep escapes out of JSON
+        colored::control::set_override(false);
+    }
+}
+
+pub(crate) fn json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+/// The name of a namespace in messages and events, `main` for the main
+/// namespace.
+pub(crate) fn namespace_name(namespace_path: &Vec<String>) -> String {
+    if namespace_path.is_empty() { "main".to_owned() } else { namespace_path.join(".") }
+}
+
+/// A structured event, printed only with `--output json`. Every event has
+/// `event` and `time` keys besides `fields`.
+pub(crate) fn event(name: &str, fields: JsonValue) {
+    if !json_output() { return }
+    println!("{}", event_json(name, fields));
+}
+
+/// Print the diagnostics of a parsed schema, one `diagnostic` event each
+/// with `--output json`.
+pub(crate) fn diagnostics_message(diagnostics: &Diagnostics) {
+    if !json_output() {
+        print_diagnostics(diagnostics, true);
+        return
+    }
+    for error in diagnostics.errors() {
+        diagnostic_event("error", error);
+    }
+    for warning in diagnostics.warnings() {
+        diagnostic_event("warning", warning);
+    }
+}
+
+fn diagnostic_event(severity: &str, log: &impl DiagnosticsLog) {
+    let (line, column) = log.span().start_position;
+    event("diagnostic", json!({
+        "severity": severity,
+        "message": log.message(),
+        "source": log.source_path(),
+        "line": line,
+        "column": column,
+    }));
+}
+
+fn event_json(name: &str, fields: JsonValue) -> JsonValue {
+    let mut result = Map::new();
+    result.insert("event".to_owned(), JsonValue::String(name.to_owned()));
+    result.insert("time".to_owned(), JsonValue::String(Local::now().to_rfc3339()));
+    if let JsonValue::Object(fields) = fields {
+        result.extend(fields);
+    }
+    JsonValue::Object(result)
+}
 
 fn timestamp() -> ColoredString {
     let local: DateTime<Local> = Local::now();
@@ -10,9 +78,21 @@ fn timestamp() -> ColoredString {
 }
 
 pub fn info_message(content: impl AsRef<str>) {
+    if json_output() {
+        println!("{}", event_json("info", json!({ "message": content.as_ref() })));
+        return
+    }
     println!("{} {}", timestamp(), content.as_ref())
 }
 
+pub fn error_message(content: impl AsRef<str>) {
+    if json_output() {
+        eprintln!("{}", event_json("error", json!({ "message": content.as_ref() })));
+        return
+    }
+    eprintln!("{} {}", timestamp(), content.as_ref().red())
+}
+
 pub fn request_message(
     time_elapsed: Duration,
     method: &str,
@@ -21,6 +101,16 @@ pub fn request_message(
     action: &str,
     code: u16,
 ) {
+    if json_output() {
+        println!("{}", event_json("request", json!({
+            "method": method,
+            "path": path,
+            "handler": handler_group_path.iter().map(|s| s.as_str()).chain(std::iter::once(action)).collect::<Vec<&str>>().join("."),
+            "code": code,
+            "ms": time_elapsed.as_millis() as u64,
+        })));
+        return
+    }
     let handler_str: String = handler_group_path.join(".") + ".";
     let code_string = format_code_into_string(code);
     let ms = time_elapsed.as_millis();
@@ -34,6 +124,15 @@ pub fn unhandled_request_message(
     path: &str,
     code: u16,
 ) {
+    if json_output() {
+        println!("{}", event_json("request", json!({
+            "method": method,
+            "path": path,
+            "code": code,
+            "ms": time_elapsed.as_millis() as u64,
+        })));
+        return
+    }
     let code_string = format_code_into_string(code);
     let ms = time_elapsed.as_millis();
     let ms_str = format!("{ms}ms").normal().clear();
@@ -0,0 +1,378 @@
+use teo_runtime::database::database::Database;
+use teo_runtime::database::r#type::DatabaseType;
+use teo_runtime::model::field::is_optional::IsOptional;
+use teo_runtime::model::field::typed::Typed;
+use teo_runtime::model::index::Type as IndexType;
+use teo_runtime::model::Model;
+use teo_runtime::Value;
+use crate::introspect::{Column, ForeignKey, Index, quote, Table};
+
+/// Statements changing the tables of a database into the tables of the
+/// models, and statements reverting them.
+#[derive(Debug, Default)]
+pub(crate) struct Ddl {
+    pub(crate) up: Vec<String>,
+    pub(crate) down: Vec<String>,
+}
+
+impl Ddl {
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.up.is_empty()
+    }
+}
+
+/// The tables the models are stored in. Columns have the `@db` type of their
+/// field, or the default type of the field type.
+pub(crate) fn tables_of(models: &[&Model], provider: &Database) -> Vec<Table> {
+    models.iter().map(|model| {
+        let columns = model.fields.values().filter(|f| !f.r#virtual).map(|field| Column {
+            name: field.column_name.clone(),
+            db_type: native_type(field.database_type()).unwrap_or_else(|| column_type(&field.r#type().unwrap_optional().to_string(), provider)),
+            nullable: field.is_optional(),
+            auto_increment: field.auto_increment,
+            default: field.default.as_ref().and_then(|d| d.as_teon()).and_then(|v| default_literal(v, provider)),
+        }).collect();
+        let indexes = model.indexes().iter().filter(|i| !matches!(i.r#type(), IndexType::Primary)).map(|index| Index {
+            name: index.name().to_owned(),
+            columns: index.keys().iter().map(|k| column_name(model, k)).collect(),
+            unique: matches!(index.r#type(), IndexType::Unique),
+        }).collect();
+        // relations to models of other connectors have no constraint
+        let foreign_keys = model.relations().iter().filter(|r| r.has_foreign_key).filter_map(|relation| {
+            let referenced = models.iter().find(|m| m.path().join(".") == relation.model_path().join("."))?;
+            Some(ForeignKey {
+                columns: relation.iter().map(|(local, _)| column_name(model, local)).collect(),
+                referenced_table: referenced.table_name.clone(),
+                referenced_columns: relation.iter().map(|(_, foreign)| column_name(referenced, foreign)).collect(),
+            })
+        }).collect();
+        Table {
+            name: model.table_name.clone(),
+            columns,
+            primary_key: model.primary_index().map(|i| i.keys().iter().map(|k| column_name(model, k)).collect()).unwrap_or_default(),
+            indexes,
+            foreign_keys,
+        }
+    }).collect()
+}
+
+fn column_name(model: &Model, field_name: &str) -> String {
+    model.fields.get(field_name).map(|f| f.column_name.clone()).unwrap_or(field_name.to_owned())
+}
+
+/// The type declared with `@db`, e.g. `@db(.varChar(255))`.
+fn native_type(database_type: &DatabaseType) -> Option<String> {
+    if database_type.is_undetermined() {
+        None
+    } else {
+        Some(database_type.to_string())
+    }
+}
+
+/// Defaults which are values, defaults of pipelines like `$now` are set by
+/// the app.
+fn default_literal(value: &Value, provider: &Database) -> Option<String> {
+    Some(match value {
+        Value::Bool(b) => match provider {
+            Database::SQLite => if *b { "1" } else { "0" }.to_owned(),
+            _ => if *b { "TRUE" } else { "FALSE" }.to_owned(),
+        },
+        Value::Int(i) => i.to_string(),
+        Value::Int64(i) => i.to_string(),
+        Value::Float32(f) => f.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Decimal(d) => d.to_string(),
+        Value::String(s) => string_literal(s, provider),
+        _ => None?,
+    })
+}
+
+/// Create and drop tables, columns and indexes so that `current` becomes
+/// `desired`. Changed columns aren't altered, they're left to the author of
+/// the migration.
+pub(crate) fn generate(current: &[Table], desired: &[Table], provider: &Database) -> Ddl {
+    let mut ddl = Ddl::default();
+    // constraints are added once every table they reference exists
+    let mut constraints = Ddl::default();
+    for table in desired {
+        let Some(existing) = current.iter().find(|t| t.name == table.name) else {
+            ddl.up.push(create_table(table, provider));
+            ddl.down.push(format!("DROP TABLE {};", identifier(&table.name, provider)));
+            for index in &table.indexes {
+                ddl.up.push(create_index(&table.name, index, provider));
+            }
+            if !matches!(provider, Database::SQLite) {
+                for foreign_key in &table.foreign_keys {
+                    constraints.up.push(add_foreign_key(&table.name, foreign_key, provider));
+                    constraints.down.push(drop_foreign_key(&table.name, foreign_key, provider));
+                }
+            }
+            continue
+        };
+        for index in existing.indexes.iter().filter(|i| !table.indexes.iter().any(|d| same_index(i, d))) {
+            ddl.up.push(drop_index(&table.name, index, provider));
+            ddl.down.push(create_index(&table.name, index, provider));
+        }
+        for column in table.columns.iter().filter(|c| !existing.columns.iter().any(|e| e.name == c.name)) {
+            ddl.up.push(format!("ALTER TABLE {} ADD COLUMN {};", identifier(&table.name, provider), column_definition(column, provider)));
+            ddl.down.push(format!("ALTER TABLE {} DROP COLUMN {};", identifier(&table.name, provider), identifier(&column.name, provider)));
+        }
+        for column in existing.columns.iter().filter(|c| !table.columns.iter().any(|d| d.name == c.name)) {
+            ddl.up.push(format!("ALTER TABLE {} DROP COLUMN {};", identifier(&table.name, provider), identifier(&column.name, provider)));
+            ddl.down.push(format!("ALTER TABLE {} ADD COLUMN {};", identifier(&table.name, provider), column_definition(column, provider)));
+        }
+        for index in table.indexes.iter().filter(|i| !existing.indexes.iter().any(|e| same_index(i, e))) {
+            ddl.up.push(create_index(&table.name, index, provider));
+            ddl.down.push(drop_index(&table.name, index, provider));
+        }
+        // SQLite can't add constraints to a table, they're left to the author
+        // of the migration like changed columns
+        if !matches!(provider, Database::SQLite) {
+            for foreign_key in table.foreign_keys.iter().filter(|f| !existing.foreign_keys.iter().any(|e| same_foreign_key(f, e))) {
+                constraints.up.push(add_foreign_key(&table.name, foreign_key, provider));
+                constraints.down.push(drop_foreign_key(&table.name, foreign_key, provider));
+            }
+        }
+    }
+    ddl.up.extend(constraints.up);
+    ddl.down.extend(constraints.down);
+    for table in current.iter().filter(|t| !desired.iter().any(|d| d.name == t.name)) {
+        ddl.up.push(format!("DROP TABLE {};", identifier(&table.name, provider)));
+        if !matches!(provider, Database::SQLite) {
+            for foreign_key in table.foreign_keys.iter().rev() {
+                ddl.down.push(add_foreign_key(&table.name, foreign_key, provider));
+            }
+        }
+        for index in table.indexes.iter().rev() {
+            ddl.down.push(create_index(&table.name, index, provider));
+        }
+        ddl.down.push(create_table(table, provider));
+    }
+    // revert in the opposite order
+    ddl.down.reverse();
+    ddl
+}
+
+fn same_index(a: &Index, b: &Index) -> bool {
+    a.columns == b.columns && a.unique == b.unique
+}
+
+fn same_foreign_key(a: &ForeignKey, b: &ForeignKey) -> bool {
+    a.columns == b.columns && a.referenced_table == b.referenced_table && a.referenced_columns == b.referenced_columns
+}
+
+fn create_table(table: &Table, provider: &Database) -> String {
+    let mut lines: Vec<String> = table.columns.iter().map(|c| format!("  {}", column_definition(c, provider))).collect();
+    // SQLite declares an autoincrement primary key on its column
+    let inline_primary_key = matches!(provider, Database::SQLite) && table.columns.iter().any(|c| c.auto_increment);
+    if !table.primary_key.is_empty() && !inline_primary_key {
+        lines.push(format!("  PRIMARY KEY ({})", identifiers(&table.primary_key, provider)));
+    }
+    // other databases add them after every table is created
+    if matches!(provider, Database::SQLite) {
+        for foreign_key in &table.foreign_keys {
+            lines.push(format!("  {}", foreign_key_definition(foreign_key, provider)));
+        }
+    }
+    format!("CREATE TABLE {} (\n{}\n);", identifier(&table.name, provider), lines.join(",\n"))
+}
+
+fn create_index(table: &str, index: &Index, provider: &Database) -> String {
+    let unique = if index.unique { "UNIQUE " } else { "" };
+    format!("CREATE {}INDEX {} ON {} ({});", unique, identifier(&index.name, provider), identifier(table, provider), identifiers(&index.columns, provider))
+}
+
+fn drop_index(table: &str, index: &Index, provider: &Database) -> String {
+    match provider {
+        Database::MySQL => format!("DROP INDEX {} ON {};", identifier(&index.name, provider), identifier(table, provider)),
+        _ => format!("DROP INDEX {};", identifier(&index.name, provider)),
+    }
+}
+
+fn foreign_key_name(table: &str, foreign_key: &ForeignKey) -> String {
+    format!("{}_{}_fkey", table, foreign_key.columns.join("_"))
+}
+
+fn foreign_key_definition(foreign_key: &ForeignKey, provider: &Database) -> String {
+    format!("FOREIGN KEY ({}) REFERENCES {} ({})", identifiers(&foreign_key.columns, provider), identifier(&foreign_key.referenced_table, provider), identifiers(&foreign_key.referenced_columns, provider))
+}
+
+fn add_foreign_key(table: &str, foreign_key: &ForeignKey, provider: &Database) -> String {
+    format!("ALTER TABLE {} ADD CONSTRAINT {} {};", identifier(table, provider), identifier(&foreign_key_name(table, foreign_key), provider), foreign_key_definition(foreign_key, provider))
+}
+
+fn drop_foreign_key(table: &str, foreign_key: &ForeignKey, provider: &Database) -> String {
+    let name = identifier(&foreign_key_name(table, foreign_key), provider);
+    match provider {
+        Database::MySQL => format!("ALTER TABLE {} DROP FOREIGN KEY {};", identifier(table, provider), name),
+        _ => format!("ALTER TABLE {} DROP CONSTRAINT {};", identifier(table, provider), name),
+    }
+}
+
+fn column_definition(column: &Column, provider: &Database) -> String {
+    let name = identifier(&column.name, provider);
+    let null = if column.nullable { "NULL" } else { "NOT NULL" };
+    if !column.auto_increment {
+        return match &column.default {
+            Some(default) => format!("{} {} {} DEFAULT {}", name, column.db_type, null, default),
+            None => format!("{} {} {}", name, column.db_type, null),
+        };
+    }
+    match provider {
+        Database::SQLite => format!("{} INTEGER PRIMARY KEY AUTOINCREMENT", name),
+        Database::MySQL => format!("{} {} NOT NULL AUTO_INCREMENT", name, column.db_type),
+        Database::PostgreSQL => format!("{} {} NOT NULL", name, if column.db_type.eq_ignore_ascii_case("bigint") { "BIGSERIAL" } else { "SERIAL" }),
+        Database::MongoDB => unreachable!(),
+    }
+}
+
+/// The default column type of a field type. Enums are stored as strings.
+fn column_type(r#type: &str, provider: &Database) -> String {
+    if let Some(element) = r#type.strip_suffix("[]") {
+        return format!("{}[]", column_type(element, provider));
+    }
+    let result = match (provider, r#type) {
+        (Database::SQLite, "Bool" | "Int" | "Int64") => "INTEGER",
+        (Database::SQLite, "Float32" | "Float") => "REAL",
+        (Database::SQLite, _) => "TEXT",
+        (Database::MySQL, "Bool") => "TINYINT(1)",
+        (Database::MySQL, "Int") => "INT",
+        (Database::MySQL, "Int64") => "BIGINT",
+        (Database::MySQL, "Float32") => "FLOAT",
+        (Database::MySQL, "Float") => "DOUBLE",
+        (Database::MySQL, "Decimal") => "DECIMAL(65,30)",
+        (Database::MySQL, "Date") => "DATE",
+        (Database::MySQL, "DateTime") => "DATETIME(3)",
+        (Database::MySQL, _) => "VARCHAR(191)",
+        (_, "Bool") => "BOOLEAN",
+        (_, "Int") => "INTEGER",
+        (_, "Int64") => "BIGINT",
+        (_, "Float32") => "REAL",
+        (_, "Float") => "DOUBLE PRECISION",
+        (_, "Decimal") => "DECIMAL(65,30)",
+        (_, "Date") => "DATE",
+        (_, "DateTime") => "TIMESTAMP(3)",
+        (_, _) => "TEXT",
+    };
+    result.to_owned()
+}
+
+pub(crate) fn identifier(name: &str, provider: &Database) -> String {
+    match provider {
+        Database::MySQL => format!("`{}`", name.replace('`', "``")),
+        _ => format!("\"{}\"", name.replace('"', "\"\"")),
+    }
+}
+
+/// A string literal. MySQL also treats backslashes as escapes.
+pub(crate) fn string_literal(value: &str, provider: &Database) -> String {
+    match provider {
+        Database::MySQL => quote(&value.replace('\\', "\\\\")),
+        _ => quote(value),
+    }
+}
+
+fn identifiers(names: &[String], provider: &Database) -> String {
+    names.iter().map(|n| identifier(n, provider)).collect::<Vec<String>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, db_type: &str, nullable: bool, auto_increment: bool) -> Column {
+        Column { name: name.to_owned(), db_type: db_type.to_owned(), nullable, auto_increment, default: None }
+    }
+
+    fn table(name: &str, columns: Vec<Column>, indexes: Vec<Index>) -> Table {
+        Table { name: name.to_owned(), columns, primary_key: vec!["id".to_owned()], indexes, foreign_keys: vec![] }
+    }
+
+    #[test]
+    fn creates_and_drops_missing_tables() {
+        let desired = vec![table("User", vec![column("id", "INTEGER", false, true), column("email", "TEXT", false, false)], vec![
+            Index { name: "User_email".to_owned(), columns: vec!["email".to_owned()], unique: true },
+        ])];
+        let ddl = generate(&[], &desired, &Database::PostgreSQL);
+        assert_eq!(ddl.up, vec![
+            "CREATE TABLE \"User\" (\n  \"id\" SERIAL NOT NULL,\n  \"email\" TEXT NOT NULL,\n  PRIMARY KEY (\"id\")\n);".to_owned(),
+            "CREATE UNIQUE INDEX \"User_email\" ON \"User\" (\"email\");".to_owned(),
+        ]);
+        assert_eq!(ddl.down, vec!["DROP TABLE \"User\";".to_owned()]);
+        let ddl = generate(&desired, &[], &Database::PostgreSQL);
+        assert_eq!(ddl.up, vec!["DROP TABLE \"User\";".to_owned()]);
+        assert_eq!(ddl.down.len(), 2);
+        assert!(ddl.down[0].starts_with("CREATE TABLE \"User\""));
+    }
+
+    #[test]
+    fn adds_and_drops_columns() {
+        let current = vec![table("Post", vec![column("id", "integer", false, true), column("body", "text", true, false)], vec![])];
+        let desired = vec![table("Post", vec![column("id", "INTEGER", false, true), column("title", "TEXT", false, false)], vec![])];
+        let ddl = generate(&current, &desired, &Database::SQLite);
+        assert_eq!(ddl.up, vec![
+            "ALTER TABLE \"Post\" ADD COLUMN \"title\" TEXT NOT NULL;".to_owned(),
+            "ALTER TABLE \"Post\" DROP COLUMN \"body\";".to_owned(),
+        ]);
+        assert_eq!(ddl.down, vec![
+            "ALTER TABLE \"Post\" ADD COLUMN \"body\" text NULL;".to_owned(),
+            "ALTER TABLE \"Post\" DROP COLUMN \"title\";".to_owned(),
+        ]);
+    }
+
+    #[test]
+    fn recreates_index_changing_uniqueness() {
+        let index = |unique| Index { name: "Post_slug".to_owned(), columns: vec!["slug".to_owned()], unique };
+        let columns = || vec![column("id", "INT", false, true), column("slug", "VARCHAR(191)", false, false)];
+        let ddl = generate(&[table("Post", columns(), vec![index(false)])], &[table("Post", columns(), vec![index(true)])], &Database::MySQL);
+        assert_eq!(ddl.up, vec![
+            "DROP INDEX `Post_slug` ON `Post`;".to_owned(),
+            "CREATE UNIQUE INDEX `Post_slug` ON `Post` (`slug`);".to_owned(),
+        ]);
+    }
+
+    #[test]
+    fn declares_sqlite_autoincrement_inline() {
+        let ddl = generate(&[], &[table("Tag", vec![column("id", "INTEGER", false, true)], vec![])], &Database::SQLite);
+        assert_eq!(ddl.up[0], "CREATE TABLE \"Tag\" (\n  \"id\" INTEGER PRIMARY KEY AUTOINCREMENT\n);");
+    }
+
+    #[test]
+    fn adds_foreign_keys_after_tables() {
+        let user = table("User", vec![column("id", "INTEGER", false, true)], vec![]);
+        let post = Table {
+            foreign_keys: vec![ForeignKey { columns: vec!["authorId".to_owned()], referenced_table: "User".to_owned(), referenced_columns: vec!["id".to_owned()] }],
+            ..table("Post", vec![column("id", "INTEGER", false, true), column("authorId", "INTEGER", false, false)], vec![])
+        };
+        let ddl = generate(&[], &[post, user], &Database::PostgreSQL);
+        assert_eq!(ddl.up[2], "ALTER TABLE \"Post\" ADD CONSTRAINT \"Post_authorId_fkey\" FOREIGN KEY (\"authorId\") REFERENCES \"User\" (\"id\");");
+        assert_eq!(ddl.down[0], "ALTER TABLE \"Post\" DROP CONSTRAINT \"Post_authorId_fkey\";");
+    }
+
+    #[test]
+    fn declares_sqlite_foreign_keys_inline() {
+        let post = Table {
+            foreign_keys: vec![ForeignKey { columns: vec!["authorId".to_owned()], referenced_table: "User".to_owned(), referenced_columns: vec!["id".to_owned()] }],
+            ..table("Post", vec![column("id", "INTEGER", false, true), column("authorId", "INTEGER", false, false)], vec![])
+        };
+        let ddl = generate(&[], &[post], &Database::SQLite);
+        assert_eq!(ddl.up, vec!["CREATE TABLE \"Post\" (\n  \"id\" INTEGER PRIMARY KEY AUTOINCREMENT,\n  \"authorId\" INTEGER NOT NULL,\n  FOREIGN KEY (\"authorId\") REFERENCES \"User\" (\"id\")\n);".to_owned()]);
+    }
+
+    #[test]
+    fn declares_defaults() {
+        let status = Column { default: default_literal(&Value::String("draft".to_owned()), &Database::MySQL), ..column("status", "VARCHAR(191)", false, false) };
+        assert_eq!(column_definition(&status, &Database::MySQL), "`status` VARCHAR(191) NOT NULL DEFAULT 'draft'");
+        assert_eq!(default_literal(&Value::Bool(true), &Database::SQLite), Some("1".to_owned()));
+        assert_eq!(default_literal(&Value::Null, &Database::SQLite), None);
+    }
+
+    #[test]
+    fn maps_field_types() {
+        assert_eq!(column_type("Int64", &Database::MySQL), "BIGINT");
+        assert_eq!(column_type("DateTime", &Database::PostgreSQL), "TIMESTAMP(3)");
+        assert_eq!(column_type("Int[]", &Database::PostgreSQL), "INTEGER[]");
+        assert_eq!(column_type("Status", &Database::SQLite), "TEXT");
+    }
+}
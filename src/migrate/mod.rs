@@ -1,12 +1,45 @@
-use teo_result::{Error, Result};
+pub(crate) mod versioned;
+pub(crate) mod ddl;
+
+use std::time::Instant;
+use serde_json::json;
+use teo_result::Result;
+use teo_runtime::namespace::Namespace;
 use crate::app::ctx::Ctx;
+use crate::message::{event, json_output, namespace_name};
 
 pub async fn migrate(dry_run: bool, reset: bool, silent: bool) -> Result<()> {
     let ctx = Ctx::conn_ctx();
     for (namespace_path, connection) in ctx.connections_iter() {
         let namespace = ctx.namespace().namespace_at_path(&namespace_path.iter().map(AsRef::as_ref).collect()).unwrap();
+        let start = Instant::now();
+        let transaction = connection.no_transaction().await?;
+        // the connector prints text, events replace it with `--output json`
+        transaction.migrate(namespace.models_under_connector(), dry_run, reset, silent || json_output()).await?;
+        migrated(namespace, dry_run, reset, start);
+    }
+    Ok(())
+}
+
+/// Migrate the databases of a namespace which isn't the app's yet, e.g. a
+/// reloaded namespace before it replaces the running one.
+pub(crate) async fn migrate_namespace(namespace: &Namespace, silent: bool) -> Result<()> {
+    for namespace in std::iter::once(namespace).chain(namespace.namespaces.values()) {
+        let Some(connection) = namespace.connection.as_ref() else { continue };
+        let start = Instant::now();
         let transaction = connection.no_transaction().await?;
-        transaction.migrate(namespace.models_under_connector(), dry_run, reset, silent).await?;
+        transaction.migrate(namespace.models_under_connector(), false, false, silent || json_output()).await?;
+        migrated(namespace, false, false, start);
     }
     Ok(())
 }
+
+fn migrated(namespace: &Namespace, dry_run: bool, reset: bool, start: Instant) {
+    event("migrate", json!({
+        "namespace": namespace_name(&namespace.path),
+        "connector": namespace.connector.as_ref().map(|c| c.provider.lowercase_desc()),
+        "dry": dry_run,
+        "reset": reset,
+        "ms": start.elapsed().as_millis() as u64,
+    }));
+}
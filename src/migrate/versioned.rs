@@ -0,0 +1,317 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use chrono::Utc;
+use ring::digest;
+use teo_result::{Error, Result};
+use teo_runtime::connection::connection::Connection;
+use teo_runtime::connection::transaction::Transaction;
+use teo_runtime::database::database::Database;
+use teo_runtime::namespace::Namespace;
+use teo_runtime::Value;
+use crate::app::ctx::Ctx;
+use serde_json::json;
+use crate::introspect::{self, quote};
+use crate::message::{event, info_message, json_output, namespace_name};
+use crate::migrate::ddl;
+
+pub(crate) const MIGRATIONS_DIR: &str = "migrations";
+const HISTORY_TABLE: &str = "_teo_migrations";
+
+/// A migration directory named `{version}_{name}` containing `up.sql` and
+/// optionally `down.sql`.
+struct MigrationFile {
+    version: String,
+    name: String,
+    up: String,
+    down: Option<String>,
+    checksum: String,
+}
+
+struct AppliedMigration {
+    version: String,
+    name: String,
+    checksum: String,
+    applied_at: String,
+}
+
+/// Versioned migrations are used when the migrations directory exists next
+/// to the main schema file.
+pub(crate) fn is_versioned() -> bool {
+    migrations_dir().is_dir()
+}
+
+fn migrations_dir() -> PathBuf {
+    let main = Path::new(&Ctx::main_schema().0);
+    main.parent().unwrap_or(Path::new("")).join(MIGRATIONS_DIR)
+}
+
+/// Write migration files changing the tables of every SQL connector into
+/// the tables of its models.
+pub(crate) async fn create(name: &str) -> Result<()> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Err(Error::new("migration name can only contain letters, digits and underscores"))?
+    }
+    let version = Utc::now().format("%Y%m%d%H%M%S").to_string();
+    for (namespace_path, namespace, connection) in sql_connections().await? {
+        let transaction = connection.no_transaction().await?;
+        let namespace_name = namespace_name(&namespace_path);
+        // the database is compared with the schema, pending files would be generated again
+        let files = migration_files(&connector_dir(&namespace_path))?;
+        let applied = applied_migrations(&transaction, provider(namespace)).await?;
+        if files.iter().any(|f| !applied.iter().any(|a| a.version == f.version)) {
+            Err(Error::new(format!("`{}` has pending migrations, run `migrate up` before creating another one", namespace_name)))?
+        }
+        let provider = provider(namespace);
+        let current = introspect::tables(provider, &transaction).await?;
+        let ddl = ddl::generate(&current, &ddl::tables_of(&namespace.models_under_connector(), provider), provider);
+        if ddl.is_empty() {
+            info_message(format!("the database of `{}` matches the schema", namespace_name));
+            continue
+        }
+        let dir = connector_dir(&namespace_path).join(format!("{}_{}", version, name));
+        if let Err(e) = fs::create_dir_all(&dir) {
+            Err(Error::new(format!("cannot create {}: {}", dir.display(), e)))?
+        }
+        let header = format!("-- {} migration `{}` for `{}`\n-- Changed columns aren't altered, review them before applying.\n", provider.lowercase_desc(), name, namespace_name);
+        write_file(&dir.join("up.sql"), &format!("{}\n{}\n", header, ddl.up.join("\n\n")))?;
+        write_file(&dir.join("down.sql"), &format!("-- Reverts `{}`, data of dropped tables and columns is not restored.\n\n{}\n", name, ddl.down.join("\n\n")))?;
+        info_message(format!("created migration {} with {} statement(s)", dir.display(), ddl.up.len()));
+    }
+    Ok(())
+}
+
+/// Apply pending migrations in order, up to and including `to`. Every
+/// migration runs in a transaction with its history row. MySQL commits
+/// schema changes implicitly, a failing migration there may be half
+/// applied.
+pub(crate) async fn up(to: Option<&str>, silent: bool) -> Result<()> {
+    for (namespace_path, namespace, connection) in sql_connections().await? {
+        let transaction = connection.no_transaction().await?;
+        ensure_history_table(&transaction).await?;
+        let files = migration_files(&connector_dir(&namespace_path))?;
+        let applied = applied_migrations(&transaction, provider(namespace)).await?;
+        verify_checksums(&files, &applied)?;
+        for file in files.iter().filter(|f| !applied.iter().any(|a| a.version == f.version)) {
+            if let Some(to) = to {
+                if file.version.as_str() > to { break }
+            }
+            let mut statements = statements(&file.up);
+            statements.push(format!(
+                "INSERT INTO {} (version, name, checksum, applied_at) VALUES ({}, {}, {}, {})",
+                HISTORY_TABLE, quote(&file.version), quote(&file.name), quote(&file.checksum), quote(&Utc::now().to_rfc3339()),
+            ));
+            run_in_transaction(&connection, statements).await
+                .map_err(|e| Error::new(format!("migration {}_{} failed: {}", file.version, file.name, e)))?;
+            if json_output() {
+                event("migrate", json!({ "direction": "up", "namespace": namespace_name(&namespace_path), "version": file.version, "name": file.name }));
+            } else if !silent {
+                info_message(format!("applied migration {}_{} for `{}`", file.version, file.name, namespace_name(&namespace_path)));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Revert the last `steps` applied migrations of every connector, each in a
+/// transaction with the removal of its history row.
+pub(crate) async fn down(steps: usize, silent: bool) -> Result<()> {
+    for (namespace_path, namespace, connection) in sql_connections().await? {
+        let transaction = connection.no_transaction().await?;
+        let files = migration_files(&connector_dir(&namespace_path))?;
+        let applied = applied_migrations(&transaction, provider(namespace)).await?;
+        for migration in applied.iter().rev().take(steps) {
+            let Some(file) = files.iter().find(|f| f.version == migration.version) else {
+                Err(Error::new(format!("cannot revert migration {}_{}, its files are missing", migration.version, migration.name)))?
+            };
+            let Some(down) = &file.down else {
+                Err(Error::new(format!("cannot revert migration {}_{}, it has no down.sql", file.version, file.name)))?
+            };
+            let mut statements = statements(down);
+            statements.push(format!("DELETE FROM {} WHERE version = {}", HISTORY_TABLE, quote(&file.version)));
+            run_in_transaction(&connection, statements).await
+                .map_err(|e| Error::new(format!("reverting migration {}_{} failed: {}", file.version, file.name, e)))?;
+            if json_output() {
+                event("migrate", json!({ "direction": "down", "namespace": namespace_name(&namespace_path), "version": file.version, "name": file.name }));
+            } else if !silent {
+                info_message(format!("reverted migration {}_{} for `{}`", file.version, file.name, namespace_name(&namespace_path)));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print applied, pending and modified migrations of every connector.
+pub(crate) async fn status() -> Result<()> {
+    println!("+-{:<14}-+-{:<32}-+-{:<16}-+-{:<25}-+-{:<10}-+", "--------------", "--------------------------------", "----------------", "-------------------------", "----------");
+    println!("| {:^14} | {:^32} | {:^16} | {:^25} | {:^10} |", "Version", "Name", "Connector", "Applied At", "Status");
+    println!("+-{:<14}-+-{:<32}-+-{:<16}-+-{:<25}-+-{:<10}-+", "--------------", "--------------------------------", "----------------", "-------------------------", "----------");
+    for (namespace_path, namespace, connection) in sql_connections().await? {
+        let transaction = connection.no_transaction().await?;
+        let files = migration_files(&connector_dir(&namespace_path))?;
+        let applied = applied_migrations(&transaction, provider(namespace)).await?;
+        let namespace_name = namespace_name(&namespace_path);
+        for file in &files {
+            let (applied_at, status) = match applied.iter().find(|a| a.version == file.version) {
+                Some(a) if a.checksum != file.checksum => (a.applied_at.as_str(), "modified"),
+                Some(a) => (a.applied_at.as_str(), "applied"),
+                None => ("", "pending"),
+            };
+            println!("| {:<14} | {:<32} | {:<16} | {:<25} | {:<10} |", file.version, file.name, namespace_name, applied_at, status);
+        }
+        for migration in applied.iter().filter(|a| !files.iter().any(|f| f.version == a.version)) {
+            println!("| {:<14} | {:<32} | {:<16} | {:<25} | {:<10} |", migration.version, migration.name, namespace_name, migration.applied_at, "missing");
+        }
+    }
+    println!("+-{:<14}---{:<32}---{:<16}---{:<25}---{:<10}-+", "--------------", "--------------------------------", "----------------", "-------------------------", "----------");
+    Ok(())
+}
+
+/// The number of migrations not applied yet. Modified migrations are errors.
+pub(crate) async fn pending() -> Result<usize> {
+    let mut result = 0;
+    for (namespace_path, namespace, connection) in sql_connections().await? {
+        let transaction = connection.no_transaction().await?;
+        let files = migration_files(&connector_dir(&namespace_path))?;
+        let applied = applied_migrations(&transaction, provider(namespace)).await?;
+        verify_checksums(&files, &applied)?;
+        result += files.iter().filter(|f| !applied.iter().any(|a| a.version == f.version)).count();
+    }
+    Ok(result)
+}
+
+/// The SQL connectors. MongoDB has no versioned migrations, a schema with a
+/// MongoDB connector is refused rather than leaving its collections behind.
+async fn sql_connections() -> Result<Vec<(Vec<String>, &'static Namespace, Arc<dyn Connection>)>> {
+    let ctx = Ctx::conn_ctx();
+    let mut result = vec![];
+    for (namespace_path, connection) in ctx.connections_iter() {
+        let namespace = ctx.namespace().namespace_at_path(&namespace_path.iter().map(AsRef::as_ref).collect()).unwrap();
+        let Some(connector) = namespace.connector.as_ref() else { continue };
+        if connector.provider.is_mongo() {
+            Err(Error::new(format!("versioned migrations don't support MongoDB, which `{}` uses, remove the `{}` directory to migrate automatically", namespace_name(namespace_path), MIGRATIONS_DIR)))?
+        }
+        result.push((namespace_path.clone(), namespace, connection.clone()));
+    }
+    Ok(result)
+}
+
+fn provider(namespace: &Namespace) -> &Database {
+    &namespace.connector.as_ref().unwrap().provider
+}
+
+/// Run `statements` in one transaction, nothing is applied if one fails.
+pub(crate) async fn run_in_transaction(connection: &Arc<dyn Connection>, statements: Vec<String>) -> Result<()> {
+    let transaction = connection.transaction().await?;
+    for statement in statements {
+        if let Err(error) = transaction.query_raw(&Value::String(statement)).await {
+            let _ = transaction.abort().await;
+            return Err(error);
+        }
+    }
+    transaction.commit().await
+}
+
+async fn ensure_history_table(transaction: &Arc<dyn Transaction>) -> Result<()> {
+    transaction.query_raw(&Value::String(format!(
+        "CREATE TABLE IF NOT EXISTS {} (version VARCHAR(32) PRIMARY KEY, name VARCHAR(255) NOT NULL, checksum VARCHAR(64) NOT NULL, applied_at VARCHAR(64) NOT NULL)",
+        HISTORY_TABLE,
+    ))).await?;
+    Ok(())
+}
+
+/// The applied migrations, none before the history table is created by
+/// `migrate up`.
+async fn applied_migrations(transaction: &Arc<dyn Transaction>, provider: &Database) -> Result<Vec<AppliedMigration>> {
+    if !history_table_exists(transaction, provider).await? {
+        return Ok(vec![]);
+    }
+    let rows = transaction.query_raw(&Value::String(format!(
+        "SELECT version, name, checksum, applied_at FROM {} ORDER BY version", HISTORY_TABLE,
+    ))).await?;
+    let column = |row: &Value, key: &str| row.as_dictionary().and_then(|d| d.get(key)).and_then(|v| v.as_str()).unwrap_or("").to_owned();
+    Ok(rows.as_array().map(|rows| rows.iter().map(|row| AppliedMigration {
+        version: column(row, "version"),
+        name: column(row, "name"),
+        checksum: column(row, "checksum"),
+        applied_at: column(row, "applied_at"),
+    }).collect()).unwrap_or_default())
+}
+
+async fn history_table_exists(transaction: &Arc<dyn Transaction>, provider: &Database) -> Result<bool> {
+    let sql = match provider {
+        Database::SQLite => format!("SELECT name FROM sqlite_master WHERE type = 'table' AND name = {}", quote(HISTORY_TABLE)),
+        Database::MySQL => format!("SELECT table_name FROM information_schema.tables WHERE table_schema = DATABASE() AND table_name = {}", quote(HISTORY_TABLE)),
+        _ => format!("SELECT table_name FROM information_schema.tables WHERE table_schema = current_schema() AND table_name = {}", quote(HISTORY_TABLE)),
+    };
+    let rows = transaction.query_raw(&Value::String(sql)).await?;
+    Ok(rows.as_array().is_some_and(|rows| !rows.is_empty()))
+}
+
+fn verify_checksums(files: &Vec<MigrationFile>, applied: &Vec<AppliedMigration>) -> Result<()> {
+    for migration in applied {
+        if let Some(file) = files.iter().find(|f| f.version == migration.version) {
+            if file.checksum != migration.checksum {
+                Err(Error::new(format!("migration {}_{} was modified after it was applied", file.version, file.name)))?
+            }
+        }
+    }
+    Ok(())
+}
+
+fn migration_files(dir: &Path) -> Result<Vec<MigrationFile>> {
+    let mut result = vec![];
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(result);
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() { continue }
+        let Some(dir_name) = path.file_name().and_then(|n| n.to_str()) else { continue };
+        let Some((version, name)) = dir_name.split_once('_') else { continue };
+        let up = read_file(&path.join("up.sql"))?;
+        let down = if path.join("down.sql").is_file() { Some(read_file(&path.join("down.sql"))?) } else { None };
+        let checksum = digest::digest(&digest::SHA256, up.as_bytes()).as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+        result.push(MigrationFile { version: version.to_owned(), name: name.to_owned(), up, down, checksum });
+    }
+    result.sort_by(|a, b| a.version.cmp(&b.version));
+    Ok(result)
+}
+
+/// Split on lines ending with `;`, skipping comment lines.
+fn statements(sql: &str) -> Vec<String> {
+    let mut result = vec![];
+    let mut current = String::new();
+    for line in sql.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("--") { continue }
+        current.push_str(line);
+        current.push('\n');
+        if trimmed.ends_with(';') {
+            result.push(current.trim().to_owned());
+            current.clear();
+        }
+    }
+    if !current.trim().is_empty() {
+        result.push(current.trim().to_owned());
+    }
+    result
+}
+
+fn connector_dir(namespace_path: &Vec<String>) -> PathBuf {
+    migrations_dir().join(namespace_name(namespace_path))
+}
+
+fn read_file(path: &Path) -> Result<String> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(content),
+        Err(e) => Err(Error::new(format!("cannot read {}: {}", path.display(), e))),
+    }
+}
+
+fn write_file(path: &Path, content: &str) -> Result<()> {
+    match fs::write(path, content) {
+        Ok(()) => Ok(()),
+        Err(e) => Err(Error::new(format!("cannot write {}: {}", path.display(), e))),
+    }
+}
@@ -0,0 +1,496 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use colored::Colorize;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use teo_result::{Error, Result};
+use teo_runtime::namespace::Namespace;
+use crate::app::ctx::Ctx;
+use crate::server::options::ServerOptions;
+use crate::server::static_files::StaticFiles;
+use crate::webhook::Webhook;
+
+/// Read before the profile of the environment, for settings shared by every
+/// environment.
+const BASE_FILE: &str = "teo.toml";
+
+/// Options of the HTTP server, as the flags of `teo serve`.
+const SERVER_OPTIONS: [&str; 7] = [
+    "server.workers",
+    "server.backlog",
+    "server.max_connections",
+    "server.keep_alive",
+    "server.client_request_timeout",
+    "server.client_disconnect_timeout",
+    "server.http2",
+];
+
+/// Marks the environment as production, see [`is_production`].
+const PRODUCTION: &str = "production";
+
+/// Where the effective value of a setting comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Layer {
+    Schema,
+    Profile(String),
+    Env(String),
+}
+
+impl Layer {
+
+    fn describe(&self) -> String {
+        match self {
+            Layer::Schema => "schema".to_owned(),
+            Layer::Profile(file) => file.clone(),
+            Layer::Env(name) => format!("${}", name),
+        }
+    }
+}
+
+/// Environment profiles override settings of the schema.
+///
+/// Keys of `teo.toml` override the schema. With `--env production` or
+/// `TEO_ENV=production`, keys of `teo.production.toml` override both, e.g.
+///
+/// ```toml
+/// [connector]
+/// url = "postgres://app@db.internal/app"
+///
+/// [server]
+/// bind = "0.0.0.0:80"
+///
+/// [namespaces.analytics.connector]
+/// url = "mysql://analytics@db.internal/analytics"
+/// ```
+///
+/// Environment variables override both, named after the key, e.g.
+/// `TEO_SERVER_BIND` or `TEO_NAMESPACES_ANALYTICS_CONNECTOR_URL`. Only
+/// settings declared in the schema can be overridden, server settings are
+/// applied by [`apply_server`].
+pub(crate) fn apply(namespace: &mut Namespace, env: Option<&str>) -> Result<()> {
+    for (key, value, _) in resolve(namespace, env)? {
+        if !is_server_setting(&key) {
+            set(namespace, &key, &value)?;
+        }
+    }
+    Ok(())
+}
+
+/// Apply the settings of the server which the schema can't declare, e.g.
+///
+/// ```toml
+/// [server]
+/// workers = 4
+/// keep_alive = 0
+/// http2 = true
+///
+/// [server.static.assets]
+/// dir = "public"
+/// fallback = "index.html"
+/// cache_control = "public, max-age=3600"
+/// ```
+///
+/// `keep_alive` is in seconds and the client timeouts in milliseconds, as
+/// the flags of `teo serve`, which override these. The static directory is
+/// mounted at `/assets`, `prefix` mounts it elsewhere. These are read once
+/// when the app starts.
+pub(crate) fn apply_server(namespace: &Namespace, env: Option<&str>) -> Result<()> {
+    let mut options = ServerOptions::default();
+    let mut mounts: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+    for (key, value, _) in resolve(namespace, env)? {
+        match key.split('.').collect::<Vec<&str>>().as_slice() {
+            ["server", "static", name, field] => {
+                mounts.entry(name.to_string()).or_default().insert(field.to_string(), value);
+            }
+            ["server", "workers"] => options.workers = Some(parse_number(&key, &value)?),
+            ["server", "backlog"] => options.backlog = Some(parse_number(&key, &value)?),
+            ["server", "max_connections"] => options.max_connections = Some(parse_number(&key, &value)?),
+            ["server", "keep_alive"] => options.keep_alive = Some(Duration::from_secs(parse_number(&key, &value)?)),
+            ["server", "client_request_timeout"] => options.client_request_timeout = Some(Duration::from_millis(parse_number(&key, &value)?)),
+            ["server", "client_disconnect_timeout"] => options.client_disconnect_timeout = Some(Duration::from_millis(parse_number(&key, &value)?)),
+            ["server", "http2"] => options.http2 = Some(parse_bool(&key, &value)?),
+            _ => (),
+        }
+    }
+    Ctx::set_server_options(Ctx::server_options().merge(&options));
+    for (name, fields) in mounts {
+        let Some(dir) = fields.get("dir") else {
+            Err(Error::new(format!("`server.static.{}.dir` is required", name)))?
+        };
+        let mut static_files = StaticFiles::new(dir);
+        if let Some(index) = fields.get("index") {
+            static_files = static_files.index(Some(index.clone()).filter(|i| !i.is_empty()));
+        }
+        if let Some(fallback) = fields.get("fallback") {
+            static_files = static_files.fallback(fallback.clone());
+        }
+        if let Some(precompressed) = fields.get("precompressed") {
+            static_files = static_files.precompressed(parse_bool(&format!("server.static.{}.precompressed", name), precompressed)?);
+        }
+        if let Some(cache_control) = fields.get("cache_control") {
+            static_files = static_files.cache_control(".*", cache_control.clone())?;
+        }
+        let prefix = fields.get("prefix").cloned().unwrap_or_else(|| format!("/{}", name));
+        Ctx::insert_static_files(&prefix, static_files);
+    }
+    Ok(())
+}
+
+/// Register the webhooks of the config files, e.g.
+///
+/// ```toml
+/// [webhooks.orders]
+/// url = "https://hooks.example.com/orders"
+/// max_attempts = 5
+/// ```
+///
+/// with the secret in `TEO_WEBHOOKS_ORDERS_SECRET` or under `secret`. Models
+/// subscribe in their callbacks, see
+/// [`define_pipeline_items`](crate::webhook::outbox::define_pipeline_items).
+/// A webhook of the app with the same name keeps its subscriptions. Runs
+/// before the schema is loaded, the files are read without it.
+pub(crate) fn apply_webhooks(env: Option<&str>) -> Result<()> {
+    let mut webhooks: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+    for (key, (value, _)) in read_profiles(env)? {
+        if let ["webhooks", name, field] = key.split('.').collect::<Vec<&str>>().as_slice() {
+            webhooks.entry(name.to_string()).or_default().insert(field.to_string(), value);
+        }
+    }
+    for (name, mut fields) in webhooks {
+        if let Ok(secret) = std::env::var(env_var_name(&format!("webhooks.{}.secret", name))) {
+            fields.insert("secret".to_owned(), secret);
+        }
+        let existing = Ctx::webhooks().iter().find(|w| w.name == name).cloned();
+        let Some(url) = fields.get("url").cloned().or_else(|| existing.as_ref().map(|w| w.url.clone())) else {
+            Err(Error::new(format!("`webhooks.{}.url` is required", name)))?
+        };
+        let Some(secret) = fields.get("secret").cloned().or_else(|| existing.as_ref().map(|w| w.secret.clone())) else {
+            Err(Error::new(format!("`webhooks.{}.secret` or ${} is required", name, env_var_name(&format!("webhooks.{}.secret", name)))))?
+        };
+        let mut webhook = match existing {
+            Some(existing) => Webhook { url, secret, ..existing },
+            None => Webhook::new(name.as_str(), url, secret),
+        };
+        if let Some(max_attempts) = fields.get("max_attempts") {
+            webhook = webhook.max_attempts(parse_number(&format!("webhooks.{}.max_attempts", name), max_attempts)?);
+        }
+        Ctx::insert_webhook(webhook);
+    }
+    Ok(())
+}
+
+/// Whether the environment is marked production, with `TEO_PRODUCTION` or
+/// in its profile, e.g. `teo.live.toml`:
+///
+/// ```toml
+/// production = true
+/// ```
+///
+/// Destructive operations refuse to run there unless forced, see
+/// [`purge::guard`](crate::purge::guard).
+pub(crate) fn is_production(env: Option<&str>) -> Result<bool> {
+    if let Ok(value) = std::env::var(env_var_name(PRODUCTION)) {
+        return parse_bool(PRODUCTION, &value);
+    }
+    production_of(&read_profiles(env)?)
+}
+
+fn production_of(profile: &BTreeMap<String, (String, String)>) -> Result<bool> {
+    match profile.get(PRODUCTION) {
+        Some((value, file)) => parse_bool(&format!("{}: {}", file, PRODUCTION), value),
+        None => Ok(false),
+    }
+}
+
+/// Keys of the base file and the profile of the environment, which wins.
+fn read_profiles(env: Option<&str>) -> Result<BTreeMap<String, (String, String)>> {
+    let mut files = vec![BASE_FILE.to_owned()];
+    if let Some(env) = env {
+        files.push(format!("teo.{}.toml", env));
+    }
+    let mut result = BTreeMap::new();
+    for file in files {
+        for (key, value) in read_profile(&file)? {
+            result.insert(key, (value, file.clone()));
+        }
+    }
+    Ok(result)
+}
+
+/// The overridden settings, in the order of keys.
+fn resolve(namespace: &Namespace, env: Option<&str>) -> Result<Vec<(String, String, Layer)>> {
+    resolve_profiles(namespace, read_profiles(env)?)
+}
+
+/// The overridden settings of `profile` and the environment variables.
+fn resolve_profiles(namespace: &Namespace, mut profile: BTreeMap<String, (String, String)>) -> Result<Vec<(String, String, Layer)>> {
+    let mut result = vec![];
+    for key in keys(namespace) {
+        let var = env_var_name(&key);
+        if let Ok(value) = std::env::var(&var) {
+            result.push((key.clone(), value, Layer::Env(var)));
+        } else if let Some((value, file)) = profile.get(&key) {
+            result.push((key.clone(), value.clone(), Layer::Profile(file.clone())));
+        }
+        profile.remove(&key);
+    }
+    for (key, (value, file)) in profile {
+        if !is_server_setting(&key) {
+            Err(Error::new(format!("{}: `{}` is not a setting declared in the schema", file, key)))?
+        }
+        result.push((key, value, Layer::Profile(file)));
+    }
+    Ok(result)
+}
+
+/// Settings only the config files declare.
+fn is_server_setting(key: &str) -> bool {
+    key == PRODUCTION || SERVER_OPTIONS.contains(&key) || is_static_setting(key) || is_webhook_setting(key)
+}
+
+fn is_webhook_setting(key: &str) -> bool {
+    matches!(key.split('.').collect::<Vec<&str>>().as_slice(), ["webhooks", _, "url" | "secret" | "max_attempts"])
+}
+
+fn is_static_setting(key: &str) -> bool {
+    matches!(key.split('.').collect::<Vec<&str>>().as_slice(), ["server", "static", _, "dir" | "prefix" | "index" | "fallback" | "precompressed" | "cache_control"])
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool> {
+    match value {
+        "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => Err(Error::new(format!("invalid value `{}` of `{}`, expect true or false", value, key))),
+    }
+}
+
+fn parse_number<T>(key: &str, value: &str) -> Result<T> where T: FromStr {
+    value.parse().map_err(|_| Error::new(format!("invalid value `{}` of `{}`, expect a number", value, key)))
+}
+
+fn read_profile(file: &str) -> Result<BTreeMap<String, String>> {
+    if !Path::new(file).exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = match fs::read_to_string(file) {
+        Ok(content) => content,
+        Err(e) => Err(Error::new(format!("cannot read {}: {}", file, e)))?,
+    };
+    let table: toml::Table = match content.parse() {
+        Ok(table) => table,
+        Err(e) => Err(Error::new(format!("cannot parse {}: {}", file, e)))?,
+    };
+    let mut result = BTreeMap::new();
+    flatten(&table, "", &mut result);
+    Ok(result)
+}
+
+fn flatten(table: &toml::Table, prefix: &str, result: &mut BTreeMap<String, String>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match value {
+            toml::Value::Table(table) => flatten(table, &key, result),
+            toml::Value::String(value) => { result.insert(key, value.clone()); }
+            value => { result.insert(key, value.to_string()); }
+        }
+    }
+}
+
+/// Keys of the settings declared in the schema.
+fn keys(namespace: &Namespace) -> Vec<String> {
+    let mut result = vec![];
+    let mut namespaces = vec![namespace];
+    while let Some(namespace) = namespaces.pop() {
+        namespaces.extend(namespace.namespaces.values());
+        if namespace.connector.is_some() {
+            result.push(format!("{}connector.url", namespace_prefix(namespace)));
+        }
+    }
+    if namespace.server.is_some() {
+        result.push("server.bind".to_owned());
+        result.push("server.path_prefix".to_owned());
+    }
+    result.extend(SERVER_OPTIONS.iter().map(|key| key.to_string()));
+    if namespace.debug.is_some() {
+        result.push("debug.log_queries".to_owned());
+        result.push("debug.log_migrations".to_owned());
+        result.push("debug.log_seed_records".to_owned());
+    }
+    for name in namespace.clients.keys() {
+        result.push(format!("clients.{}.dest", name));
+    }
+    for name in namespace.entities.keys() {
+        result.push(format!("entities.{}.dest", name));
+    }
+    result.sort();
+    result
+}
+
+fn set(namespace: &mut Namespace, key: &str, value: &str) -> Result<()> {
+    let invalid = |expected: &str| Error::new(format!("invalid value `{}` of `{}`, expect {}", value, key, expected));
+    let segments: Vec<&str> = key.split('.').collect();
+    match segments.as_slice() {
+        ["server", "bind"] => {
+            let Some((host, port)) = value.rsplit_once(':') else {
+                Err(invalid("host:port"))?
+            };
+            let port: i32 = port.parse().map_err(|_| invalid("host:port"))?;
+            namespace.server.as_mut().unwrap().bind = (host.to_owned(), port);
+        }
+        ["server", "path_prefix"] => {
+            namespace.server.as_mut().unwrap().path_prefix = if value.is_empty() { None } else { Some(value.to_owned()) };
+        }
+        ["debug", "log_queries"] => namespace.debug.as_mut().unwrap().log_queries = parse_bool(key, value)?,
+        ["debug", "log_migrations"] => namespace.debug.as_mut().unwrap().log_migrations = parse_bool(key, value)?,
+        ["debug", "log_seed_records"] => namespace.debug.as_mut().unwrap().log_seed_records = parse_bool(key, value)?,
+        ["clients", name, "dest"] => namespace.clients.get_mut(*name).unwrap().dest = value.to_owned(),
+        ["entities", name, "dest"] => namespace.entities.get_mut(*name).unwrap().dest = value.to_owned(),
+        ["connector", "url"] => namespace.connector.as_mut().unwrap().url = value.to_owned(),
+        ["namespaces", path @ .., "connector", "url"] => {
+            let mut child = namespace;
+            for name in path {
+                let Some(next) = child.namespaces.get_mut(*name) else {
+                    Err(Error::new(format!("namespace of `{}` is not found", key)))?
+                };
+                child = next;
+            }
+            child.connector.as_mut().unwrap().url = value.to_owned();
+        }
+        _ => Err(Error::new(format!("unknown setting `{}`", key)))?,
+    }
+    Ok(())
+}
+
+/// Print the effective configuration and where each overridden value comes
+/// from.
+pub(crate) fn print(env: Option<&str>) -> Result<()> {
+    let namespace = Ctx::main_namespace();
+    let overrides = resolve(namespace, env)?;
+    println!("{} {}", "environment".bold(), env.unwrap_or("(none)"));
+    for key in keys(namespace) {
+        let value = value(namespace, &key);
+        let layer = overrides.iter().find(|(k, _, _)| k == &key).map(|(_, _, l)| l.clone()).unwrap_or(Layer::Schema);
+        println!("{:<40} {:<48} {}", key, mask_password(&value), format!("[{}]", layer.describe()).dimmed());
+    }
+    for (key, value, layer) in overrides.iter().filter(|(k, _, _)| is_static_setting(k) || is_webhook_setting(k)) {
+        let value = if key.ends_with(".secret") { "***" } else { value.as_str() };
+        println!("{:<40} {:<48} {}", key, value, format!("[{}]", layer.describe()).dimmed());
+    }
+    Ok(())
+}
+
+fn value(namespace: &Namespace, key: &str) -> String {
+    let segments: Vec<&str> = key.split('.').collect();
+    match segments.as_slice() {
+        ["server", "bind"] => namespace.server.as_ref().map(|s| format!("{}:{}", s.bind.0, s.bind.1)).unwrap_or_default(),
+        ["server", "path_prefix"] => namespace.server.as_ref().and_then(|s| s.path_prefix.clone()).unwrap_or_default(),
+        ["server", "workers"] => Ctx::server_options().workers.map(|w| w.to_string()).unwrap_or_default(),
+        ["server", "backlog"] => Ctx::server_options().backlog.map(|b| b.to_string()).unwrap_or_default(),
+        ["server", "max_connections"] => Ctx::server_options().max_connections.map(|m| m.to_string()).unwrap_or_default(),
+        ["server", "keep_alive"] => Ctx::server_options().keep_alive.map(|k| k.as_secs().to_string()).unwrap_or_default(),
+        ["server", "client_request_timeout"] => Ctx::server_options().client_request_timeout.map(|t| t.as_millis().to_string()).unwrap_or_default(),
+        ["server", "client_disconnect_timeout"] => Ctx::server_options().client_disconnect_timeout.map(|t| t.as_millis().to_string()).unwrap_or_default(),
+        ["server", "http2"] => Ctx::server_options().http2().to_string(),
+        ["debug", "log_queries"] => namespace.debug.as_ref().map(|d| d.log_queries.to_string()).unwrap_or_default(),
+        ["debug", "log_migrations"] => namespace.debug.as_ref().map(|d| d.log_migrations.to_string()).unwrap_or_default(),
+        ["debug", "log_seed_records"] => namespace.debug.as_ref().map(|d| d.log_seed_records.to_string()).unwrap_or_default(),
+        ["clients", name, "dest"] => namespace.clients.get(*name).map(|c| c.dest.clone()).unwrap_or_default(),
+        ["entities", name, "dest"] => namespace.entities.get(*name).map(|e| e.dest.clone()).unwrap_or_default(),
+        ["connector", "url"] => namespace.connector.as_ref().map(|c| c.url.clone()).unwrap_or_default(),
+        ["namespaces", path @ .., "connector", "url"] => path.iter()
+            .try_fold(namespace, |namespace, name| namespace.namespaces.get(*name))
+            .and_then(|n| n.connector.as_ref())
+            .map(|c| c.url.clone())
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+fn namespace_prefix(namespace: &Namespace) -> String {
+    if namespace.path.is_empty() { String::new() } else { format!("namespaces.{}.", namespace.path.join(".")) }
+}
+
+fn env_var_name(key: &str) -> String {
+    format!("TEO_{}", key.replace('.', "_").to_uppercase())
+}
+
+/// Hide the password of a connection url, in the user info or in a query
+/// parameter like `?password=`.
+pub(crate) fn mask_password(url: &str) -> String {
+    static USER_INFO: Lazy<Regex> = Lazy::new(|| Regex::new(r"://([^:/?#@]*):([^/?#@]+)@").unwrap());
+    static QUERY: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)([?&](?:password|pass|pwd)=)[^&#]*").unwrap());
+    let url = USER_INFO.replace(url, "://$1:***@");
+    QUERY.replace_all(&url, "${1}***").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_passwords() {
+        assert_eq!(mask_password("postgres://app:s3cret@db:5432/app"), "postgres://app:***@db:5432/app");
+        assert_eq!(mask_password("redis://:s3cret@cache:6379"), "redis://:***@cache:6379");
+        assert_eq!(mask_password("mysql://db/app?user=app&password=s3cret&ssl=true"), "mysql://db/app?user=app&password=***&ssl=true");
+        assert_eq!(mask_password("mongodb://db/app?Password=s3cret"), "mongodb://db/app?Password=***");
+        assert_eq!(mask_password("sqlite:app.sqlite"), "sqlite:app.sqlite");
+        assert_eq!(mask_password("postgres://app@db/app"), "postgres://app@db/app");
+        assert_eq!(mask_password("postgres://db:5432/app?tag=a@b"), "postgres://db:5432/app?tag=a@b");
+    }
+
+    #[test]
+    fn flattens_tables_into_dotted_keys() {
+        let table: toml::Table = "[server]\nbind = \"0.0.0.0:80\"\nworkers = 4\n\n[namespaces.analytics.connector]\nurl = \"mysql://db\"\n".parse().unwrap();
+        let mut result = BTreeMap::new();
+        flatten(&table, "", &mut result);
+        assert_eq!(result.get("server.bind").map(|s| s.as_str()), Some("0.0.0.0:80"));
+        assert_eq!(result.get("server.workers").map(|s| s.as_str()), Some("4"));
+        assert_eq!(result.get("namespaces.analytics.connector.url").map(|s| s.as_str()), Some("mysql://db"));
+        assert_eq!(result.len(), 3);
+    }
+
+    #[test]
+    fn resolves_environment_over_profiles() {
+        let namespace = Namespace::main();
+        let profile = BTreeMap::from([
+            ("server.backlog".to_owned(), ("64".to_owned(), "teo.toml".to_owned())),
+            ("server.workers".to_owned(), ("2".to_owned(), "teo.production.toml".to_owned())),
+            ("server.static.assets.dir".to_owned(), ("public".to_owned(), "teo.toml".to_owned())),
+        ]);
+        std::env::set_var("TEO_SERVER_BACKLOG", "128");
+        let resolved = resolve_profiles(&namespace, profile).unwrap();
+        std::env::remove_var("TEO_SERVER_BACKLOG");
+        assert!(resolved.contains(&("server.backlog".to_owned(), "128".to_owned(), Layer::Env("TEO_SERVER_BACKLOG".to_owned()))));
+        assert!(resolved.contains(&("server.workers".to_owned(), "2".to_owned(), Layer::Profile("teo.production.toml".to_owned()))));
+        assert!(resolved.contains(&("server.static.assets.dir".to_owned(), "public".to_owned(), Layer::Profile("teo.toml".to_owned()))));
+    }
+
+    #[test]
+    fn reads_production_flag() {
+        let profile = BTreeMap::from([("production".to_owned(), ("true".to_owned(), "teo.live.toml".to_owned()))]);
+        assert!(production_of(&profile).unwrap());
+        assert!(!production_of(&BTreeMap::new()).unwrap());
+        let profile = BTreeMap::from([("production".to_owned(), ("yes".to_owned(), "teo.live.toml".to_owned()))]);
+        assert!(production_of(&profile).is_err());
+        let resolved = resolve_profiles(&Namespace::main(), BTreeMap::from([("production".to_owned(), ("true".to_owned(), "teo.live.toml".to_owned()))])).unwrap();
+        assert_eq!(resolved.len(), 1);
+    }
+
+    #[test]
+    fn refuses_settings_not_declared() {
+        let profile = BTreeMap::from([("connector.url".to_owned(), ("sqlite::memory:".to_owned(), "teo.toml".to_owned()))]);
+        let error = resolve_profiles(&Namespace::main(), profile).unwrap_err();
+        assert!(format!("{}", error).contains("`connector.url` is not a setting declared in the schema"));
+    }
+
+    #[test]
+    fn refuses_invalid_and_unknown_settings() {
+        let mut namespace = Namespace::main();
+        assert!(set(&mut namespace, "server.bind", "no-port").is_err());
+        assert!(set(&mut namespace, "namespaces.missing.connector.url", "sqlite::memory:").is_err());
+        assert!(set(&mut namespace, "server.unknown", "1").is_err());
+    }
+}
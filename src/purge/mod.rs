@@ -1,12 +1,116 @@
-use teo_result::Result;
+use std::io::{stdin, stdout, IsTerminal, Write};
+use std::time::Instant;
+use key_path::path;
+use serde_json::json;
+use teo_result::{Error, Result};
+use teo_runtime::connection::transaction;
+use teo_runtime::model::Model;
+use teo_runtime::teon;
 use crate::app::ctx::Ctx;
+use crate::cli::command::PurgeCommand;
+use crate::{dump, profile};
+use crate::message::{event, info_message, json_output, namespace_name};
 
+/// Clear every model under every connector without dropping tables.
 pub(crate) async fn purge() -> Result<()> {
     let ctx = Ctx::conn_ctx();
     for (namespace_path, connection) in ctx.connections_iter() {
         let namespace = ctx.namespace().namespace_at_path(&namespace_path.iter().map(AsRef::as_ref).collect()).unwrap();
+        let start = Instant::now();
         let transaction = connection.no_transaction().await?;
         transaction.purge(namespace.models_under_connector()).await?;
+        event("purge", json!({
+            "namespace": namespace_name(namespace_path),
+            "ms": start.elapsed().as_millis() as u64,
+        }));
+    }
+    Ok(())
+}
+
+/// Clear only `models`, grouped by the connector they are stored with.
+pub(crate) async fn purge_models(models: &Vec<&'static Model>) -> Result<()> {
+    let ctx = Ctx::conn_ctx();
+    for (namespace_path, connection) in ctx.connections_iter() {
+        let namespace = ctx.namespace().namespace_at_path(&namespace_path.iter().map(AsRef::as_ref).collect()).unwrap();
+        let selected: Vec<&'static Model> = namespace.models_under_connector().into_iter()
+            .filter(|m| models.iter().any(|model| model.path() == m.path()))
+            .collect();
+        if selected.is_empty() { continue }
+        let start = Instant::now();
+        let transaction = connection.no_transaction().await?;
+        transaction.purge(selected.clone()).await?;
+        event("purge", json!({
+            "namespace": namespace_name(namespace_path),
+            "models": selected.iter().map(|m| m.path().join(".")).collect::<Vec<String>>(),
+            "ms": start.elapsed().as_millis() as u64,
+        }));
+    }
+    Ok(())
+}
+
+/// Refuse destructive operations, e.g. `purge`, when the active environment
+/// is marked production unless forced, see [`profile::is_production`].
+pub(crate) fn guard(operation: &str, force: bool) -> Result<()> {
+    let env = Ctx::cli().env();
+    if !force && profile::is_production(env)? {
+        let mut error = Error::new(match env {
+            Some(env) => format!("refusing to {} in the production environment `{}`", operation, env),
+            None => format!("refusing to {} in production", operation),
+        });
+        error.code = 403;
+        Err(error)?
+    }
+    Ok(())
+}
+
+/// The `purge` command. Selected models and their row counts are shown
+/// and confirmed before anything is deleted.
+pub(crate) async fn purge_command(command: &PurgeCommand, silent: bool) -> Result<()> {
+    guard("purge", command.force)?;
+    let models = dump::models(command.namespace.as_deref(), command.models.as_ref())?;
+    let ctx = transaction::Ctx::new(Ctx::conn_ctx().clone());
+    let mut counts = vec![];
+    for model in &models {
+        counts.push(ctx.count_objects(model, &teon!({}), path![]).await?);
+    }
+    let total: usize = counts.iter().sum();
+    if json_output() {
+        event("count", json!({
+            "models": models.iter().zip(&counts).map(|(m, c)| json!({ "model": m.path().join("."), "count": c })).collect::<Vec<_>>(),
+            "total": total,
+        }));
+    } else {
+        println!("+-{:<48}-+-{:<12}-+", "-".repeat(48), "-".repeat(12));
+        println!("| {:^48} | {:^12} |", "Model", "Records");
+        println!("+-{:<48}-+-{:<12}-+", "-".repeat(48), "-".repeat(12));
+        for (model, count) in models.iter().zip(&counts) {
+            println!("| {:<48} | {:>12} |", model.path().join("."), count);
+        }
+        println!("+-{:<48}---{:<12}-+", "-".repeat(48), "-".repeat(12));
+    }
+    if !command.yes {
+        if !stdin().is_terminal() {
+            Err(Error::new("pass `--yes` to purge without a terminal"))?
+        }
+        print!("Delete {} record(s) of {} model(s)? [y/N]: ", total, models.len());
+        let _ = stdout().flush();
+        let mut answer = String::new();
+        if let Err(e) = stdin().read_line(&mut answer) {
+            Err(Error::new(format!("cannot read answer: {}", e)))?
+        }
+        if !matches!(answer.trim(), "y" | "Y" | "yes") {
+            Err(Error::new("purge is cancelled"))?
+        }
+    }
+    if let Some(snapshot) = &command.snapshot {
+        dump::snapshot(&models, snapshot, silent).await?;
+        if !silent {
+            info_message(format!("snapshot written to {}", snapshot));
+        }
+    }
+    purge_models(&models).await?;
+    if !silent {
+        info_message(format!("purged {} record(s) of {} model(s)", total, models.len()));
     }
     Ok(())
 }
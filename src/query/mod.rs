@@ -0,0 +1,121 @@
+use std::fs;
+use std::io::{read_to_string, stdin};
+use actix_web::http::Method as HttpMethod;
+use actix_web::HttpMessage;
+use actix_web::test::{call_service, init_service, TestRequest};
+use key_path::path;
+use serde_json::{Map, Value as JsonValue};
+use teo_result::{Error, Result};
+use teo_runtime::connection::transaction;
+use teo_runtime::model::field::typed::Typed;
+use teo_runtime::model::Object;
+use teo_runtime::teon;
+use crate::app::ctx::Ctx;
+use crate::cli::command::QueryCommand;
+use crate::console::{literal, uri};
+use crate::dump::value_for_type;
+use crate::message::json_output;
+use crate::server::make::{make_server_app, Impersonation};
+use crate::test::response::TestResponse;
+
+/// Call an action or a handler once, e.g. `query User.updateMany '{...}'`.
+///
+/// The request goes through the same service as `serve`, so input
+/// validation, pipelines, middlewares and permissions apply. Pass `--token`
+/// to call as the identity the token belongs to, or `--as User:1` to call as
+/// a record without signing a token.
+pub(crate) async fn query(command: &QueryCommand) -> Result<()> {
+    let segments: Vec<&str> = command.target.split('.').collect();
+    if segments.iter().any(|s| s.is_empty()) {
+        Err(Error::new(format!("invalid target `{}`, expect a call like `User.findMany`", command.target)))?
+    }
+    let body = body(command.body.as_ref().map(|b| b.as_str()))?;
+    let method = match HttpMethod::from_bytes(command.method.to_uppercase().as_bytes()) {
+        Ok(method) => method,
+        Err(_) => Err(Error::new(format!("unknown http method {}", command.method)))?,
+    };
+    let mut request = TestRequest::default().method(method.clone()).uri(&uri(&segments));
+    if method != HttpMethod::GET && method != HttpMethod::DELETE {
+        request = request.set_json(body);
+    }
+    if let Some(token) = &command.token {
+        request = request.insert_header(("Authorization", format!("Bearer {}", token)));
+    }
+    for header in &command.headers {
+        let Some((name, value)) = header.split_once(':') else {
+            Err(Error::new(format!("invalid header `{}`, expect `Name: value`", header)))?
+        };
+        request = request.insert_header((name.trim().to_owned(), value.trim().to_owned()));
+    }
+    let request = request.to_request();
+    if let Some(identity) = &command.identity {
+        request.extensions_mut().insert(Impersonation(identity_object(identity).await?));
+    }
+    let service = init_service(make_server_app(Ctx::conn_ctx().namespace(), Ctx::static_files(), Ctx::admin_dashboard())).await;
+    let response = TestResponse::from_service_response(call_service(&service, request).await).await?;
+    let output = match response.json() {
+        Ok(json) if json_output() => json.to_string(),
+        Ok(json) => serde_json::to_string_pretty(&json).unwrap(),
+        Err(_) => response.text().unwrap_or_default(),
+    };
+    println!("{}", output);
+    if response.status() >= 400 {
+        Err(Error::new(format!("{} responded with status {}", command.target, response.status())))?
+    }
+    Ok(())
+}
+
+/// The record of `Model:<id>`, found by its primary key.
+async fn identity_object(argument: &str) -> Result<Object> {
+    let Some((model_path, id)) = argument.split_once(':') else {
+        Err(Error::new(format!("invalid identity `{}`, expect `Model:<id>`", argument)))?
+    };
+    let Some(model) = Ctx::main_namespace().model_at_path(&model_path.split('.').collect()) else {
+        Err(Error::new(format!("model `{}` is not found", model_path)))?
+    };
+    let key = match model.primary_index().map(|index| index.keys()) {
+        Some(keys) if keys.len() == 1 => &keys[0],
+        _ => Err(Error::new(format!("`{}` has no single field primary key", model_path)))?,
+    };
+    let Some(field) = model.field(key) else {
+        Err(Error::new(format!("primary key `{}` of `{}` is not a field", key, model_path)))?
+    };
+    // numbers are parsed, other types take the id as a string
+    let id_json = match serde_json::from_str::<JsonValue>(id) {
+        Ok(JsonValue::Number(number)) => JsonValue::Number(number),
+        _ => JsonValue::String(id.to_owned()),
+    };
+    let id = value_for_type(&id_json, field.r#type()).map_err(|message| Error::new(format!("invalid id of `{}`: {}", model_path, message)))?;
+    let mut finder = teon!({});
+    finder.as_dictionary_mut().unwrap().insert(key.clone(), id);
+    let ctx = transaction::Ctx::new(Ctx::conn_ctx().clone());
+    match ctx.find_unique::<Object>(model, &teon!({ "where": finder }), None, path![]).await? {
+        Some(object) => Ok(object),
+        None => Err(Error::new(format!("`{}` is not found", argument))),
+    }
+}
+
+/// The request body from an argument, `@file` or `-` for stdin. Besides
+/// JSON, the literals of the console are accepted.
+fn body(argument: Option<&str>) -> Result<JsonValue> {
+    let source = match argument {
+        None => return Ok(JsonValue::Object(Map::new())),
+        Some("-") => match read_to_string(stdin()) {
+            Ok(source) => source,
+            Err(e) => Err(Error::new(format!("cannot read stdin: {}", e)))?,
+        },
+        Some(argument) if argument.starts_with('@') => match fs::read_to_string(&argument[1..]) {
+            Ok(source) => source,
+            Err(e) => Err(Error::new(format!("cannot read {}: {}", &argument[1..], e)))?,
+        },
+        Some(argument) => argument.to_owned(),
+    };
+    let source = source.trim();
+    if source.is_empty() {
+        return Ok(JsonValue::Object(Map::new()));
+    }
+    match serde_json::from_str(source) {
+        Ok(body) => Ok(body),
+        Err(_) => literal::parse(source).map_err(|message| Error::new(format!("invalid body: {}", message))),
+    }
+}
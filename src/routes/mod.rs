@@ -0,0 +1,230 @@
+use std::str::FromStr;
+use actix_http::Method as HttpMethod;
+use serde_json::{json, Value as JsonValue};
+use teo_parser::ast::handler::HandlerInputFormat;
+use teo_result::{Error, Result};
+use teo_runtime::handler::handler::Method;
+use teo_runtime::handler::Handler;
+use teo_runtime::namespace::Namespace;
+use crate::app::ctx::Ctx;
+use crate::cli::command::{RoutesCommand, RoutesMatchCommand};
+use crate::console::BUILTIN_ACTIONS;
+use crate::message::json_output;
+use crate::server::make::{method_from, resolve_handler, HandlerResolved};
+
+/// An endpoint as `serve` resolves it.
+struct Route {
+    method: Method,
+    url: String,
+    handler: String,
+    builtin: bool,
+    format: HandlerInputFormat,
+    reachable: bool,
+    /// The middlewares a request passes through, outermost first.
+    middlewares: Vec<String>,
+}
+
+impl Route {
+
+    fn to_json(&self) -> JsonValue {
+        json!({
+            "method": method_str(self.method),
+            "url": self.url,
+            "handler": self.handler,
+            "kind": if self.builtin { "builtin" } else { "custom" },
+            "format": format_str(self.format),
+            "reachable": self.reachable,
+            "middlewares": self.middlewares,
+        })
+    }
+}
+
+pub(crate) fn routes(command: &RoutesCommand) -> Result<()> {
+    match &command.r#match {
+        Some(match_command) => explain(match_command),
+        None => list(),
+    }
+}
+
+fn list() -> Result<()> {
+    let routes = collect();
+    if json_output() {
+        println!("{}", JsonValue::Array(routes.iter().map(|r| r.to_json()).collect()));
+        return Ok(());
+    }
+    println!("+-{:<7}-+-{:<48}-+-{:<32}-+-{:<7}-+-{:<6}-+-{:<32}-+", "-".repeat(7), "-".repeat(48), "-".repeat(32), "-".repeat(7), "-".repeat(6), "-".repeat(32));
+    println!("| {:^7} | {:^48} | {:^32} | {:^7} | {:^6} | {:^32} |", "Method", "URL", "Handler", "Kind", "Format", "Middlewares");
+    println!("+-{:<7}-+-{:<48}-+-{:<32}-+-{:<7}-+-{:<6}-+-{:<32}-+", "-".repeat(7), "-".repeat(48), "-".repeat(32), "-".repeat(7), "-".repeat(6), "-".repeat(32));
+    if routes.is_empty() {
+        println!("| {:^147} |", "No routes.");
+    } else {
+        for route in &routes {
+            // shadowed routes never receive a request, mark them instead of hiding them
+            let kind = if route.reachable { if route.builtin { "builtin" } else { "custom" } } else { "shadow" };
+            println!("| {:<7} | {:<48} | {:<32} | {:<7} | {:<6} | {:<32} |", method_str(route.method), route.url, route.handler, kind, format_str(route.format), route.middlewares.join(", "));
+        }
+    }
+    println!("+-{:<7}---{:<48}---{:<32}---{:<7}---{:<6}---{:<32}-+", "-".repeat(7), "-".repeat(48), "-".repeat(32), "-".repeat(7), "-".repeat(6), "-".repeat(32));
+    Ok(())
+}
+
+/// Explain which handler a request would hit.
+fn explain(command: &RoutesMatchCommand) -> Result<()> {
+    let main_namespace = Ctx::main_namespace();
+    let method = match HttpMethod::from_str(&command.method.to_uppercase()) {
+        Ok(method) => method_from(&method)?,
+        Err(_) => Err(Error::new(format!("unknown http method {}", command.method)))?,
+    };
+    let path_prefix = main_namespace.server.as_ref().and_then(|s| s.path_prefix.as_ref());
+    let path = main_namespace.handler_map.remove_path_prefix(command.path.as_str(), path_prefix.map(|s| s.as_str()));
+    let (match_result, default) = if let Some(m) = main_namespace.handler_map.r#match(method, path) {
+        (Some(m), false)
+    } else if let Some(m) = main_namespace.handler_map.default_match(method, path) {
+        (Some(m), true)
+    } else {
+        (None, false)
+    };
+    let resolved = match_result.as_ref().and_then(|m| resolve_handler(main_namespace, m).ok());
+    let explanation = match (&match_result, resolved) {
+        (Some(match_result), Some((dest_namespace, handler_resolved))) => {
+            let handler = match_result.path.iter().chain(std::iter::once(&match_result.name)).cloned().collect::<Vec<String>>().join(".");
+            let (builtin, format) = match handler_resolved {
+                HandlerResolved::Custom(handler) => (false, handler.format),
+                HandlerResolved::Builtin(_, _) => (true, HandlerInputFormat::Json),
+            };
+            json!({
+                "matched": true,
+                "by": if default { "default route" } else { "handler map" },
+                "handler": handler,
+                "kind": if builtin { "builtin" } else { "custom" },
+                "format": format_str(format),
+                "middlewares": middlewares(main_namespace, &dest_namespace.path),
+            })
+        }
+        (Some(match_result), None) => json!({
+            "matched": false,
+            "reason": format!("`{}` is matched by the url but no handler or action with this name exists", match_result.path.iter().chain(std::iter::once(&match_result.name)).cloned().collect::<Vec<String>>().join(".")),
+        }),
+        (None, _) => json!({
+            "matched": false,
+            "reason": "no url matches, the request is not found",
+        }),
+    };
+    if json_output() {
+        println!("{}", explanation);
+        return Ok(());
+    }
+    println!("{} {}", method_str(method), command.path);
+    if explanation["matched"] == JsonValue::Bool(true) {
+        println!("  handler:     {}", explanation["handler"].as_str().unwrap());
+        println!("  kind:        {}", explanation["kind"].as_str().unwrap());
+        println!("  matched by:  {}", explanation["by"].as_str().unwrap());
+        println!("  format:      {}", explanation["format"].as_str().unwrap());
+        let middlewares: Vec<&str> = explanation["middlewares"].as_array().unwrap().iter().filter_map(|m| m.as_str()).collect();
+        println!("  middlewares: {}", if middlewares.is_empty() { "none".to_owned() } else { middlewares.join(", ") });
+    } else {
+        println!("  {} (404)", explanation["reason"].as_str().unwrap());
+    }
+    Ok(())
+}
+
+fn collect() -> Vec<Route> {
+    let main_namespace = Ctx::main_namespace();
+    let path_prefix = main_namespace.server.as_ref().and_then(|s| s.path_prefix.as_ref()).map(|p| format!("/{}", p.trim_matches('/'))).unwrap_or_default();
+    let mut routes = vec![];
+    let mut namespaces = vec![main_namespace];
+    while let Some(namespace) = namespaces.pop() {
+        namespaces.extend(namespace.namespaces.values());
+        let middlewares = middlewares(main_namespace, &namespace.path);
+        for model_name in namespace.models.keys() {
+            let group = namespace.model_handler_groups.get(model_name);
+            for action in BUILTIN_ACTIONS {
+                if group.map_or(false, |g| g.handlers.contains_key(action)) { continue }
+                let path = join(&namespace.path, Some(model_name), action);
+                routes.push(route(main_namespace, Method::Post, format!("/{}", path.replace('.', "/")), path, true, HandlerInputFormat::Json, &middlewares));
+            }
+            if let Some(group) = group {
+                for (name, handler) in &group.handlers {
+                    routes.push(custom_route(main_namespace, &namespace.path, Some(model_name), name, handler, &middlewares));
+                }
+            }
+        }
+        for (group_name, group) in &namespace.handler_groups {
+            for (name, handler) in &group.handlers {
+                routes.push(custom_route(main_namespace, &namespace.path, Some(group_name), name, handler, &middlewares));
+            }
+        }
+        for (name, handler) in &namespace.handlers {
+            routes.push(custom_route(main_namespace, &namespace.path, None, name, handler, &middlewares));
+        }
+    }
+    for route in routes.iter_mut() {
+        route.url = format!("{}{}", path_prefix, route.url);
+    }
+    routes.sort_by(|a, b| a.url.cmp(&b.url).then(method_str(a.method).cmp(method_str(b.method))));
+    routes
+}
+
+fn custom_route(main_namespace: &Namespace, namespace_path: &Vec<String>, group: Option<&String>, name: &str, handler: &Handler, middlewares: &Vec<String>) -> Route {
+    let url = match &handler.url {
+        Some(url) if handler.ignore_prefix => format!("/{}", url.trim_start_matches('/')),
+        Some(url) => {
+            let mut segments: Vec<&str> = namespace_path.iter().map(|s| s.as_str()).collect();
+            if let Some(group) = group {
+                segments.push(group.as_str());
+            }
+            segments.push(url.trim_start_matches('/'));
+            format!("/{}", segments.join("/"))
+        }
+        None => format!("/{}", join(namespace_path, group, name).replace('.', "/")),
+    };
+    route(main_namespace, handler.method, url, join(namespace_path, group, name), false, handler.format, middlewares)
+}
+
+/// Resolve `url` back through the handler map, so that routes shadowed by
+/// another handler are reported.
+fn route(main_namespace: &Namespace, method: Method, url: String, handler: String, builtin: bool, format: HandlerInputFormat, middlewares: &Vec<String>) -> Route {
+    let match_result = main_namespace.handler_map.r#match(method, url.as_str())
+        .or_else(|| main_namespace.handler_map.default_match(method, url.as_str()));
+    let reachable = match_result.as_ref().map_or(false, |m| {
+        let full_name = m.path.iter().chain(std::iter::once(&m.name)).cloned().collect::<Vec<String>>().join(".");
+        resolve_handler(main_namespace, m).is_ok() && full_name == handler
+    });
+    Route { method, url, handler, builtin, format, reachable, middlewares: middlewares.clone() }
+}
+
+/// The middlewares of the namespace at `namespace_path`. A namespace runs
+/// the middlewares of its parents before its own.
+fn middlewares(main_namespace: &Namespace, namespace_path: &Vec<String>) -> Vec<String> {
+    let mut result = vec![];
+    for depth in 0..=namespace_path.len() {
+        let path: Vec<&str> = namespace_path[..depth].iter().map(|s| s.as_str()).collect();
+        let Some(namespace) = main_namespace.namespace_at_path(&path) else { continue };
+        if let Some(block) = &namespace.middlewares_block {
+            result.extend(block.uses.iter().map(|u| u.path.join(".")));
+        }
+    }
+    result
+}
+
+fn join(namespace_path: &Vec<String>, group: Option<&String>, name: &str) -> String {
+    namespace_path.iter().chain(group).map(|s| s.as_str()).chain(std::iter::once(name)).collect::<Vec<&str>>().join(".")
+}
+
+fn method_str(method: Method) -> &'static str {
+    match method {
+        Method::Get => "GET",
+        Method::Post => "POST",
+        Method::Patch => "PATCH",
+        Method::Put => "PUT",
+        Method::Delete => "DELETE",
+        Method::Options => "OPTIONS",
+    }
+}
+
+fn format_str(format: HandlerInputFormat) -> &'static str {
+    match format {
+        HandlerInputFormat::Json => "json",
+        HandlerInputFormat::Form => "form",
+    }
+}
@@ -0,0 +1,99 @@
+mod templates;
+
+use std::fs;
+use std::io::{stdin, stdout, IsTerminal, Write};
+use std::path::Path;
+use teo_result::{Error, Result};
+use crate::cli::command::{NewCommand, NewConnector, NewTemplate};
+use crate::message::info_message;
+
+/// Create a project in a new directory. Options which are not passed are
+/// asked for on a terminal, and default to a Rust app with SQLite otherwise.
+///
+/// Programs are Rust closures registered on the app, so a schema only
+/// project has no sample program. Its test calls the API with `query`
+/// instead of the test server.
+pub(crate) fn new(command: &NewCommand) -> Result<()> {
+    let dir = Path::new(&command.dir);
+    if dir.exists() && fs::read_dir(dir).map_or(true, |mut entries| entries.next().is_some()) {
+        Err(Error::new(format!("`{}` already exists and is not empty", command.dir)))?
+    }
+    let interactive = !command.yes && stdin().is_terminal();
+    let template = match command.template {
+        Some(template) => template,
+        None if interactive => match ask("Template, a Rust app or a schema only project", &["rust", "schema"])? {
+            "schema" => NewTemplate::Schema,
+            _ => NewTemplate::Rust,
+        },
+        None => NewTemplate::Rust,
+    };
+    let connector = match command.connector {
+        Some(connector) => connector,
+        None if interactive => match ask("Database", &["sqlite", "mysql", "postgres", "mongo"])? {
+            "mysql" => NewConnector::MySQL,
+            "postgres" => NewConnector::PostgreSQL,
+            "mongo" => NewConnector::MongoDB,
+            _ => NewConnector::SQLite,
+        },
+        None => NewConnector::SQLite,
+    };
+    let name = package_name(dir);
+    let mut files = vec![
+        ("schema.teo", templates::schema(connector)),
+        ("data.teo", templates::data()),
+        (".env", templates::dot_env(connector, &name)),
+        (".gitignore", templates::gitignore(template)),
+    ];
+    if template == NewTemplate::Rust {
+        files.push(("Cargo.toml", templates::cargo_toml(&name)));
+        files.push(("src/main.rs", templates::main_rs()));
+        files.push(("tests/api.rs", templates::api_test()));
+    } else {
+        files.push(("tests/api.sh", templates::api_test_script()));
+    }
+    for (path, content) in files {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            if let Err(e) = fs::create_dir_all(parent) {
+                Err(Error::new(format!("cannot create {}: {}", parent.display(), e)))?
+            }
+        }
+        if let Err(e) = fs::write(&path, content) {
+            Err(Error::new(format!("cannot write {}: {}", path.display(), e)))?
+        }
+    }
+    info_message(format!("created {} in {}", name, command.dir));
+    match template {
+        NewTemplate::Rust => info_message(format!("cd {} && cargo run -- serve", command.dir)),
+        NewTemplate::Schema => {
+            info_message(format!("cd {} && cargo teo serve", command.dir));
+            info_message("run `sh tests/api.sh` to test the API");
+        }
+    }
+    Ok(())
+}
+
+fn ask<'a>(question: &str, choices: &[&'a str]) -> Result<&'a str> {
+    loop {
+        print!("{} ({}) [{}]: ", question, choices.join(", "), choices[0]);
+        let _ = stdout().flush();
+        let mut answer = String::new();
+        if let Err(e) = stdin().read_line(&mut answer) {
+            Err(Error::new(format!("cannot read answer: {}", e)))?
+        }
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Ok(choices[0]);
+        }
+        if let Some(choice) = choices.iter().find(|c| **c == answer) {
+            return Ok(*choice);
+        }
+    }
+}
+
+/// A crate and database name derived from the directory name.
+fn package_name(dir: &Path) -> String {
+    let name = dir.file_name().and_then(|n| n.to_str()).unwrap_or("app");
+    let name: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' }).collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) { format!("app_{}", name) } else { name }
+}
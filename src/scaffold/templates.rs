@@ -0,0 +1,167 @@
+use crate::cli::command::{NewConnector, NewTemplate};
+
+fn url(connector: NewConnector, name: &str) -> String {
+    match connector {
+        NewConnector::SQLite => format!("sqlite:{}.sqlite", name),
+        NewConnector::MySQL => format!("mysql://root@127.0.0.1:3306/{}", name),
+        NewConnector::PostgreSQL => format!("postgres://postgres@127.0.0.1:5432/{}", name),
+        NewConnector::MongoDB => format!("mongodb://127.0.0.1:27017/{}", name),
+    }
+}
+
+/// The main schema. The connector reads its url from `.env`.
+pub(super) fn schema(connector: NewConnector) -> String {
+    let provider = match connector {
+        NewConnector::SQLite => "sqlite",
+        NewConnector::MySQL => "mysql",
+        NewConnector::PostgreSQL => "postgres",
+        NewConnector::MongoDB => "mongo",
+    };
+    let id = match connector {
+        NewConnector::MongoDB => "  @id @auto @map(\"_id\") @readonly\n  id: ObjectId",
+        _ => "  @id @autoIncrement @readonly\n  id: Int",
+    };
+    format!(r#"import "./data"
+
+connector {{
+  provider .{provider}
+  url env("DATABASE_URL")
+}}
+
+server {{
+  bind ("0.0.0.0", 5050)
+}}
+
+model User {{
+{id}
+  @unique
+  email: String
+  name: String?
+}}
+"#, provider = provider, id = id)
+}
+
+pub(super) fn data() -> String {
+    r#"autoseed dataset default {
+  group User {
+    record ada {
+      "email": "ada@example.com",
+      "name": "Ada",
+    }
+  }
+}
+"#.to_owned()
+}
+
+pub(super) fn dot_env(connector: NewConnector, name: &str) -> String {
+    format!("# loaded before the schema, the connector in schema.teo reads DATABASE_URL\nDATABASE_URL={}\n", url(connector, name))
+}
+
+pub(super) fn gitignore(template: NewTemplate) -> String {
+    let mut result = String::from(".env\n*.sqlite\n.teo_console_history\n");
+    if template == NewTemplate::Rust {
+        result.push_str("/target\n");
+    }
+    result
+}
+
+pub(super) fn cargo_toml(name: &str) -> String {
+    format!(r#"[package]
+name = "{name}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+teo = "{version}"
+tokio = {{ version = "1.0", features = ["full"] }}
+
+[dev-dependencies]
+actix-web = "4.5.1"
+serde_json = "1.0"
+serial_test = "3.0.0"
+"#, name = name, version = env!("CARGO_PKG_VERSION"))
+}
+
+pub(super) fn main_rs() -> String {
+    r#"use teo::prelude::{main, path, teon, transaction, App, Result};
+use teo::prelude::model::Object;
+
+#[main]
+async fn main() -> Result<()> {
+    let app = App::new()?;
+    app.program("users", Some("Print the email of every user"), |ctx: transaction::Ctx| async move {
+        let model = ctx.namespace().model_at_path(&vec!["User"]).unwrap();
+        let users: Vec<Object> = ctx.find_many(model, &teon!({}), None, path![]).await?;
+        for user in users {
+            println!("{}", user.get_value("email")?.as_str().unwrap_or_default());
+        }
+        Ok(())
+    });
+    app.run().await
+}
+"#.to_owned()
+}
+
+pub(super) fn api_test() -> String {
+    r#"use serde_json::json;
+use serial_test::serial;
+use teo::prelude::App;
+use teo::test::TestServer;
+
+async fn test_server() -> TestServer {
+    let server = TestServer::new(App::new_with_schema_path("schema.teo").unwrap()).await.unwrap();
+    server.reset().await.unwrap();
+    server
+}
+
+#[serial]
+#[actix_web::test]
+async fn create_user() {
+    let server = test_server().await;
+    let response = server.action("User", "create", json!({
+        "create": { "email": "grace@example.com", "name": "Grace" },
+    })).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.json().unwrap()["data"]["email"], "grace@example.com");
+}
+"#.to_owned()
+}
+
+/// The test of a schema only project. Responses with an error status make
+/// `query` exit with an error.
+pub(super) fn api_test_script() -> String {
+    r#"#!/bin/sh
+# Runs against the database in .env, which is purged first.
+set -e
+cargo teo migrate
+cargo teo purge --yes
+cargo teo query User.create '{"create": {"email": "grace@example.com", "name": "Grace"}}'
+cargo teo query User.findUnique '{"where": {"email": "grace@example.com"}}'
+"#.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use teo_parser::parse;
+    use super::*;
+
+    #[test]
+    fn scaffolded_schemas_parse() {
+        for connector in [NewConnector::SQLite, NewConnector::MySQL, NewConnector::PostgreSQL, NewConnector::MongoDB] {
+            let schema = schema(connector);
+            assert!(schema.contains("url env(\"DATABASE_URL\")"));
+            let unsaved_files = HashMap::from([
+                ("/scaffold/schema.teo".to_owned(), schema),
+                ("/scaffold/data.teo".to_owned(), data()),
+            ]);
+            let (_, diagnostics) = parse("/scaffold/schema.teo", None, Some(unsaved_files));
+            assert!(!diagnostics.has_errors());
+        }
+    }
+
+    #[test]
+    fn dot_env_sets_the_database_url() {
+        assert_eq!(dot_env(NewConnector::SQLite, "blog").lines().last(), Some("DATABASE_URL=sqlite:blog.sqlite"));
+    }
+}
@@ -0,0 +1,174 @@
+pub mod models;
+pub mod worker;
+
+use std::str::FromStr;
+use std::time::Duration;
+use teo_result::{Error, Result};
+use teo_runtime::namespace::Namespace;
+use crate::app::Ctx;
+
+/// Run a program on a cron schedule while the server is running.
+///
+/// Expressions have five fields, `minute hour day month weekday`, or six
+/// with leading seconds, e.g. `"*/15 * * * *"`. Weekdays are numbered as in
+/// crontab, `0` to `6` from Sunday with `7` for Sunday too, or named like
+/// `MON-FRI`. Times are UTC. With several server instances, every scheduled
+/// time is run by one of them.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    pub(crate) program: String,
+    pub(crate) cron: String,
+    pub(crate) args: Vec<String>,
+    pub(crate) max_duration: Duration,
+}
+
+impl Schedule {
+
+    pub fn new(program: impl Into<String>, cron: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            cron: cron.into(),
+            args: vec![],
+            max_duration: Duration::from_secs(60 * 60),
+        }
+    }
+
+    /// Arguments passed to a program taking a signature, as on the command
+    /// line.
+    pub fn args<T>(mut self, args: impl IntoIterator<Item=T>) -> Self where T: Into<String> {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// A run is considered stuck after this, and no longer keeps the next
+    /// runs from starting. Defaults to an hour.
+    pub fn max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = max_duration;
+        self
+    }
+
+    pub fn program(&self) -> &str {
+        self.program.as_str()
+    }
+
+    pub(crate) fn parsed(&self) -> Result<cron::Schedule> {
+        let invalid = |message: String| Error::new(format!("invalid schedule `{}` of program `{}`: {}", self.cron, self.program, message));
+        // the cron crate expects seconds first
+        let mut fields: Vec<String> = self.cron.split_whitespace().map(|f| f.to_owned()).collect();
+        if fields.len() == 5 {
+            fields.insert(0, "0".to_owned());
+        }
+        if let Some(weekday) = fields.get_mut(5) {
+            *weekday = crate_weekdays(weekday).map_err(invalid)?;
+        }
+        cron::Schedule::from_str(&fields.join(" ")).map_err(|e| invalid(e.to_string()))
+    }
+}
+
+/// Translate a weekday field from crontab numbering, `0` or `7` for Sunday,
+/// to the cron crate's, `1` for Sunday to `7` for Saturday. Names are the
+/// same in both. Steps over `*` select the same days in both.
+fn crate_weekdays(field: &str) -> std::result::Result<String, String> {
+    let weekday = |day: &str| -> std::result::Result<Option<u32>, String> {
+        match day.parse::<u32>() {
+            Ok(day @ 0..=6) => Ok(Some(day + 1)),
+            Ok(7) => Ok(Some(1)),
+            Ok(day) => Err(format!("weekday {} is out of range 0-7", day)),
+            Err(_) => Ok(None),
+        }
+    };
+    let mut items = vec![];
+    for item in field.split(',') {
+        let (base, step) = match item.split_once('/') {
+            Some((base, step)) => (base, Some(step)),
+            None => (item, None),
+        };
+        let step = step.map(|s| format!("/{}", s)).unwrap_or_default();
+        match base.split_once('-') {
+            Some((start, end)) => match (weekday(start)?, weekday(end)?) {
+                // 0-7 is every day
+                (Some(1), Some(_)) if end == "7" => items.push(format!("1-7{}", step)),
+                // a range up to Sunday wraps around, split it in two
+                (Some(start), Some(_)) if end == "7" => {
+                    if !step.is_empty() {
+                        Err(format!("range {} with a step can't end on 7, end it on 6", base))?
+                    }
+                    items.push(format!("{}-7", start));
+                    items.push("1".to_owned());
+                }
+                (Some(start), Some(end)) => items.push(format!("{}-{}{}", start, end, step)),
+                (None, None) => items.push(item.to_owned()),
+                _ => Err(format!("range {} mixes numbers and names", base))?,
+            },
+            None => match weekday(base)? {
+                Some(day) => items.push(format!("{}{}", day, step)),
+                None => items.push(item.to_owned()),
+            },
+        }
+    }
+    Ok(items.join(","))
+}
+
+/// Make sure the std model recording runs is loaded, and every schedule
+/// refers to a program it can call.
+pub(crate) fn check(namespace: &Namespace) -> Result<()> {
+    if namespace.model_at_path(&vec!["std", "ProgramRun"]).is_none() {
+        Err(Error::new("schedules require the `std.ProgramRun` model"))?
+    }
+    for schedule in Ctx::schedules() {
+        schedule.parsed()?;
+        let Some(program) = Ctx::get().programs.get(&schedule.program) else {
+            Err(Error::new(format!("scheduled program `{}` is not defined", schedule.program)))?
+        };
+        program.signature.try_parse(&schedule.program, &schedule.args)
+            .map_err(|e| Error::new(format!("invalid arguments of scheduled program `{}`: {}", schedule.program, e)))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Datelike, TimeZone, Utc, Weekday};
+    use super::*;
+
+    fn weekdays(cron: &str) -> Vec<Weekday> {
+        // 2024-01-01 is a Monday
+        let start: DateTime<Utc> = Utc.with_ymd_and_hms(2023, 12, 31, 12, 0, 0).unwrap();
+        let mut days: Vec<Weekday> = Schedule::new("report", cron).parsed().unwrap().after(&start).take(7).map(|t| t.weekday()).collect();
+        days.sort_by_key(|d| d.num_days_from_sunday());
+        days.dedup();
+        days
+    }
+
+    #[test]
+    fn numbers_weekdays_as_crontab() {
+        assert_eq!(weekdays("0 0 * * 0"), vec![Weekday::Sun]);
+        assert_eq!(weekdays("0 0 * * 7"), vec![Weekday::Sun]);
+        assert_eq!(weekdays("0 0 * * 1-5"), vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri]);
+        assert_eq!(weekdays("0 0 * * 5-7"), vec![Weekday::Sun, Weekday::Fri, Weekday::Sat]);
+        assert_eq!(weekdays("0 0 * * 0,6"), vec![Weekday::Sun, Weekday::Sat]);
+        assert_eq!(weekdays("0 0 * * 0-7").len(), 7);
+        assert_eq!(weekdays("0 0 * * */2"), vec![Weekday::Sun, Weekday::Tue, Weekday::Thu, Weekday::Sat]);
+        assert_eq!(weekdays("0 0 * * MON-FRI"), vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri]);
+        assert_eq!(weekdays("30 0 0 * * 1"), vec![Weekday::Mon]);
+    }
+
+    #[test]
+    fn parses_five_and_six_fields() {
+        let start: DateTime<Utc> = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let next = Schedule::new("report", "*/15 * * * *").parsed().unwrap().after(&start).next().unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2024, 1, 1, 0, 15, 0).unwrap());
+        let next = Schedule::new("report", "30 * * * * *").parsed().unwrap().after(&start).next().unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 30).unwrap());
+    }
+
+    #[test]
+    fn refuses_invalid_expressions() {
+        assert!(Schedule::new("report", "0 0 * * 8").parsed().is_err());
+        assert!(Schedule::new("report", "0 0 * * 5-7/2").parsed().is_err());
+        assert!(Schedule::new("report", "0 0 * * MON-5").parsed().is_err());
+        assert!(Schedule::new("report", "every day").parsed().is_err());
+        let error = Schedule::new("report", "61 * * * *").parsed().unwrap_err();
+        assert!(format!("{}", error).contains("invalid schedule `61 * * * *` of program `report`"));
+    }
+}
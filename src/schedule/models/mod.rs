@@ -0,0 +1 @@
+pub mod program_run;
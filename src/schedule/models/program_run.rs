@@ -0,0 +1,119 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::borrow::Borrow;
+use chrono::{DateTime, Utc};
+use key_path::path;
+use teo_runtime::connection::transaction;
+use teo_runtime::model;
+use crate::prelude::{Value, Result};
+
+/// Program run
+pub struct ProgramRun {
+    pub(super) inner: model::Object,
+}
+
+impl ProgramRun {
+
+    /// Find many program runs.
+    pub async fn find_many(query: impl Borrow<Value>, ctx: transaction::Ctx) -> Result<Vec<ProgramRun>> {
+        let model = ctx.namespace().model_at_path(&vec!["std", "ProgramRun"]).unwrap();
+        Ok(ctx.find_many(model, query.borrow(), None, path![]).await?)
+    }
+
+    /// Find a unique program run.
+    pub async fn find_unique(query: impl Borrow<Value>, ctx: transaction::Ctx) -> Result<Option<ProgramRun>> {
+        let model = ctx.namespace().model_at_path(&vec!["std", "ProgramRun"]).unwrap();
+        Ok(ctx.find_unique(model, query.borrow(), None, path![]).await?)
+    }
+
+    /// Create a new program run.
+    pub async fn new(values: impl Borrow<Value>, ctx: transaction::Ctx) -> Result<Self> {
+        let model = ctx.namespace().model_at_path(&vec!["std", "ProgramRun"]).unwrap();
+        Ok(ctx.create_object(model, values.borrow(), None).await?.into())
+    }
+
+    /// Save this program run.
+    pub async fn save(&self) -> Result<()> {
+        self.inner.save().await
+    }
+
+    /// Delete this program run.
+    pub async fn delete(&self) -> Result<()> {
+        self.inner.delete().await
+    }
+
+    /// Id
+    pub fn id(&self) -> String {
+        self.inner.get("id").unwrap()
+    }
+
+    /// Program
+    pub fn program(&self) -> String {
+        self.inner.get("program").unwrap()
+    }
+
+    /// Slot
+    pub fn slot(&self) -> DateTime<Utc> {
+        self.inner.get("slot").unwrap()
+    }
+
+    /// Status
+    pub fn status(&self) -> String {
+        self.inner.get("status").unwrap()
+    }
+
+    pub fn set_status(&self, new_value: impl Into<String>) {
+        self.inner.set("status", new_value.into()).unwrap();
+    }
+
+    /// Instance
+    pub fn instance(&self) -> String {
+        self.inner.get("instance").unwrap()
+    }
+
+    /// Started at
+    pub fn started_at(&self) -> DateTime<Utc> {
+        self.inner.get("startedAt").unwrap()
+    }
+
+    /// Finished at
+    pub fn finished_at(&self) -> Option<DateTime<Utc>> {
+        self.inner.get("finishedAt").unwrap()
+    }
+
+    pub fn set_finished_at(&self, new_value: Option<DateTime<Utc>>) {
+        self.inner.set("finishedAt", new_value).unwrap();
+    }
+
+    /// Error
+    pub fn error(&self) -> Option<String> {
+        self.inner.get("error").unwrap()
+    }
+
+    pub fn set_error(&self, new_value: Option<String>) {
+        self.inner.set("error", new_value).unwrap();
+    }
+}
+
+impl Into<model::Object> for ProgramRun {
+    fn into(self) -> model::Object {
+        self.inner.clone()
+    }
+}
+
+impl From<model::Object> for ProgramRun {
+    fn from(value: model::Object) -> Self {
+        Self { inner: value }
+    }
+}
+
+impl Debug for ProgramRun {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.inner, f)
+    }
+}
+
+impl Display for ProgramRun {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.inner, f)
+    }
+}
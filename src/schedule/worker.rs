@@ -0,0 +1,171 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use teo_result::{Error, Result};
+use teo_runtime::connection::transaction;
+use teo_runtime::teon;
+use crate::app::Ctx;
+use crate::message::{error_message, event, info_message};
+use crate::schedule::Schedule;
+use crate::server::watch::RELOAD_LOCK;
+use crate::schedule::models::program_run::ProgramRun;
+
+const TICK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Run scheduled programs in the background until the process exits.
+///
+/// Every due time is claimed by creating a `std.ProgramRun` whose id is
+/// the program and the time, so that only one server instance runs it.
+/// The same record keeps the status and the error of the run.
+pub(crate) fn spawn(silent: bool) {
+    if !silent {
+        info_message(format!("scheduler started for {} schedule(s)", Ctx::schedules().len()));
+    }
+    // expressions are checked before serving, see `schedule::check`
+    let schedules: Vec<(&'static Schedule, cron::Schedule)> = Ctx::schedules().iter()
+        .filter_map(|schedule| schedule.parsed().ok().map(|parsed| (schedule, parsed)))
+        .collect();
+    tokio::spawn(async move {
+        // programs running in this process, a run is skipped while the last one is running
+        let running: Arc<Mutex<HashSet<String>>> = Arc::new(Mutex::new(HashSet::new()));
+        let mut last_tick = Utc::now();
+        loop {
+            tokio::time::sleep(TICK_INTERVAL).await;
+            let now = Utc::now();
+            for (schedule, parsed) in &schedules {
+                let schedule = *schedule;
+                let Some(slot) = due_slot(parsed, last_tick, now) else {
+                    continue
+                };
+                let running = running.clone();
+                tokio::spawn(async move {
+                    if let Err(error) = run(schedule, slot, running, silent).await {
+                        error_message(format!("scheduler: {}: {}", schedule.program, error));
+                    }
+                });
+            }
+            last_tick = now;
+        }
+    });
+}
+
+/// The latest time of `schedule` after `last_tick` and until `now`. Missed
+/// times, e.g. while the process was suspended, are collapsed into it.
+fn due_slot(schedule: &cron::Schedule, last_tick: DateTime<Utc>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    schedule.after(&last_tick).take_while(|t| *t <= now).last()
+}
+
+async fn run(schedule: &'static Schedule, slot: DateTime<Utc>, running: Arc<Mutex<HashSet<String>>>, silent: bool) -> Result<()> {
+    // a reload waits for running programs
+    let _guard = RELOAD_LOCK.read().await;
+    let program = Ctx::get().programs.get(&schedule.program).unwrap();
+    let args = program.signature.try_parse(&schedule.program, &schedule.args)?;
+    let ctx = transaction::Ctx::new(Ctx::conn_ctx().clone());
+    let overlapping = running.lock().unwrap().contains(&schedule.program) || running_elsewhere(schedule, ctx.clone()).await?;
+    let Some(record) = claim(schedule, slot, if overlapping { "skipped" } else { "running" }, ctx.clone()).await? else {
+        return Ok(());
+    };
+    if overlapping {
+        record.set_error(Some("the last run is still running".to_owned()));
+        record.set_finished_at(Some(Utc::now()));
+        return record.save().await;
+    }
+    running.lock().unwrap().insert(schedule.program.clone());
+    if !silent {
+        info_message(format!("running scheduled program {}", schedule.program));
+    }
+    let func = program.func.clone();
+    // a panicking program fails its run instead of the scheduler
+    let result = tokio::spawn(async move {
+        func.call(transaction::Ctx::new(Ctx::conn_ctx().clone()), args).await
+    }).await;
+    running.lock().unwrap().remove(&schedule.program);
+    let error = match result {
+        Ok(Ok(())) => None,
+        Ok(Err(error)) => Some(format!("{}", error)),
+        Err(_) => Some("the program panicked".to_owned()),
+    };
+    let duration = Utc::now() - record.started_at();
+    event("schedule", json!({
+        "program": schedule.program,
+        "slot": slot.to_rfc3339(),
+        "result": if error.is_none() { "success" } else { "failure" },
+        "ms": duration.num_milliseconds(),
+        "error": error,
+    }));
+    if let Some(error) = &error {
+        error_message(format!("scheduled program {} failed: {}", schedule.program, error));
+    }
+    record.set_status(if error.is_none() { "succeeded" } else { "failed" });
+    record.set_error(error);
+    record.set_finished_at(Some(Utc::now()));
+    record.save().await
+}
+
+/// Whether another instance is running the program and the run isn't
+/// stuck.
+async fn running_elsewhere(schedule: &Schedule, ctx: transaction::Ctx) -> Result<bool> {
+    let since = Utc::now() - chrono::Duration::from_std(schedule.max_duration).unwrap_or(chrono::Duration::hours(1));
+    let runs = ProgramRun::find_many(teon!({
+        "where": {
+            "program": schedule.program.as_str(),
+            "status": "running",
+            "startedAt": { "gt": since },
+        },
+        "take": 1,
+    }), ctx).await?;
+    Ok(!runs.is_empty())
+}
+
+/// Create the run of `slot`, or `None` if another instance created it first.
+async fn claim(schedule: &Schedule, slot: DateTime<Utc>, status: &str, ctx: transaction::Ctx) -> Result<Option<ProgramRun>> {
+    let id = format!("{}@{}", schedule.program, slot.to_rfc3339());
+    let record = ProgramRun::new(teon!({
+        "id": id.as_str(),
+        "program": schedule.program.as_str(),
+        "slot": slot,
+        "status": status,
+        "instance": instance(),
+        "startedAt": Utc::now(),
+    }), ctx.clone()).await?;
+    match record.save().await {
+        Ok(()) => Ok(Some(record)),
+        // the id is unique, a record of the slot means another instance claimed it
+        Err(error) => match ProgramRun::find_unique(teon!({ "where": { "id": id.as_str() } }), ctx).await? {
+            Some(_) => Ok(None),
+            None => Err(Error::new(format!("cannot record run: {}", error))),
+        },
+    }
+}
+
+/// Identifies the server instance in run history.
+fn instance() -> String {
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_owned());
+    format!("{}:{}", host, std::process::id())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use super::*;
+
+    fn at(hour: u32, minute: u32, second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, hour, minute, second).unwrap()
+    }
+
+    #[test]
+    fn finds_the_slot_of_a_tick() {
+        let schedule = Schedule::new("report", "*/15 * * * *").parsed().unwrap();
+        assert_eq!(due_slot(&schedule, at(0, 14, 59), at(0, 15, 0)), Some(at(0, 15, 0)));
+        assert_eq!(due_slot(&schedule, at(0, 15, 0), at(0, 15, 1)), None);
+        assert_eq!(due_slot(&schedule, at(0, 15, 1), at(0, 29, 59)), None);
+    }
+
+    #[test]
+    fn collapses_missed_slots_into_the_latest() {
+        let schedule = Schedule::new("report", "*/15 * * * *").parsed().unwrap();
+        assert_eq!(due_slot(&schedule, at(0, 0, 30), at(1, 7, 0)), Some(at(1, 0, 0)));
+    }
+}
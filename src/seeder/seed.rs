@@ -1,3 +1,4 @@
+use std::time::Instant;
 use bson::oid::ObjectId;
 use indexmap::{IndexMap, indexmap};
 use itertools::Itertools;
@@ -16,22 +17,21 @@ use teo_runtime::model::field::is_optional::IsOptional;
 use teo_runtime::model::{Model, Object, Relation};
 use teo_runtime::model::field::typed::Typed;
 use teo_runtime::traits::named::Named;
+use crate::message::event;
 
-pub(crate) async fn seed(action: SeedCommandAction, datasets: Vec<DataSet>, ctx: transaction::Ctx, exit: bool) -> Result<()> {
+pub(crate) async fn seed(action: SeedCommandAction, datasets: Vec<DataSet>, ctx: transaction::Ctx) -> Result<()> {
     // seed for user
     for dataset in &datasets {
-        match action {
-            SeedCommandAction::Seed => seed_dataset(dataset, ctx.clone()).await,
-            SeedCommandAction::Reseed => reseed_dataset(dataset, ctx.clone()).await,
-            SeedCommandAction::Unseed => unseed_dataset(dataset, ctx.clone()).await,
-        }
+        let start = Instant::now();
+        let action_name = match action {
+            SeedCommandAction::Seed => { seed_dataset(dataset, ctx.clone()).await; "seed" }
+            SeedCommandAction::Reseed => { reseed_dataset(dataset, ctx.clone()).await; "reseed" }
+            SeedCommandAction::Unseed => { unseed_dataset(dataset, ctx.clone()).await; "unseed" }
+        };
+        event("seed", json!({ "action": action_name, "dataset": dataset.name.join("."), "ms": start.elapsed().as_millis() as u64 }));
     }
     remove_user_deleted_dataset_records_and_relations(&datasets, ctx).await;
-    if exit {
-        std::process::exit(0);
-    } else {
-        Ok(())
-    }
+    Ok(())
 }
 
 pub(crate) async fn seed_dataset(dataset: &DataSet, ctx: transaction::Ctx) {
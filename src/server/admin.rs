@@ -0,0 +1,202 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use educe::Educe;
+use teo_result::{Error, Result};
+use teo_runtime::config::client::ClientHost;
+use teo_runtime::namespace::Namespace;
+use teo_runtime::model::Object;
+use teo_runtime::response::Response;
+use crate::server::static_files::{normalize_path, StaticFiles};
+
+pub trait AdminGuard: Send + Sync {
+    fn call(&self, identity: &Object) -> bool;
+}
+
+impl<F> AdminGuard for F where F: Fn(&Object) -> bool + Send + Sync {
+    fn call(&self, identity: &Object) -> bool {
+        self(identity)
+    }
+}
+
+/// Serve the admin dashboard built from `cargo teo generate admin`.
+///
+/// The prebuilt assets are read from `dist`, which defaults to the `dist`
+/// directory under the admin `dest`. Build the dashboard with its base set
+/// to `path`. When the admin `host` is injected, e.g.
+/// `host .inject("globalThis.ADMIN_API")`, the served page sets it to the
+/// API of the same server.
+///
+/// A dashboard is only served to the identities let in by a
+/// [`guard`](Self::guard) or a [`role`](Self::role), or to everyone when it's
+/// explicitly [`unguarded`](Self::unguarded). The identity of a request is
+/// resolved by the middlewares of the main namespace, e.g. from a token.
+#[derive(Educe, Clone)]
+#[educe(Debug)]
+pub struct AdminDashboard {
+    path: String,
+    dist: Option<PathBuf>,
+    #[educe(Debug(ignore))]
+    guard: Option<Arc<dyn AdminGuard>>,
+    unguarded: bool,
+    files: Option<StaticFiles>,
+    #[educe(Debug(ignore))]
+    index: Option<String>,
+}
+
+impl AdminDashboard {
+
+    pub fn new() -> Self {
+        Self {
+            path: "/_admin".to_owned(),
+            dist: None,
+            guard: None,
+            unguarded: false,
+            files: None,
+            index: None,
+        }
+    }
+
+    /// Where the dashboard is mounted, `/_admin` by default.
+    pub fn path(mut self, path: impl AsRef<str>) -> Self {
+        self.path = format!("/{}", path.as_ref().trim_matches('/'));
+        self
+    }
+
+    /// The directory of the built dashboard.
+    pub fn dist(mut self, dist: impl Into<PathBuf>) -> Self {
+        self.dist = Some(dist.into());
+        self
+    }
+
+    /// Only serve the dashboard to identities passing `f`. Requests without
+    /// an identity receive 401, identities failing `f` receive 403.
+    pub fn guard<F>(mut self, f: F) -> Self where F: Fn(&Object) -> bool + Send + Sync + 'static {
+        self.guard = Some(Arc::new(f));
+        self
+    }
+
+    /// Only serve the dashboard to identities whose `field` is one of
+    /// `roles`, e.g. `.role("role", ["admin"])`.
+    pub fn role<I, S>(self, field: impl Into<String>, roles: I) -> Self where I: IntoIterator<Item = S>, S: Into<String> {
+        let field = field.into();
+        let roles: Vec<String> = roles.into_iter().map(Into::into).collect();
+        self.guard(move |identity: &Object| {
+            identity.get::<String>(&field).is_ok_and(|role| roles.contains(&role))
+        })
+    }
+
+    /// Serve the dashboard to every request, e.g. on a private network.
+    pub fn unguarded(mut self) -> Self {
+        self.unguarded = true;
+        self
+    }
+
+    pub fn mount_path(&self) -> &str {
+        self.path.as_str()
+    }
+
+    /// Whether identities are checked at all, an unguarded dashboard doesn't
+    /// resolve them.
+    pub(crate) fn is_guarded(&self) -> bool {
+        self.guard.is_some()
+    }
+
+    pub(crate) fn allows(&self, identity: &Object) -> bool {
+        self.guard.as_ref().map_or(true, |guard| guard.call(identity))
+    }
+
+    /// Serve `path` relative to the mount path. Paths of the app, which fall
+    /// back to `index.html`, receive the page with the API host injected.
+    pub(crate) fn serve(&self, path: &str, accept_encoding: Option<&str>) -> Result<Response> {
+        let Some(files) = self.files.as_ref() else {
+            Err(Error::internal_server_error_message("admin dashboard is not resolved"))?
+        };
+        if let Some(index) = &self.index {
+            let is_asset = normalize_path(path).is_some_and(|p| p.as_os_str() != "index.html" && files.base().join(&p).is_file());
+            if !is_asset {
+                let response = Response::string(index.clone(), "text/html; charset=utf-8");
+                response.headers().set("cache-control", "no-cache");
+                return Ok(response);
+            }
+        }
+        files.serve(path, accept_encoding)
+    }
+
+    /// Locate the built assets before serving.
+    pub(crate) fn resolve(&mut self, namespace: &Namespace) -> Result<()> {
+        if self.guard.is_none() && !self.unguarded {
+            Err(Error::new("the admin dashboard requires a guard, set `AdminDashboard::guard` or `AdminDashboard::role` in the app, or `AdminDashboard::unguarded` to serve it to everyone"))?
+        }
+        let dist = match &self.dist {
+            Some(dist) => dist.clone(),
+            None => match &namespace.admin {
+                Some(admin) => PathBuf::from(&admin.dest).join("dist"),
+                None => Err(Error::new("admin dashboard requires an `admin` config or a dist directory"))?,
+            },
+        };
+        if !dist.join("index.html").is_file() {
+            Err(Error::new(format!("admin dashboard is not built, cannot find {}", dist.join("index.html").display())))?
+        }
+        self.index = match namespace.admin.as_ref().map(|admin| &admin.host) {
+            Some(ClientHost::Inject(expression)) => Some(inject_host(&dist.join("index.html"), expression, namespace)?),
+            _ => None,
+        };
+        self.files = Some(StaticFiles::new(dist)
+            .fallback("index.html")
+            .cache_control(r"^assets/", "public, max-age=31536000, immutable")?);
+        Ok(())
+    }
+}
+
+/// The page of the dashboard, setting the injected host to the API of this
+/// server before the app's scripts run.
+fn inject_host(index: &Path, expression: &str, namespace: &Namespace) -> Result<String> {
+    let html = fs::read_to_string(index).map_err(|e| Error::new(format!("cannot read {}: {}", index.display(), e)))?;
+    let path_prefix = namespace.server.as_ref().and_then(|s| s.path_prefix.as_ref())
+        .map(|p| format!("/{}", p.trim_matches('/')))
+        .unwrap_or_default();
+    let script = format!("<script>{} = location.origin + {:?};</script>", expression, path_prefix);
+    Ok(match html.find("<head>") {
+        Some(position) => format!("{}{}{}", &html[..position + 6], script, &html[position + 6..]),
+        None => format!("{}{}", script, html),
+    })
+}
+
+impl Default for AdminDashboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_to_serve_without_guard() {
+        let error = AdminDashboard::new().resolve(&Namespace::main()).unwrap_err();
+        assert!(format!("{}", error).contains("requires a guard"));
+        let error = AdminDashboard::new().unguarded().resolve(&Namespace::main()).unwrap_err();
+        assert!(format!("{}", error).contains("requires an `admin` config"));
+        let error = AdminDashboard::new().role("role", ["admin"]).resolve(&Namespace::main()).unwrap_err();
+        assert!(format!("{}", error).contains("requires an `admin` config"));
+    }
+
+    #[test]
+    fn unguarded_skips_identities() {
+        assert!(!AdminDashboard::new().unguarded().is_guarded());
+        assert!(AdminDashboard::new().role("role", ["admin"]).is_guarded());
+    }
+
+    #[test]
+    fn injects_host_into_head() {
+        let dir = std::env::temp_dir().join(format!("teo-admin-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let index = dir.join("index.html");
+        fs::write(&index, "<html><head><title>Admin</title></head></html>").unwrap();
+        let html = inject_host(&index, "globalThis.ADMIN_API", &Namespace::main()).unwrap();
+        assert_eq!(html, "<html><head><script>globalThis.ADMIN_API = location.origin + \"\";</script><title>Admin</title></head></html>");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
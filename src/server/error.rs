@@ -26,12 +26,15 @@ impl From<Error> for WrapError {
 impl ResponseError for WrapError {
 
     fn status_code(&self) -> StatusCode {
-        StatusCode::from_u16(self.0.code).unwrap()
+        StatusCode::from_u16(self.0.code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
     }
 
     fn error_response(&self) -> HttpResponse<BoxBody> {
         let value: Value = (&self.0).into();
-        let json_value: serde_json::Value = value.try_into().unwrap();
+        let json_value: serde_json::Value = value.try_into().unwrap_or_else(|_| json!({
+            "type": "InternalServerError",
+            "message": self.0.to_string(),
+        }));
         HttpResponse::Ok().status(self.status_code()).json(json!({
             "error": json_value
         }))
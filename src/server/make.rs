@@ -1,10 +1,12 @@
-use std::sync::Arc;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 use actix_web::dev::Service;
 use futures_util::FutureExt;
 use colored::Colorize;
 use futures_util::future;
-use serde_json::{Value as JsonValue};
+use indexmap::IndexMap;
+use serde_json::{json, Value as JsonValue};
 use teo_parser::diagnostics::diagnostics::Diagnostics;
 use teo_result::{Error, Result};
 use teo_runtime::config::server::Server;
@@ -22,12 +24,12 @@ use teo_runtime::handler::handler::Method;
 use teo_runtime::{connection, request};
 use teo_runtime::connection::transaction;
 use teo_runtime::handler::default::{create, find_first, find_many, find_unique, update, upsert, copy, create_many, update_many, copy_many, delete_many, count, aggregate, group_by, delete};
-use teo_runtime::model::Model;
+use teo_runtime::model::{Model, Object};
 use teo_runtime::response::Response;
 use teo_runtime::Value;
 use crate::cli::entrance::Entrance;
 use crate::cli::runtime_version::RuntimeVersion;
-use crate::purge;
+use crate::{purge, stdlib};
 use crate::seeder::seed::seed;
 use crate::server::parse::{parse_form_body, parse_json_body};
 use teo_runtime::handler::input::{validate_and_transform_json_input_for_handler, validate_and_transform_json_input_for_builtin_action};
@@ -36,14 +38,21 @@ use teo_runtime::schema::load::load_data_sets::load_data_sets;
 use crate::app::Ctx;
 use crate::app::database::connect_databases;
 use crate::cli::command::SeedCommandAction;
-use crate::message::{info_message, request_message, unhandled_request_message};
+use crate::message::{event, info_message, json_output, request_message, unhandled_request_message};
 use crate::server::error::WrapError;
 use crate::server::request::RequestImpl;
 use crate::server::responder::IntoHttpResponse;
+use crate::server::static_files::StaticFiles;
+use crate::idempotency::{self, IdempotencyBegin};
+use crate::server::options::ServerOptions;
+use crate::server::watch::RELOAD_LOCK;
+use crate::server::admin::AdminDashboard;
+use crate::server::panic::{ensure_request_id, in_request, install_panic_hook, InRequest, panic_response, request_id_header_value};
 
-fn make_server_app(
+pub(crate) fn make_server_app(
     main_namespace: &'static Namespace,
-    conf: &'static Server,
+    static_files: &'static Vec<(String, StaticFiles)>,
+    admin_dashboard: Option<&'static AdminDashboard>,
 ) -> App<impl ServiceFactory<
     ServiceRequest,
     Response = ServiceResponse<impl MessageBody>,
@@ -51,7 +60,29 @@ fn make_server_app(
     InitError = (),
     Error = actix_web::Error,
 > + 'static> {
-    let app = App::new()
+    let mut app = App::new()
+        .wrap_fn(|mut req, srv| {
+            let request_id = ensure_request_id(&mut req);
+            let http_request = req.request().clone();
+            // services may panic before returning their future
+            let fut = in_request(&request_id, || panic::catch_unwind(AssertUnwindSafe(|| srv.call(req))));
+            async move {
+                let result = match fut {
+                    Ok(fut) => AssertUnwindSafe(InRequest::new(&request_id, fut)).catch_unwind().await,
+                    Err(payload) => Err(payload),
+                };
+                let mut res = match result {
+                    Ok(res) => res?.map_into_boxed_body(),
+                    Err(payload) => {
+                        let response = panic_response(&http_request, &request_id, payload);
+                        ServiceResponse::new(http_request, response)
+                    }
+                };
+                let (name, value) = request_id_header_value(&request_id);
+                res.headers_mut().insert(name, value);
+                Ok(res)
+            }
+        })
         .wrap(DefaultHeaders::new()
             .add(("Access-Control-Allow-Origin", "*"))
             .add(("Access-Control-Allow-Methods", "OPTIONS, POST, GET"))
@@ -65,7 +96,7 @@ fn make_server_app(
                 {
                     let binding = res.request().extensions();
                     let handler_found_info = binding.get::<HandlerMatch>().clone();
-                    let time_elapsed = SystemTime::now().duration_since(start).unwrap();
+                    let time_elapsed = SystemTime::now().duration_since(start).unwrap_or_default();
                     let path = res.request().path();
                     let method = res.request().method().as_str();
                     if let Some(handler_found_info) = handler_found_info {
@@ -76,10 +107,46 @@ fn make_server_app(
                 }
                 Ok(res)
             }
-        })
+        });
+    if let Some(admin_dashboard) = admin_dashboard {
+        app = app.service(web::scope(admin_dashboard.mount_path()).default_service(web::route().to(move |http_request: HttpRequest| async move {
+            if http_request.method() != HttpMethod::GET && http_request.method() != HttpMethod::HEAD {
+                return Ok::<HttpResponse, WrapError>(HttpResponse::MethodNotAllowed().finish());
+            }
+            if admin_dashboard.is_guarded() {
+                let Some(identity) = request_identity(&http_request, main_namespace).await? else {
+                    let mut error = Error::new("unauthorized");
+                    error.code = 401;
+                    Err(error)?
+                };
+                if !admin_dashboard.allows(&identity) {
+                    let mut error = Error::new("forbidden");
+                    error.code = 403;
+                    Err(error)?
+                }
+            }
+            let path = http_request.path().strip_prefix(admin_dashboard.mount_path()).unwrap_or("");
+            let accept_encoding = http_request.headers().get("accept-encoding").and_then(|v| v.to_str().ok());
+            Ok::<HttpResponse, WrapError>(admin_dashboard.serve(path, accept_encoding)?.into_http_response(http_request.clone()))
+        })));
+    }
+    for (prefix, files) in static_files {
+        app = app.service(web::scope(prefix.as_str()).default_service(web::route().to(move |http_request: HttpRequest| async move {
+            if http_request.method() != HttpMethod::GET && http_request.method() != HttpMethod::HEAD {
+                return Ok::<HttpResponse, WrapError>(HttpResponse::MethodNotAllowed().finish());
+            }
+            let path = http_request.path().strip_prefix(prefix.as_str()).unwrap_or("");
+            let accept_encoding = http_request.headers().get("accept-encoding").and_then(|v| v.to_str().ok());
+            Ok::<HttpResponse, WrapError>(files.serve(path, accept_encoding)?.into_http_response(http_request.clone()))
+        })));
+    }
+    let app = app
         .default_service(web::route().to(move |http_request: HttpRequest, payload: web::Payload| async move {
+            // the namespace is not reloaded while handling this request
+            let _reload_guard = RELOAD_LOCK.read().await;
             // validate path
-            let path = main_namespace.handler_map.remove_path_prefix(http_request.path(), conf.path_prefix.as_ref().map(|s| s.as_str()));
+            let path_prefix = main_namespace.server.as_ref().and_then(|s| s.path_prefix.as_ref());
+            let path = main_namespace.handler_map.remove_path_prefix(http_request.path(), path_prefix.map(|s| s.as_str()));
             let method = method_from(http_request.method())?;
             let match_result = if let Some(m_result) = main_namespace.handler_map.r#match(method, path) {
                 m_result
@@ -100,56 +167,7 @@ fn make_server_app(
             }
 
             // Normal handling
-            let mut group = false;
-            let dest_namespace = if let Some(d) = main_namespace.namespace_at_path(&match_result.path()) {
-                d
-            } else if match_result.path().len() > 0 {
-                if let Some(d) = main_namespace.namespace_at_path(&match_result.path_without_last()) {
-                    group = true;
-                    d
-                } else {
-                    Err(Error::not_found())?
-                }
-            } else {
-                Err(Error::not_found())?
-            };
-            let handler_resolved = if group {
-                if let Some(model) = dest_namespace.models.get(match_result.group_name()) {
-                    if let Some(group) = dest_namespace.model_handler_groups.get(match_result.group_name()) {
-                        if let Some(handler) = group.handlers.get(match_result.handler_name()) {
-                            (dest_namespace, HandlerResolved::Custom(handler))
-                        } else {
-                            if let Some(action) = builtin_action_handler_from_name(match_result.handler_name()) {
-                                (dest_namespace, HandlerResolved::Builtin(model, action))
-                            } else {
-                                Err(Error::not_found())?
-                            }
-                        }
-                    } else {
-                        if let Some(action) = builtin_action_handler_from_name(match_result.handler_name()) {
-                            (dest_namespace, HandlerResolved::Builtin(model, action))
-                        } else {
-                            Err(Error::not_found())?
-                        }
-                    }
-                } else if let Some(group) = dest_namespace.handler_groups.get(match_result.group_name()) {
-                    if let Some(handler) = group.handlers.get(match_result.handler_name()) {
-                        (dest_namespace, HandlerResolved::Custom(handler))
-                    } else {
-                        Err(Error::not_found())?
-                    }
-                } else {
-                    Err(Error::not_found())?
-                }
-            } else {
-                if let Some(handler) = dest_namespace.handlers.get(match_result.handler_name()) {
-                    (dest_namespace, HandlerResolved::Custom(handler))
-                } else {
-                    Err(Error::not_found())?
-                }
-            };
-            let dest_namespace = handler_resolved.0;
-            let handler_resolved = handler_resolved.1;
+            let (dest_namespace, handler_resolved) = resolve_handler(main_namespace, &match_result)?;
             if method == Method::Options {
                 // special handle for options
                 let conn_ctx = connection::Ctx::from_namespace(main_namespace);
@@ -181,79 +199,101 @@ fn make_server_app(
                 },
                 HandlerInputFormat::Form => parse_form_body(http_request.clone(), payload).await?,
             };
-            return match handler_resolved {
-                HandlerResolved::Builtin(model, action) => {
-                    let body = validate_and_transform_json_input_for_builtin_action(model, action, &json_body, main_namespace)?;
-                    let conn_ctx = connection::Ctx::from_namespace(main_namespace);
-                    let transaction_ctx = transaction::Ctx::new(conn_ctx);
-                    let ctx = request::Ctx::new(
-                        request::Request::new(Arc::new(RequestImpl::new(http_request.clone()))),
-                        Arc::new(body),
-                        transaction_ctx,
-                        match_result.clone(),
-                    );
-                    match match_result.handler_name() {
-                        "findMany" => Ok::<HttpResponse, WrapError>(dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
-                            find_many(&ctx).await
-                        }).await?.into_http_response(http_request.clone())),
-                        "findFirst" => Ok::<HttpResponse, WrapError>(dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
-                            find_first(&ctx).await
-                        }).await?.into_http_response(http_request.clone())),
-                        "findUnique" => Ok::<HttpResponse, WrapError>(dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
-                            find_unique(&ctx).await
-                        }).await?.into_http_response(http_request.clone())),
-                        "create" => Ok::<HttpResponse, WrapError>(dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
-                            create(&ctx).await
-                        }).await?.into_http_response(http_request.clone())),
-                        "delete" => Ok::<HttpResponse, WrapError>(dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
-                            delete(&ctx).await
-                        }).await?.into_http_response(http_request.clone())),
-                        "update" => Ok::<HttpResponse, WrapError>(dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
-                            update(&ctx).await
-                        }).await?.into_http_response(http_request.clone())),
-                        "upsert" => Ok::<HttpResponse, WrapError>(dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
-                            upsert(&ctx).await
-                        }).await?.into_http_response(http_request.clone())),
-                        "copy" => Ok::<HttpResponse, WrapError>(dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
-                            copy(&ctx).await
-                        }).await?.into_http_response(http_request.clone())),
-                        "createMany" => Ok::<HttpResponse, WrapError>(dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
-                            create_many(&ctx).await
-                        }).await?.into_http_response(http_request.clone())),
-                        "updateMany" => Ok::<HttpResponse, WrapError>(dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
-                            update_many(&ctx).await
-                        }).await?.into_http_response(http_request.clone())),
-                        "copyMany" => Ok::<HttpResponse, WrapError>(dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
-                            copy_many(&ctx).await
-                        }).await?.into_http_response(http_request.clone())),
-                        "deleteMany" => Ok::<HttpResponse, WrapError>(dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
-                            delete_many(&ctx).await
-                        }).await?.into_http_response(http_request.clone())),
-                        "count" => Ok::<HttpResponse, WrapError>(dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
-                            count(&ctx).await
-                        }).await?.into_http_response(http_request.clone())),
-                        "aggregate" => Ok::<HttpResponse, WrapError>(dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
-                            aggregate(&ctx).await
-                        }).await?.into_http_response(http_request.clone())),
-                        "groupBy" => Ok::<HttpResponse, WrapError>(dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
-                            group_by(&ctx).await
-                        }).await?.into_http_response(http_request.clone())),
-                        _ => Err(Error::not_found())?,
+            // claim the idempotency key, or replay the stored response
+            let mut idempotency_claim = None;
+            if let Some(idempotency) = Ctx::idempotency() {
+                if idempotency.applies_to(&match_result, matches!(handler_resolved, HandlerResolved::Builtin(_, _))) {
+                    if let Some(key) = idempotency::idempotency_key(&http_request) {
+                        let transaction_ctx = transaction::Ctx::new(connection::Ctx::from_namespace(main_namespace));
+                        match idempotency::begin(idempotency, key, &http_request, &json_body, transaction_ctx).await? {
+                            IdempotencyBegin::Replay(response) => return Ok::<HttpResponse, WrapError>(response),
+                            IdempotencyBegin::Proceed(claim) => idempotency_claim = Some(claim),
+                        }
                     }
-                },
-                HandlerResolved::Custom(handler) => {
-                    let body = validate_and_transform_json_input_for_handler(handler, &json_body, main_namespace)?;
-                    let conn_ctx = connection::Ctx::from_namespace(main_namespace);
-                    let transaction_ctx = transaction::Ctx::new(conn_ctx);
-                    let ctx = request::Ctx::new(
-                        request::Request::new(Arc::new(RequestImpl::new(http_request.clone()))),
-                        Arc::new(body),
-                        transaction_ctx,
-                        match_result
-                    );
-                    Ok::<HttpResponse, WrapError>(dest_namespace.middleware_stack.call(ctx, handler.call).await?.into_http_response(http_request.clone()))
                 }
             }
+            let result: Result<Response> = async {
+                match handler_resolved {
+                    HandlerResolved::Builtin(model, action) => {
+                        let body = validate_and_transform_json_input_for_builtin_action(model, action, &json_body, main_namespace)?;
+                        let transaction_ctx = request_transaction_ctx(&http_request, main_namespace);
+                        let ctx = request::Ctx::new(
+                            request::Request::new(Arc::new(RequestImpl::new(http_request.clone()))),
+                            Arc::new(body),
+                            transaction_ctx,
+                            match_result.clone(),
+                        );
+                        impersonate(&http_request, &ctx);
+                        let response = match match_result.handler_name() {
+                            "findMany" => dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
+                                find_many(&ctx).await
+                            }).await,
+                            "findFirst" => dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
+                                find_first(&ctx).await
+                            }).await,
+                            "findUnique" => dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
+                                find_unique(&ctx).await
+                            }).await,
+                            "create" => dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
+                                create(&ctx).await
+                            }).await,
+                            "delete" => dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
+                                delete(&ctx).await
+                            }).await,
+                            "update" => dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
+                                update(&ctx).await
+                            }).await,
+                            "upsert" => dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
+                                upsert(&ctx).await
+                            }).await,
+                            "copy" => dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
+                                copy(&ctx).await
+                            }).await,
+                            "createMany" => dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
+                                create_many(&ctx).await
+                            }).await,
+                            "updateMany" => dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
+                                update_many(&ctx).await
+                            }).await,
+                            "copyMany" => dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
+                                copy_many(&ctx).await
+                            }).await,
+                            "deleteMany" => dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
+                                delete_many(&ctx).await
+                            }).await,
+                            "count" => dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
+                                count(&ctx).await
+                            }).await,
+                            "aggregate" => dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
+                                aggregate(&ctx).await
+                            }).await,
+                            "groupBy" => dest_namespace.middleware_stack.call(ctx, &|ctx: request::Ctx| async move {
+                                group_by(&ctx).await
+                            }).await,
+                            _ => Err(Error::not_found())?,
+                        };
+                        response
+                    },
+                    HandlerResolved::Custom(handler) => {
+                        let body = validate_and_transform_json_input_for_handler(handler, &json_body, main_namespace)?;
+                        let transaction_ctx = request_transaction_ctx(&http_request, main_namespace);
+                        let ctx = request::Ctx::new(
+                            request::Request::new(Arc::new(RequestImpl::new(http_request.clone()))),
+                            Arc::new(body),
+                            transaction_ctx,
+                            match_result
+                        );
+                        impersonate(&http_request, &ctx);
+                        dest_namespace.middleware_stack.call(ctx, handler.call).await
+                    }
+                }
+            }.await;
+            // a failed request drops the claim, which releases the key
+            let response = result?;
+            if let Some(claim) = idempotency_claim {
+                claim.complete(&response).await;
+            }
+            Ok::<HttpResponse, WrapError>(response.into_http_response(http_request.clone()))
         }));
     app
 }
@@ -261,26 +301,59 @@ fn make_server_app(
 pub(crate) async fn serve(
     namespace: &'static Namespace,
     conf: &'static Server,
+    options: ServerOptions,
     runtime_version: &'static RuntimeVersion,
     entrance: &'static Entrance,
     silent: bool,
 ) -> Result<()> {
     let bind = conf.bind.clone();
     let port = bind.1;
-    let server = HttpServer::new(move || {
-        make_server_app(namespace, conf)
-    })
-        .bind((bind.0, bind.1 as u16))
-        .unwrap()
-        .run();
+    install_panic_hook();
+    let static_files = Ctx::static_files();
+    let admin_dashboard = Ctx::admin_dashboard();
+    let mut server = HttpServer::new(move || {
+        make_server_app(namespace, static_files, admin_dashboard)
+    });
+    if let Some(workers) = options.workers {
+        server = server.workers(workers);
+    }
+    if let Some(backlog) = options.backlog {
+        server = server.backlog(backlog);
+    }
+    if let Some(max_connections) = options.max_connections {
+        server = server.max_connections(max_connections);
+    }
+    if let Some(keep_alive) = options.actix_keep_alive() {
+        server = server.keep_alive(keep_alive);
+    }
+    if let Some(timeout) = options.client_request_timeout {
+        server = server.client_request_timeout(timeout);
+    }
+    if let Some(timeout) = options.client_disconnect_timeout {
+        server = server.client_disconnect_timeout(timeout);
+    }
+    let address = (bind.0, bind.1 as u16);
+    let server = if options.http2() {
+        server.bind_auto_h2c(address)
+    } else {
+        server.bind(address)
+    };
+    let server = match server {
+        Ok(server) => server.run(),
+        Err(e) => Err(Error::new(format!("cannot bind to port {}: {}", port, e)))?,
+    };
     let result = future::join(server, server_start_message(port as u16, runtime_version, entrance, silent)).await;
     result.1
 }
 
 async fn server_start_message(port: u16, runtime_version: &'static RuntimeVersion, entrance: &'static Entrance, silent: bool) -> Result<()> {
+    let teo_version = env!("CARGO_PKG_VERSION");
+    if json_output() {
+        event("listening", json!({ "version": teo_version, "runtime": runtime_version.to_string(), "entrance": entrance.to_str(), "port": port }));
+        return Ok(())
+    }
     if silent { return Ok(()) }
     // Introducing
-    let teo_version = env!("CARGO_PKG_VERSION");
     let teo = format!("Teo {}", teo_version);
     info_message(format!("{} ({}, {})", teo, runtime_version.to_string(), entrance.to_str()));
     // Listening
@@ -289,7 +362,7 @@ async fn server_start_message(port: u16, runtime_version: &'static RuntimeVersio
     Ok(())
 }
 
-fn method_from(m: &HttpMethod) -> Result<Method> {
+pub(crate) fn method_from(m: &HttpMethod) -> Result<Method> {
     Ok(match m.as_str() {
         "GET" => Method::Get,
         "POST" => Method::Post,
@@ -305,6 +378,9 @@ async fn dangerous_operation(action :&str)-> Result<Response>{
         let dangerous_operation = DangerousOperations::try_from(action)?;
         match dangerous_operation {
             DangerousOperations::Seed | DangerousOperations::Unseed | DangerousOperations::Reseed => {
+                if !matches!(dangerous_operation, DangerousOperations::Seed) {
+                    purge::guard(action, false)?;
+                }
                 let mut diagnostics = Diagnostics::new();
                 let data_sets = load_data_sets(Ctx::main_namespace(), None, false, Ctx::schema(), &mut diagnostics)?;
                 let transaction_ctx = transaction::Ctx::new(Ctx::conn_ctx().clone());
@@ -312,24 +388,134 @@ async fn dangerous_operation(action :&str)-> Result<Response>{
                     seed_from_dangerous_operation(dangerous_operation)?,
                     data_sets,
                     transaction_ctx,
-                    false,
                 )
                 .await?
             }
             DangerousOperations::PurgeAndSeed => {
+                purge::guard(action, false)?;
                 purge::purge().await?;
                 connect_databases(Ctx::main_namespace_mut(),true).await?;
                 let mut diagnostics = Diagnostics::new();
                 let data_sets = load_data_sets(Ctx::main_namespace(), None, false, Ctx::schema(), &mut diagnostics)?;
                 let transaction_ctx = transaction::Ctx::new(Ctx::conn_ctx().clone());
-                seed(SeedCommandAction::Seed, data_sets, transaction_ctx, false).await?
+                seed(SeedCommandAction::Seed, data_sets, transaction_ctx).await?
+            }
+            DangerousOperations::Purge => {
+                purge::guard(action, false)?;
+                purge::purge().await?
             }
-            DangerousOperations::Purge => purge::purge().await?,
         }
         Ok(Response::data(Value::Bool(true)))
 }
 
-enum HandlerResolved<'a> {
+/// A transaction which requests join instead of starting their own, e.g.
+/// the one opened by `.begin` in the console. Only inserted in-process,
+/// clients can't set request extensions.
+#[derive(Clone)]
+pub(crate) struct SharedTransaction(pub(crate) transaction::Ctx);
+
+/// An identity requests are performed as, instead of the one a token
+/// resolves to, e.g. the record passed to `query --as`. Only inserted
+/// in-process like [`SharedTransaction`].
+#[derive(Clone)]
+pub(crate) struct Impersonation(pub(crate) Object);
+
+fn impersonate(http_request: &HttpRequest, ctx: &request::Ctx) {
+    if let Some(impersonation) = http_request.extensions().get::<Impersonation>() {
+        ctx.set_identity(Some(impersonation.0.clone()));
+    }
+}
+
+/// The identity of a request outside of handlers, resolved by the
+/// middlewares of the main namespace the same way as for handlers.
+async fn request_identity(http_request: &HttpRequest, main_namespace: &'static Namespace) -> Result<Option<Object>> {
+    let ctx = request::Ctx::new(
+        request::Request::new(Arc::new(RequestImpl::new(http_request.clone()))),
+        Arc::new(Value::Null),
+        transaction::Ctx::new(connection::Ctx::from_namespace(main_namespace)),
+        HandlerMatch::new(vec![], "admin".to_owned(), IndexMap::new()),
+    );
+    let identity: Arc<Mutex<Option<Object>>> = Arc::new(Mutex::new(None));
+    let resolved = identity.clone();
+    main_namespace.middleware_stack.call(ctx, &move |ctx: request::Ctx| {
+        let resolved = resolved.clone();
+        async move {
+            *resolved.lock().unwrap() = ctx.identity();
+            Ok(Response::empty())
+        }
+    }).await?;
+    let identity = identity.lock().unwrap().take();
+    Ok(identity)
+}
+
+fn request_transaction_ctx(http_request: &HttpRequest, main_namespace: &Namespace) -> transaction::Ctx {
+    match http_request.extensions().get::<SharedTransaction>() {
+        Some(shared) => shared.0.clone(),
+        None => transaction::Ctx::new(connection::Ctx::from_namespace(main_namespace)),
+    }
+}
+
+/// Find the namespace and the handler of a match, the same way requests are
+/// served.
+pub(crate) fn resolve_handler<'a>(main_namespace: &'a Namespace, match_result: &HandlerMatch) -> Result<(&'a Namespace, HandlerResolved<'a>)> {
+    let mut group = false;
+    let dest_namespace = if let Some(d) = main_namespace.namespace_at_path(&match_result.path()) {
+        d
+    } else if match_result.path().len() > 0 {
+        if let Some(d) = main_namespace.namespace_at_path(&match_result.path_without_last()) {
+            group = true;
+            d
+        } else {
+            Err(Error::not_found())?
+        }
+    } else {
+        Err(Error::not_found())?
+    };
+    let handler_resolved = if group {
+        if let Some(model) = dest_namespace.models.get(match_result.group_name()) {
+            if let Some(group) = dest_namespace.model_handler_groups.get(match_result.group_name()) {
+                if let Some(handler) = group.handlers.get(match_result.handler_name()) {
+                    (dest_namespace, HandlerResolved::Custom(handler))
+                } else {
+                    if let Some(action) = builtin_action_handler_from_name(match_result.handler_name()) {
+                        (dest_namespace, HandlerResolved::Builtin(model, action))
+                    } else {
+                        Err(Error::not_found())?
+                    }
+                }
+            } else {
+                if let Some(action) = builtin_action_handler_from_name(match_result.handler_name()) {
+                    (dest_namespace, HandlerResolved::Builtin(model, action))
+                } else {
+                    Err(Error::not_found())?
+                }
+            }
+        } else if let Some(group) = dest_namespace.handler_groups.get(match_result.group_name()) {
+            if let Some(handler) = group.handlers.get(match_result.handler_name()) {
+                (dest_namespace, HandlerResolved::Custom(handler))
+            } else {
+                Err(Error::not_found())?
+            }
+        } else {
+            Err(Error::not_found())?
+        }
+    } else {
+        if let Some(handler) = dest_namespace.handlers.get(match_result.handler_name()) {
+            (dest_namespace, HandlerResolved::Custom(handler))
+        } else {
+            Err(Error::not_found())?
+        }
+    };
+    // records of the shipped std models are only written by the server
+    if let (_, HandlerResolved::Builtin(model, _)) = &handler_resolved {
+        if stdlib::is_internal(model) {
+            Err(Error::not_found())?
+        }
+    }
+    Ok(handler_resolved)
+}
+
+pub(crate) enum HandlerResolved<'a> {
     Custom(&'a Handler),
     Builtin(&'a Model, Action),
 }
@@ -4,3 +4,7 @@ pub mod request;
 pub mod responder;
 pub mod error;
 pub mod static_files;
+pub mod options;
+pub mod panic;
+pub(crate) mod watch;
+pub mod admin;
@@ -0,0 +1,45 @@
+use std::time::Duration;
+use actix_web::http::KeepAlive;
+
+/// Worker pool and connection settings of the HTTP server. Unset values use
+/// actix-web's defaults.
+#[derive(Debug, Clone, Default)]
+pub struct ServerOptions {
+    pub workers: Option<usize>,
+    pub backlog: Option<u32>,
+    pub max_connections: Option<usize>,
+    /// Keep alive duration. `Some(Duration::ZERO)` disables keep alive.
+    pub keep_alive: Option<Duration>,
+    pub client_request_timeout: Option<Duration>,
+    pub client_disconnect_timeout: Option<Duration>,
+    /// Accept HTTP/2 over cleartext connections besides HTTP/1.
+    pub http2: Option<bool>,
+}
+
+impl ServerOptions {
+
+    /// Values set in `other` take precedence.
+    pub fn merge(&self, other: &ServerOptions) -> ServerOptions {
+        ServerOptions {
+            workers: other.workers.or(self.workers),
+            backlog: other.backlog.or(self.backlog),
+            max_connections: other.max_connections.or(self.max_connections),
+            keep_alive: other.keep_alive.or(self.keep_alive),
+            client_request_timeout: other.client_request_timeout.or(self.client_request_timeout),
+            client_disconnect_timeout: other.client_disconnect_timeout.or(self.client_disconnect_timeout),
+            http2: other.http2.or(self.http2),
+        }
+    }
+
+    pub(crate) fn http2(&self) -> bool {
+        self.http2.unwrap_or(false)
+    }
+
+    pub(crate) fn actix_keep_alive(&self) -> Option<KeepAlive> {
+        self.keep_alive.map(|duration| if duration.is_zero() {
+            KeepAlive::Disabled
+        } else {
+            KeepAlive::Timeout(duration)
+        })
+    }
+}
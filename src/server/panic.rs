@@ -0,0 +1,159 @@
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::Once;
+use std::task::{Context, Poll};
+use actix_http::header::{HeaderName, HeaderValue};
+use actix_web::{HttpRequest, HttpResponse, ResponseError};
+use actix_web::dev::ServiceRequest;
+use teo_result::Error;
+use crate::app::Ctx;
+use crate::message::error_message;
+use crate::server::error::WrapError;
+
+pub(crate) const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Information about a panic caught while handling a request.
+#[derive(Debug, Clone)]
+pub struct PanicReport {
+    pub request_id: String,
+    pub method: String,
+    pub path: String,
+    pub message: String,
+    pub backtrace: String,
+}
+
+pub trait PanicHook: Send + Sync {
+    fn call(&self, report: &PanicReport);
+}
+
+impl<F> PanicHook for F where F: Fn(&PanicReport) + Send + Sync {
+    fn call(&self, report: &PanicReport) {
+        self(report)
+    }
+}
+
+/// A panic recorded by the hook, before unwinding loses its backtrace.
+struct CapturedPanic {
+    request_id: String,
+    message: String,
+    backtrace: String,
+}
+
+thread_local! {
+    /// The request being handled on this thread.
+    static CURRENT_REQUEST: RefCell<Option<String>> = RefCell::new(None);
+    static LAST_PANIC: RefCell<Option<CapturedPanic>> = RefCell::new(None);
+}
+
+static INSTALL_PANIC_HOOK: Once = Once::new();
+
+/// Record the message and backtrace of panics raised while handling a
+/// request, so that the request pipeline can report them after unwinding.
+/// Other panics only reach the previous hook.
+pub(crate) fn install_panic_hook() {
+    INSTALL_PANIC_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if let Some(request_id) = CURRENT_REQUEST.with(|current| current.borrow().clone()) {
+                let message = match info.location() {
+                    Some(location) => format!("{} at {}", payload_message(info.payload()), location),
+                    None => payload_message(info.payload()),
+                };
+                let backtrace = Backtrace::force_capture().to_string();
+                LAST_PANIC.with(|last| *last.borrow_mut() = Some(CapturedPanic { request_id, message, backtrace }));
+            }
+            previous(info);
+        }));
+    });
+}
+
+/// Run `f` as part of handling the request, panics it raises are captured.
+pub(crate) fn in_request<T>(request_id: &str, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<String>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CURRENT_REQUEST.with(|current| *current.borrow_mut() = self.0.take());
+        }
+    }
+    // restored on unwinding too, after the hook has seen the request
+    let _restore = Restore(CURRENT_REQUEST.with(|current| current.replace(Some(request_id.to_owned()))));
+    f()
+}
+
+/// Polls the future of a request with [`in_request`].
+pub(crate) struct InRequest<F> {
+    request_id: String,
+    future: Pin<Box<F>>,
+}
+
+impl<F> InRequest<F> {
+    pub(crate) fn new(request_id: &str, future: F) -> Self {
+        Self { request_id: request_id.to_owned(), future: Box::pin(future) }
+    }
+}
+
+impl<F> Future for InRequest<F> where F: Future {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        in_request(&this.request_id, || this.future.as_mut().poll(cx))
+    }
+}
+
+/// Reuse the request ID sent by the client or generate a new one. The ID is
+/// written back into the request headers so handlers can read it.
+pub(crate) fn ensure_request_id(req: &mut ServiceRequest) -> String {
+    if let Some(request_id) = req.headers().get(REQUEST_ID_HEADER).and_then(|v| v.to_str().ok()) {
+        if !request_id.is_empty() {
+            return request_id.to_owned();
+        }
+    }
+    let request_id = uuid::Uuid::new_v4().to_string();
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        req.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+    request_id
+}
+
+pub(crate) fn request_id_header_value(request_id: &str) -> (HeaderName, HeaderValue) {
+    (HeaderName::from_static(REQUEST_ID_HEADER), HeaderValue::from_str(request_id).unwrap_or(HeaderValue::from_static("")))
+}
+
+/// Log the panic, forward it to the user's hook and build a 500 response.
+pub(crate) fn panic_response(http_request: &HttpRequest, request_id: &str, payload: Box<dyn Any + Send>) -> HttpResponse {
+    // a panic captured for another request is never attached to this one
+    let captured = LAST_PANIC.with(|last| last.borrow_mut().take()).filter(|c| c.request_id == request_id);
+    let (message, backtrace) = match captured {
+        Some(captured) => (captured.message, captured.backtrace),
+        None => (payload_message(payload.as_ref()), String::new()),
+    };
+    let report = PanicReport {
+        request_id: request_id.to_owned(),
+        method: http_request.method().as_str().to_owned(),
+        path: http_request.path().to_owned(),
+        message,
+        backtrace,
+    };
+    error_message(format!("panic while handling {} {} (request {}): {}\n{}", report.method, report.path, report.request_id, report.message, report.backtrace));
+    if let Some(hook) = Ctx::panic_hook() {
+        if panic::catch_unwind(AssertUnwindSafe(|| hook.call(&report))).is_err() {
+            error_message(format!("panic hook panicked while reporting request {}", report.request_id));
+        }
+    }
+    WrapError::from(Error::internal_server_error_message("internal server error")).error_response()
+}
+
+fn payload_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_owned()
+    }
+}
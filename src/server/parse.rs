@@ -9,7 +9,10 @@ use teo_result::{Result, Error};
 pub(super) async fn parse_json_body(mut payload: web::Payload) -> Result<JsonValue> {
     let mut body = web::BytesMut::new();
     while let Some(chunk) = payload.next().await {
-        let chunk = chunk.unwrap();
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(_) => return Err(Error::invalid_request_message("cannot read request body")),
+        };
         // limit max size of in-memory payload
         if (body.len() + chunk.len()) > 262_144usize {
             return Err(Error::internal_server_error_message("memory overflow"));
@@ -37,17 +40,30 @@ pub(super) async fn parse_form_body(http_request: HttpRequest, mut payload: web:
         Err(err) => return Err(Error::invalid_request_message("incorrect form format")),
     };
     let mut result_value = json!({});
-    while let Some(mut field) = multipart.try_next().await.unwrap() {
+    while let Some(mut field) = multipart.try_next().await.map_err(|_| Error::invalid_request_message("incorrect form format"))? {
         // A multipart/form-data stream has to contain `content_disposition`
         if let Some(filename) = field.content_disposition().get_filename().map(|f| f.to_owned()) {
-            let filepath = std::env::temp_dir().join(filename.clone()).to_str().unwrap().to_owned();
+            // only keep the last component, the file name is controlled by the client
+            let Some(safe_filename) = std::path::Path::new(&filename).file_name().map(|f| f.to_owned()) else {
+                return Err(Error::invalid_request_message("invalid file name"));
+            };
+            let filepath = match std::env::temp_dir().join(safe_filename).to_str() {
+                Some(filepath) => filepath.to_owned(),
+                None => return Err(Error::invalid_request_message("invalid file name")),
+            };
             let filepath2 = filepath.clone();
             // File::create is blocking operation, use threadpool
-            let mut f = web::block(move || std::fs::File::create(&filepath)).await.unwrap().unwrap();
+            let mut f = match web::block(move || std::fs::File::create(&filepath)).await {
+                Ok(Ok(f)) => f,
+                _ => return Err(Error::internal_server_error_message("cannot create temporary file")),
+            };
             // Field in turn is stream of *Bytes* object
-            while let Some(chunk) = field.try_next().await.unwrap() {
+            while let Some(chunk) = field.try_next().await.map_err(|_| Error::invalid_request_message("incorrect form format"))? {
                 // filesystem operations are blocking, we have to use threadpool
-                f = web::block(move || f.write_all(&chunk).map(|_| f)).await.unwrap().unwrap();
+                f = match web::block(move || f.write_all(&chunk).map(|_| f)).await {
+                    Ok(Ok(f)) => f,
+                    _ => return Err(Error::internal_server_error_message("cannot write temporary file")),
+                };
             }
             let owned_field_name = field.name().to_owned();
             if owned_field_name.ends_with("[]") {
@@ -55,7 +71,10 @@ pub(super) async fn parse_form_body(http_request: HttpRequest, mut payload: web:
                 if !result_value.as_object_mut().unwrap().contains_key(field_name_without_suffix) {
                     result_value.as_object_mut().unwrap().insert(field_name_without_suffix.to_owned(), json!([]));
                 }
-                result_value.as_object_mut().unwrap().get_mut(field_name_without_suffix).unwrap().as_array_mut().unwrap().push(json!({
+                let Some(array) = result_value.as_object_mut().unwrap().get_mut(field_name_without_suffix).unwrap().as_array_mut() else {
+                    return Err(Error::invalid_request_message("incorrect form field name"));
+                };
+                array.push(json!({
                     "filepath": filepath2,
                     "contentType": field.content_type().map(|c| c.to_string()),
                     "filename": filename,
@@ -63,13 +82,18 @@ pub(super) async fn parse_form_body(http_request: HttpRequest, mut payload: web:
                 }));
             } else if owned_field_name.ends_with("]") {
                 let regex = Regex::new("(.*)\\[(.*)\\]").unwrap();
-                let found = regex.captures(&owned_field_name).unwrap();
+                let Some(found) = regex.captures(&owned_field_name) else {
+                    return Err(Error::invalid_request_message("incorrect form field name"));
+                };
                 let field_name = found.get(1).unwrap().as_str().to_owned();
                 let dict_name = found.get(2).unwrap().as_str().to_owned();
                 if !result_value.as_object_mut().unwrap().contains_key(&field_name) {
-                    result_value.as_object_mut().unwrap().insert(field_name.clone(), json!([]));
+                    result_value.as_object_mut().unwrap().insert(field_name.clone(), json!({}));
                 }
-                result_value.as_object_mut().unwrap().get_mut(&field_name).unwrap().as_object_mut().unwrap().insert(dict_name, json!({
+                let Some(dict) = result_value.as_object_mut().unwrap().get_mut(&field_name).unwrap().as_object_mut() else {
+                    return Err(Error::invalid_request_message("incorrect form field name"));
+                };
+                dict.insert(dict_name, json!({
                     "filepath": filepath2,
                     "contentType": field.content_type().map(|c| c.to_string()),
                     "filename": filename,
@@ -85,10 +109,13 @@ pub(super) async fn parse_form_body(http_request: HttpRequest, mut payload: web:
             }
         } else {
             let mut body = web::BytesMut::new();
-            while let Some(chunk) = field.try_next().await.unwrap() {
+            while let Some(chunk) = field.try_next().await.map_err(|_| Error::invalid_request_message("incorrect form format"))? {
                 body.extend_from_slice(&chunk);
             }
-            result_value.as_object_mut().unwrap().insert(field.name().to_owned(), serde_json::Value::String(String::from_utf8(body.as_ref().to_vec()).unwrap()));
+            let Ok(string) = String::from_utf8(body.as_ref().to_vec()) else {
+                return Err(Error::invalid_request_message("form field is not valid utf-8"));
+            };
+            result_value.as_object_mut().unwrap().insert(field.name().to_owned(), serde_json::Value::String(string));
         }
     }
     Ok(result_value)
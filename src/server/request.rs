@@ -90,7 +90,7 @@ impl teo_runtime::request::header::readonly::r#trait::HeaderMap for HeadersImpl
     }
 
     fn get(&self, key: &str) -> Option<&str> {
-        self.http_headers.get(key).map(|v| v.to_str().unwrap())
+        self.http_headers.get(key).and_then(|v| v.to_str().ok())
     }
 }
 
@@ -1,8 +1,12 @@
 use actix_web::{HttpRequest, HttpResponse};
 use actix_web::http::StatusCode;
+use actix_web::http::header::{HeaderName, HeaderValue};
 use teo_runtime::response::body::BodyInner;
 use teo_runtime::response::Response;
 use actix_files::NamedFile;
+use actix_web::ResponseError;
+use teo_result::Error;
+use crate::server::error::WrapError;
 
 pub trait IntoHttpResponse {
     fn into_http_response(self, http_request: HttpRequest) -> HttpResponse;
@@ -12,18 +16,37 @@ impl IntoHttpResponse for Response {
 
     fn into_http_response(self, http_request: HttpRequest) -> HttpResponse {
         let mut builder = HttpResponse::Ok();
-        builder.status(StatusCode::from_u16(self.code()).unwrap());
+        builder.status(StatusCode::from_u16(self.code()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR));
         for key in self.headers().keys() {
-            builder.insert_header((key.clone(), self.headers().get(&key).unwrap().as_str()));
+            if let Some(value) = self.headers().get(&key) {
+                builder.insert_header((key.clone(), value.as_str()));
+            }
         }
         match self.body().inner.as_ref() {
             BodyInner::Empty => (),
             BodyInner::String(content) => return builder.body(content.to_string()),
-            BodyInner::File(file) => return NamedFile::open(file).unwrap().into_response(&http_request),
+            BodyInner::File(file) => {
+                let mut response = match NamedFile::open(file) {
+                    Ok(named_file) => named_file.into_response(&http_request),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return WrapError::from(Error::not_found()).error_response(),
+                    Err(_) => return WrapError::from(Error::internal_server_error_message("cannot open file")).error_response(),
+                };
+                // headers set by the handler override the ones guessed from the file
+                for key in self.headers().keys() {
+                    if let Some(value) = self.headers().get(&key) {
+                        if let (Ok(name), Ok(value)) = (HeaderName::try_from(key.as_str()), HeaderValue::from_str(value.as_str())) {
+                            response.headers_mut().insert(name, value);
+                        }
+                    }
+                }
+                return response;
+            },
             BodyInner::Teon(value) => {
                 builder.content_type("application/json");
-                let json_value = serde_json::Value::try_from(value).unwrap();
-                let string_value = serde_json::to_string(&json_value).unwrap();
+                let string_value = match serde_json::Value::try_from(value) {
+                    Ok(json_value) => json_value.to_string(),
+                    Err(_) => return WrapError::from(Error::internal_server_error_message("cannot serialize response")).error_response(),
+                };
                 return builder.body(string_value);
             }
         }
@@ -1,14 +1,225 @@
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
+use regex::Regex;
 use teo_result::{Result, Error};
 use teo_runtime::response::Response;
 
+/// A directory of static files served by the server.
+///
+/// Paths are normalized before touching the file system, so a request can
+/// never escape `base`. Directories resolve to `index`, missing files fall
+/// back to `fallback` when it's set, and precompressed `.br` and `.gz`
+/// siblings are preferred when the client accepts them. ETag,
+/// Last-Modified and range requests are handled when the response is sent.
+#[derive(Debug, Clone)]
+pub struct StaticFiles {
+    base: PathBuf,
+    index: Option<String>,
+    fallback: Option<String>,
+    precompressed: bool,
+    cache_control: Vec<(Regex, String)>,
+}
+
+impl StaticFiles {
+
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        Self {
+            base: base.into(),
+            index: Some("index.html".to_owned()),
+            fallback: None,
+            precompressed: true,
+            cache_control: vec![],
+        }
+    }
+
+    /// The file to serve when a directory is requested. Pass `None` to disable.
+    pub fn index(mut self, index: Option<impl Into<String>>) -> Self {
+        self.index = index.map(Into::into);
+        self
+    }
+
+    /// The file to serve when nothing matches, for single page apps.
+    pub fn fallback(mut self, fallback: impl Into<String>) -> Self {
+        self.fallback = Some(fallback.into());
+        self
+    }
+
+    /// Whether `.br` and `.gz` variants are looked up.
+    pub fn precompressed(mut self, precompressed: bool) -> Self {
+        self.precompressed = precompressed;
+        self
+    }
+
+    /// Add a `Cache-Control` rule. The first rule whose pattern matches the
+    /// normalized relative path wins.
+    pub fn cache_control(mut self, pattern: impl AsRef<str>, value: impl Into<String>) -> Result<Self> {
+        let regex = match Regex::new(pattern.as_ref()) {
+            Ok(regex) => regex,
+            Err(e) => Err(Error::new(format!("invalid cache control pattern: {}", e)))?,
+        };
+        self.cache_control.push((regex, value.into()));
+        Ok(self)
+    }
+
+    pub fn base(&self) -> &Path {
+        self.base.as_path()
+    }
+
+    /// Serve `path` relative to `base`. `accept_encoding` is the value of the
+    /// request's `Accept-Encoding` header.
+    pub fn serve(&self, path: impl AsRef<str>, accept_encoding: Option<&str>) -> Result<Response> {
+        let Some(relative) = normalize_path(path.as_ref()) else {
+            return Err(Error::not_found());
+        };
+        if let Some(found) = self.resolve(&relative) {
+            return Ok(self.file_response(found, accept_encoding));
+        }
+        if let Some(fallback) = &self.fallback {
+            if let Some(relative) = normalize_path(fallback) {
+                let combined_path = self.base.join(&relative);
+                if combined_path.is_file() {
+                    let response = self.file_response((combined_path, relative), accept_encoding);
+                    response.headers().set("cache-control", "no-cache");
+                    return Ok(response);
+                }
+            }
+        }
+        Err(Error::not_found())
+    }
+
+    fn resolve(&self, relative: &Path) -> Option<(PathBuf, PathBuf)> {
+        let combined_path = self.base.join(relative);
+        if combined_path.is_file() {
+            return Some((combined_path, relative.to_owned()));
+        }
+        if combined_path.is_dir() {
+            if let Some(index) = &self.index {
+                let index_path = combined_path.join(index);
+                if index_path.is_file() {
+                    return Some((index_path, relative.join(index)));
+                }
+            }
+        }
+        None
+    }
+
+    fn file_response(&self, found: (PathBuf, PathBuf), accept_encoding: Option<&str>) -> Response {
+        let (file_path, relative) = found;
+        let (served_path, encoding) = self.precompressed_variant(&file_path, accept_encoding);
+        let response = Response::file(served_path);
+        if let Some(encoding) = encoding {
+            // describe the original file, not the `.br` or `.gz` variant
+            let extension = file_path.extension().and_then(|e| e.to_str()).unwrap_or("");
+            let mime = actix_files::file_extension_to_mime(extension);
+            let file_name = file_path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            response.headers().set("content-encoding", encoding);
+            response.headers().set("content-type", mime.to_string());
+            response.headers().set("content-disposition", content_disposition(inline(mime.type_().as_str(), mime.subtype().as_str()), &file_name));
+        }
+        if self.precompressed {
+            response.headers().set("vary", "accept-encoding");
+        }
+        let relative_str = relative.to_string_lossy().replace('\\', "/");
+        if let Some((_, value)) = self.cache_control.iter().find(|(regex, _)| regex.is_match(&relative_str)) {
+            response.headers().set("cache-control", value.as_str());
+        }
+        response
+    }
+
+    fn precompressed_variant(&self, file_path: &Path, accept_encoding: Option<&str>) -> (PathBuf, Option<&'static str>) {
+        if !self.precompressed {
+            return (file_path.to_owned(), None);
+        }
+        let Some(accept_encoding) = accept_encoding else {
+            return (file_path.to_owned(), None);
+        };
+        for (encoding, extension) in [("br", "br"), ("gzip", "gz")] {
+            if accepts_encoding(accept_encoding, encoding) {
+                let mut variant = file_path.as_os_str().to_owned();
+                variant.push(".");
+                variant.push(extension);
+                let variant = PathBuf::from(variant);
+                if variant.is_file() {
+                    return (variant, Some(encoding));
+                }
+            }
+        }
+        (file_path.to_owned(), None)
+    }
+}
+
 pub fn serve_static_files(base: impl AsRef<str>, path: impl AsRef<str>) -> Result<Response> {
-    let base_str = base.as_ref();
-    let path_str = path.as_ref();
-    let combined_path = PathBuf::from(base_str).join(path_str);
-    if combined_path.is_file() {
-        Ok(Response::file(combined_path))
+    StaticFiles::new(base.as_ref()).precompressed(false).serve(path, None)
+}
+
+/// Percent-decode `path` and turn it into a relative path without `.` or `..`
+/// segments. Returns `None` when the path would escape its base.
+pub(crate) fn normalize_path(path: &str) -> Option<PathBuf> {
+    let decoded = percent_decode(path)?;
+    if decoded.contains('\0') || decoded.contains('\\') {
+        return None;
+    }
+    let mut result = PathBuf::new();
+    for segment in decoded.split('/') {
+        match segment {
+            "" | "." => (),
+            ".." => return None,
+            segment => {
+                let mut components = Path::new(segment).components();
+                match (components.next(), components.next()) {
+                    (Some(Component::Normal(_)), None) => result.push(segment),
+                    _ => return None,
+                }
+            }
+        }
+    }
+    Some(result)
+}
+
+fn percent_decode(path: &str) -> Option<String> {
+    let bytes = path.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(index + 1..index + 3)?).ok()?;
+            result.push(u8::from_str_radix(hex, 16).ok()?);
+            index += 3;
+        } else {
+            result.push(bytes[index]);
+            index += 1;
+        }
+    }
+    String::from_utf8(result).ok()
+}
+
+/// Whether a file of this mime type is displayed by browsers, the same
+/// choice as `NamedFile` makes.
+fn inline(r#type: &str, subtype: &str) -> bool {
+    match r#type {
+        "image" | "text" | "audio" | "video" => true,
+        "application" => matches!(subtype, "javascript" | "json" | "wasm" | "xhtml"),
+        _ => false,
+    }
+}
+
+fn content_disposition(inline: bool, file_name: &str) -> String {
+    let disposition = if inline { "inline" } else { "attachment" };
+    if file_name.is_ascii() {
+        format!("{}; filename=\"{}\"", disposition, file_name.replace('\\', "\\\\").replace('"', "\\\""))
     } else {
-        Err(Error::not_found())
+        let encoded: String = file_name.bytes().map(|b| if b.is_ascii_alphanumeric() || b"-._~".contains(&b) { (b as char).to_string() } else { format!("%{:02X}", b) }).collect();
+        format!("{}; filename*=UTF-8''{}", disposition, encoded)
     }
 }
+
+fn accepts_encoding(accept_encoding: &str, encoding: &str) -> bool {
+    accept_encoding.split(',').any(|item| {
+        let mut parts = item.trim().split(';');
+        let name = parts.next().unwrap_or("").trim();
+        let refused = parts.any(|p| {
+            let p = p.trim();
+            p == "q=0" || p == "q=0.0" || p == "q=0.00" || p == "q=0.000"
+        });
+        name.eq_ignore_ascii_case(encoding) && !refused
+    })
+}
@@ -0,0 +1,88 @@
+use std::env::current_dir;
+use std::{fs, mem};
+use std::time::{Duration, SystemTime};
+use once_cell::sync::Lazy;
+use tokio::sync::RwLock;
+use teo_result::{Error, Result};
+use teo_runtime::namespace::Namespace;
+use teo_runtime::schema::load::load_schema::load_schema;
+use teo_runtime::stdlib::load::{load as load_std};
+use teo_runtime::utils::find_main_schema_file;
+use crate::app::Ctx;
+use crate::app::database::{connect_namespace, install_connections};
+use crate::message::{diagnostics_message, error_message, info_message};
+use crate::migrate::migrate_namespace;
+use crate::{profile, stdlib};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Held for reading while a request is handled or a worker runs, and for
+/// writing while the namespace is swapped, so that nothing reads the
+/// namespace while it's replaced.
+pub(crate) static RELOAD_LOCK: Lazy<RwLock<()>> = Lazy::new(|| RwLock::new(()));
+
+/// Reload the schema whenever one of its files changes.
+pub(crate) fn spawn(silent: bool) {
+    if !silent {
+        info_message("watching schema files for changes");
+    }
+    tokio::spawn(async move {
+        let mut last_modified = modification_times();
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            if modification_times() == last_modified {
+                continue
+            }
+            info_message("schema changed, reloading");
+            match reload(silent).await {
+                Ok(()) => info_message("schema reloaded"),
+                Err(error) => error_message(format!("{}, keeping the running schema", error)),
+            }
+            // imports may have changed
+            last_modified = modification_times();
+        }
+    });
+}
+
+async fn reload(silent: bool) -> Result<()> {
+    let current_dir = match current_dir() {
+        Ok(current_dir) => current_dir,
+        Err(e) => Err(Error::new(format!("{}", e)))?,
+    };
+    let main_schema_file = find_main_schema_file(Ctx::cli().main(), &current_dir)?;
+    let (schema, diagnostics) = stdlib::parse_schema(main_schema_file.as_path().to_str().unwrap(), None)?;
+    diagnostics_message(&diagnostics);
+    if diagnostics.has_errors() {
+        Err(Error::new("schema has errors"))?
+    }
+    let schema = stdlib::declare_missing_models(&schema).await?.unwrap_or(schema);
+    let mut namespace = Namespace::main();
+    load_std(&mut namespace);
+    stdlib::define_pipeline_items(&mut namespace);
+    load_schema(&mut namespace, &schema, false).await?;
+    profile::apply(&mut namespace, Ctx::cli().env())?;
+    let bind = namespace.server.as_ref().map(|s| s.bind.clone());
+    if bind != Ctx::main_namespace().server.as_ref().map(|s| s.bind.clone()) {
+        Err(Error::new("server bind changed, restart the server to apply it"))?
+    }
+    // the running namespace keeps serving if connecting or migrating fails
+    connect_namespace(&mut namespace, silent).await?;
+    migrate_namespace(&namespace, silent).await?;
+    // wait for running requests and workers, new ones wait for the swap
+    let _guard = RELOAD_LOCK.write().await;
+    // the running namespace and schema may be referenced as `'static` after
+    // the swap, so they're leaked instead of dropped
+    mem::forget(mem::replace(Ctx::main_namespace_mut(), namespace));
+    if let Some(schema) = Ctx::get_mut().schema.replace(schema) {
+        mem::forget(schema);
+    }
+    install_connections();
+    Ok(())
+}
+
+fn modification_times() -> Vec<(String, Option<SystemTime>)> {
+    Ctx::schema().sources().iter().map(|source| {
+        let modified = fs::metadata(&source.file_path).and_then(|m| m.modified()).ok();
+        (source.file_path.clone(), modified)
+    }).collect()
+}
@@ -0,0 +1,38 @@
+use std::any::Any;
+use std::sync::{Arc, Mutex, Weak};
+use teo_runtime::model::Object;
+
+/// Values read by a `@beforeSave` callback of an object and taken by its
+/// `@afterSave` callback, e.g. the stored record before an update.
+///
+/// Values are kept per object, so concurrent saves of the same record don't
+/// see each other's. Holding a weak reference keeps the address of an
+/// object from being reused, and values of dropped objects, e.g. of an
+/// aborted save, are discarded on the next insert.
+pub(crate) struct BeforeSave<T> {
+    entries: Mutex<Vec<(Weak<dyn Any + Send + Sync>, T)>>,
+}
+
+impl<T> BeforeSave<T> {
+
+    pub(crate) const fn new() -> Self {
+        Self { entries: Mutex::new(Vec::new()) }
+    }
+
+    pub(crate) fn insert(&self, object: &Object, value: T) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|(owner, _)| owner.strong_count() > 0 && !is_owner(owner, object));
+        let inner: Arc<dyn Any + Send + Sync> = object.inner.clone();
+        entries.push((Arc::downgrade(&inner), value));
+    }
+
+    pub(crate) fn take(&self, object: &Object) -> Option<T> {
+        let mut entries = self.entries.lock().unwrap();
+        let index = entries.iter().position(|(owner, _)| is_owner(owner, object))?;
+        Some(entries.swap_remove(index).1)
+    }
+}
+
+fn is_owner(owner: &Weak<dyn Any + Send + Sync>, object: &Object) -> bool {
+    owner.as_ptr() as *const () == Arc::as_ptr(&object.inner) as *const ()
+}
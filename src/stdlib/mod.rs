@@ -0,0 +1,204 @@
+pub(crate) mod before_save;
+
+use std::collections::HashMap;
+use std::fs;
+use regex::Regex;
+use teo_parser::{parse as schema_parse};
+use teo_parser::ast::schema::Schema;
+use teo_parser::diagnostics::diagnostics::Diagnostics;
+use teo_result::{Error, Result};
+use teo_runtime::database::database::Database;
+use teo_runtime::model::Model;
+use teo_runtime::namespace::Namespace;
+use teo_runtime::schema::load::load_schema::load_schema;
+use teo_runtime::stdlib::load::{load as load_std};
+use crate::app::Ctx;
+use crate::{audit, idempotency, schedule, webhook};
+use crate::message::diagnostics_message;
+
+/// A std model backing an opt-in feature.
+struct StdModel {
+    name: &'static str,
+    declaration: &'static str,
+    enabled: fn() -> bool,
+}
+
+/// The std models this crate ships. They are declared in the `std`
+/// namespace of the main schema when their feature is enabled, and never
+/// served as builtin actions.
+const MODELS: [StdModel; 5] = [
+    StdModel {
+        name: "IdempotencyRecord",
+        declaration: include_str!("../idempotency/models/idempotency_record.teo"),
+        enabled: || Ctx::idempotency().is_some(),
+    },
+    StdModel {
+        name: "WebhookDelivery",
+        declaration: include_str!("../webhook/models/webhook_delivery.teo"),
+        enabled: || !Ctx::webhooks().is_empty(),
+    },
+    StdModel {
+        name: "WebhookLease",
+        declaration: include_str!("../webhook/models/webhook_lease.teo"),
+        enabled: || !Ctx::webhooks().is_empty(),
+    },
+    StdModel {
+        name: "AuditLog",
+        declaration: include_str!("../audit/models/audit_log.teo"),
+        enabled: || Ctx::audit().is_some(),
+    },
+    StdModel {
+        name: "ProgramRun",
+        declaration: include_str!("../schedule/models/program_run.teo"),
+        enabled: || !Ctx::schedules().is_empty(),
+    },
+];
+
+/// The std pipeline items this crate implements. They are always declared,
+/// so that a schema using them parses before the app is configured.
+const ITEMS: [&str; 2] = [
+    include_str!("../webhook/items.teo"),
+    include_str!("../audit/items.teo"),
+];
+
+/// Make sure the std models of the enabled features are loaded into
+/// `namespace`, and that every schedule refers to a program. Runs after the
+/// databases are connected.
+pub(crate) fn check_models(namespace: &Namespace) -> Result<()> {
+    if Ctx::idempotency().is_some() {
+        idempotency::check_model(namespace)?;
+    }
+    if !Ctx::webhooks().is_empty() {
+        webhook::check_model(namespace)?;
+    }
+    if Ctx::audit().is_some() {
+        audit::check_model(namespace)?;
+    }
+    if !Ctx::schedules().is_empty() {
+        schedule::check(namespace)?;
+    }
+    Ok(())
+}
+
+/// Parse the main schema file with the std declarations of this crate
+/// appended, the pipeline items and `models`.
+fn parse(main: &str, unsaved_files: Option<HashMap<String, String>>, models: &[&StdModel], provider: Option<&Database>) -> Result<(Schema, Diagnostics)> {
+    let mut unsaved_files = unsaved_files.unwrap_or_default();
+    let source = match unsaved_files.get(main) {
+        Some(source) => source.clone(),
+        None => fs::read_to_string(main).map_err(|e| Error::new(format!("cannot read {}: {}", main, e)))?,
+    };
+    // appended after the source, positions of diagnostics don't change
+    let source = format!("{}\n\n{}\n", source, declarations(models, provider));
+    unsaved_files.insert(main.to_owned(), source);
+    Ok(schema_parse(main, None, Some(unsaved_files)))
+}
+
+/// Parse the main schema file of the app, see [`parse`].
+pub(crate) fn parse_schema(main: &str, unsaved_files: Option<HashMap<String, String>>) -> Result<(Schema, Diagnostics)> {
+    parse(main, unsaved_files, &[], None)
+}
+
+/// Parse the main schema file again with the declarations of the std models
+/// which enabled features need and `schema` doesn't declare. `None` if
+/// nothing is missing. Runs before the schema is loaded into the main
+/// namespace.
+pub(crate) async fn declare_missing_models(schema: &Schema) -> Result<Option<Schema>> {
+    if !MODELS.iter().any(|m| (m.enabled)()) {
+        return Ok(None);
+    }
+    // a scratch load tells the declared models and the provider
+    let mut scratch = Namespace::main();
+    load_std(&mut scratch);
+    load_schema(&mut scratch, schema, true).await?;
+    let missing: Vec<&StdModel> = MODELS.iter()
+        .filter(|m| (m.enabled)() && scratch.model_at_path(&vec!["std", m.name]).is_none())
+        .collect();
+    if missing.is_empty() {
+        return Ok(None);
+    }
+    let Some(connector) = scratch.connector.as_ref() else {
+        Err(Error::new(format!("`std.{}` requires a connector in the main namespace", missing[0].name)))?
+    };
+    let (main, source) = Ctx::main_schema();
+    let unsaved_files = source.clone().map(|source| HashMap::from([(main.clone(), source)]));
+    let (schema, diagnostics) = parse(main, unsaved_files, &missing, Some(&connector.provider))?;
+    if diagnostics.has_errors() {
+        diagnostics_message(&diagnostics);
+        Err(Error::new("cannot declare the std models of the enabled features"))?
+    }
+    Ok(Some(schema))
+}
+
+/// Implement the std pipeline items in `namespace`, before the schema is
+/// loaded into it.
+pub(crate) fn define_pipeline_items(namespace: &mut Namespace) {
+    let std = namespace.namespace_mut_or_create_at_path(&vec!["std"]);
+    webhook::outbox::define_pipeline_items(std);
+    audit::define_pipeline_items(std);
+}
+
+/// Whether the model is a shipped std model, which requests can't reach.
+pub(crate) fn is_internal(model: &Model) -> bool {
+    let path = model.path();
+    path.len() == 2 && path[0] == "std" && MODELS.iter().any(|m| m.name == path[1])
+}
+
+fn declarations(models: &[&StdModel], provider: Option<&Database>) -> String {
+    let models = models.iter().map(|m| for_provider(m.declaration, provider.unwrap()));
+    let body = ITEMS.iter().map(|i| i.to_string()).chain(models).collect::<Vec<String>>().join("\n");
+    let indented = body.lines().map(|l| if l.is_empty() { String::new() } else { format!("  {}", l) }).collect::<Vec<String>>().join("\n");
+    format!("namespace std {{\n{}\n}}", indented)
+}
+
+/// Declarations are written for SQL databases with `@db(.text)` on long
+/// strings.
+fn for_provider(declaration: &str, provider: &Database) -> String {
+    let db = Regex::new(r"@db\(\.text\)\n\s*").unwrap();
+    match provider {
+        Database::MySQL => declaration.replace("@db(.text)", "@db(.longText)"),
+        Database::PostgreSQL => declaration.to_owned(),
+        Database::SQLite => db.replace_all(declaration, "").to_string(),
+        Database::MongoDB => db.replace_all(declaration, "").replace("@id\n", "@id @map(\"_id\")\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn declares_items_and_models_in_std_namespace() {
+        let result = declarations(&[&MODELS[0]], Some(&Database::PostgreSQL));
+        assert!(result.starts_with("namespace std {\n  /// Remember whether the record is new"));
+        assert!(result.contains("\n  declare pipeline item webhookDelete<T>(name: String?): T -> T\n"));
+        assert!(result.contains("\n  model IdempotencyRecord {\n    /// The key"));
+        assert!(result.ends_with("  }\n}"));
+    }
+
+    #[test]
+    fn declares_only_items_without_models() {
+        let result = declarations(&[], None);
+        assert!(result.contains("webhookSave"));
+        assert!(result.contains("auditBefore"));
+        assert!(!result.contains("model "));
+    }
+
+    #[test]
+    fn declares_models_which_parse() {
+        let models: Vec<&StdModel> = MODELS.iter().collect();
+        let source = format!("connector {{\n  provider .sqlite\n  url \"sqlite::memory:\"\n}}\n\n{}\n", declarations(&models, Some(&Database::SQLite)));
+        let (_, diagnostics) = schema_parse("/std/schema.teo", None, Some(HashMap::from([("/std/schema.teo".to_owned(), source)])));
+        assert!(!diagnostics.has_errors());
+    }
+
+    #[test]
+    fn adapts_declarations_to_provider() {
+        let declaration = MODELS[0].declaration;
+        assert!(for_provider(declaration, &Database::MySQL).contains("@db(.longText)\n  responseBody"));
+        assert!(!for_provider(declaration, &Database::SQLite).contains("@db"));
+        let mongo = for_provider(declaration, &Database::MongoDB);
+        assert!(!mongo.contains("@db"));
+        assert!(mongo.contains("@id @map(\"_id\")\n  key: String"));
+    }
+}
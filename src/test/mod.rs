@@ -0,0 +1,6 @@
+pub mod server;
+pub mod response;
+
+pub use actix_web::test::TestRequest;
+pub use server::TestServer;
+pub use response::TestResponse;
@@ -0,0 +1,61 @@
+use actix_web::body::{self, MessageBody};
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::HeaderMap;
+use actix_web::web::Bytes;
+use serde_json::{Value as JsonValue};
+use teo_result::{Error, Result};
+
+/// A response received from a [`TestServer`](crate::test::TestServer).
+#[derive(Debug, Clone)]
+pub struct TestResponse {
+    status: u16,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl TestResponse {
+
+    pub(crate) async fn from_service_response<B>(response: ServiceResponse<B>) -> Result<Self> where B: MessageBody {
+        let status = response.status().as_u16();
+        let headers = response.headers().clone();
+        let body = match body::to_bytes(response.into_body()).await {
+            Ok(body) => body,
+            Err(e) => {
+                // body errors only promise a conversion into a boxed error
+                let e: Box<dyn std::error::Error> = e.into();
+                Err(Error::new(format!("cannot read response body: {}", e)))?
+            }
+        };
+        Ok(Self { status, headers, body })
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+
+    pub fn body(&self) -> &[u8] {
+        self.body.as_ref()
+    }
+
+    pub fn text(&self) -> Result<String> {
+        match String::from_utf8(self.body.to_vec()) {
+            Ok(text) => Ok(text),
+            Err(_) => Err(Error::new("response body is not valid UTF-8")),
+        }
+    }
+
+    pub fn json(&self) -> Result<JsonValue> {
+        match serde_json::from_slice(self.body.as_ref()) {
+            Ok(json) => Ok(json),
+            Err(e) => Err(Error::new(format!("response body is not JSON: {}", e))),
+        }
+    }
+}
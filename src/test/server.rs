@@ -0,0 +1,156 @@
+use std::path::Path;
+use std::rc::Rc;
+use actix_http::Request;
+use actix_web::test::{call_service, init_service, TestRequest};
+use educe::Educe;
+use futures_util::future::LocalBoxFuture;
+use serde_json::{Value as JsonValue};
+use teo_parser::diagnostics::diagnostics::Diagnostics;
+use teo_result::{Error, Result};
+use teo_runtime::connection::transaction;
+use teo_runtime::schema::load::load_data_sets::load_data_sets;
+use crate::app::{App, Ctx};
+use crate::app::database::connect_databases;
+use crate::cli::command::SeedCommandAction;
+use crate::dump;
+use crate::migrate::migrate;
+use crate::purge::purge;
+use crate::seeder::seed::seed;
+use crate::server::make::make_server_app;
+use crate::server::panic::install_panic_hook;
+use crate::test::response::TestResponse;
+use crate::{stdlib, webhook};
+
+/// Serves an app in-process for tests.
+///
+/// Requests go through the same service as `serve` without binding a port.
+/// Only one app exists at a time, so tests using a test server should run
+/// serially, e.g. with `#[serial]`. The app is released on drop.
+#[derive(Educe)]
+#[educe(Debug)]
+pub struct TestServer {
+    app: App,
+    #[educe(Debug(ignore))]
+    service: Rc<dyn Fn(Request) -> LocalBoxFuture<'static, Result<TestResponse>>>,
+}
+
+impl TestServer {
+
+    /// Load the schema, connect databases, migrate, seed autoseed data sets
+    /// and run the setup callback.
+    pub async fn new(app: App) -> Result<Self> {
+        app.prepare_for_run().await?;
+        connect_databases(Ctx::main_namespace_mut(), true).await?;
+        install_panic_hook();
+        stdlib::check_models(Ctx::main_namespace())?;
+        migrate(false, false, true).await?;
+        // the service is built once, like a worker of `serve`
+        let service = Rc::new(init_service(make_server_app(Ctx::conn_ctx().namespace(), Ctx::static_files(), Ctx::admin_dashboard())).await);
+        let service = Rc::new(move |request: Request| {
+            let service = service.clone();
+            Box::pin(async move {
+                let response = call_service(service.as_ref(), request).await;
+                TestResponse::from_service_response(response).await
+            }) as LocalBoxFuture<'static, Result<TestResponse>>
+        });
+        let server = Self { app, service };
+        server.seed_autoseed().await?;
+        if let Some(setup) = Ctx::setup() {
+            setup.call(server.transaction_ctx()).await?;
+        }
+        Ok(server)
+    }
+
+    pub fn app(&self) -> &App {
+        &self.app
+    }
+
+    /// A fresh transaction ctx, e.g. to query or prepare records directly.
+    pub fn transaction_ctx(&self) -> transaction::Ctx {
+        transaction::Ctx::new(Ctx::conn_ctx().clone())
+    }
+
+    /// Send a request built with [`TestRequest`].
+    pub async fn call(&self, request: Request) -> Result<TestResponse> {
+        (self.service)(request).await
+    }
+
+    pub async fn get(&self, path: &str) -> Result<TestResponse> {
+        self.call(TestRequest::get().uri(&self.uri(path)).to_request()).await
+    }
+
+    pub async fn post(&self, path: &str, body: JsonValue) -> Result<TestResponse> {
+        self.call(TestRequest::post().uri(&self.uri(path)).set_json(body).to_request()).await
+    }
+
+    /// Call a builtin action or a model handler, e.g.
+    /// `server.action("User", "create", json!({ "create": { ... } }))`.
+    pub async fn action(&self, model: &str, action: &str, body: JsonValue) -> Result<TestResponse> {
+        self.post(&format!("/{}/{}", model.replace('.', "/"), action), body).await
+    }
+
+    /// Send the due webhook deliveries now instead of waiting for the
+    /// worker. Returns the number of deliveries attempted.
+    pub async fn deliver_webhooks(&self) -> Result<usize> {
+        webhook::worker::deliver_due(&reqwest::Client::new()).await
+    }
+
+    /// Delete every record and seed autoseed data sets again.
+    pub async fn reset(&self) -> Result<()> {
+        self.purge().await?;
+        self.seed_autoseed().await
+    }
+
+    /// Delete every record of every connector.
+    pub async fn purge(&self) -> Result<()> {
+        purge().await
+    }
+
+    /// Write every record into an archive like `cargo teo dump`.
+    pub async fn dump(&self, path: impl AsRef<Path>) -> Result<()> {
+        let Some(path) = path.as_ref().to_str() else {
+            Err(Error::new("dump path is not valid unicode"))?
+        };
+        dump::snapshot(&dump::models(None, None)?, path, true).await
+    }
+
+    /// Replace every record with the records of an archive, like `cargo teo
+    /// restore --purge`.
+    pub async fn restore(&self, path: impl AsRef<Path>) -> Result<()> {
+        let Some(path) = path.as_ref().to_str() else {
+            Err(Error::new("dump path is not valid unicode"))?
+        };
+        dump::restore_archive(path, true, true).await
+    }
+
+    /// Seed data sets by name.
+    pub async fn seed(&self, names: &[&str]) -> Result<()> {
+        let names = names.iter().map(|n| n.to_string()).collect::<Vec<String>>();
+        let mut diagnostics = Diagnostics::new();
+        let data_sets = load_data_sets(Ctx::main_namespace(), Some(&names), false, Ctx::schema(), &mut diagnostics)?;
+        seed(SeedCommandAction::Seed, data_sets, self.transaction_ctx()).await
+    }
+
+    async fn seed_autoseed(&self) -> Result<()> {
+        if Ctx::main_namespace().database.is_some() {
+            let mut diagnostics = Diagnostics::new();
+            let data_sets = load_data_sets(Ctx::main_namespace(), None, false, Ctx::schema(), &mut diagnostics)?;
+            seed(SeedCommandAction::Seed, data_sets, self.transaction_ctx()).await?;
+        }
+        Ok(())
+    }
+
+    fn uri(&self, path: &str) -> String {
+        let path_prefix = Ctx::main_namespace().server.as_ref().and_then(|s| s.path_prefix.as_ref());
+        match path_prefix {
+            Some(path_prefix) => format!("/{}/{}", path_prefix.trim_matches('/'), path.trim_start_matches('/')),
+            None => format!("/{}", path.trim_start_matches('/')),
+        }
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = Ctx::drop();
+    }
+}
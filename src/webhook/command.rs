@@ -0,0 +1,56 @@
+use teo_result::Result;
+use teo_runtime::connection::transaction;
+use teo_runtime::teon;
+use crate::app::Ctx;
+use crate::cli::command::WebhooksCommand;
+use crate::message::info_message;
+use crate::webhook::models::webhook_delivery::WebhookDelivery;
+use crate::webhook::worker::{replay, Replayed};
+
+pub(crate) async fn webhooks(command: &WebhooksCommand) -> Result<()> {
+    match command {
+        WebhooksCommand::List(list_command) => {
+            let ctx = transaction::Ctx::new(Ctx::conn_ctx().clone());
+            let mut query = teon!({
+                "orderBy": { "createdAt": "desc" },
+                "take": list_command.limit as i32,
+            });
+            if let Some(status) = &list_command.status {
+                query.as_dictionary_mut().unwrap().insert("where".to_owned(), teon!({ "status": status.as_str() }));
+            }
+            let deliveries = WebhookDelivery::find_many(query, ctx).await?;
+            println!("+-{:<36}-+-{:<16}-+-{:<24}-+-{:<9}-+-{:<8}-+-{:<32}-+", "-".repeat(36), "-".repeat(16), "-".repeat(24), "-".repeat(9), "-".repeat(8), "-".repeat(32));
+            println!("| {:^36} | {:^16} | {:^24} | {:^9} | {:^8} | {:^32} |", "Id", "Webhook", "Event", "Status", "Attempts", "Last error");
+            println!("+-{:<36}-+-{:<16}-+-{:<24}-+-{:<9}-+-{:<8}-+-{:<32}-+", "-".repeat(36), "-".repeat(16), "-".repeat(24), "-".repeat(9), "-".repeat(8), "-".repeat(32));
+            if deliveries.is_empty() {
+                println!("| {:^140} |", "No deliveries.");
+            } else {
+                for delivery in deliveries {
+                    let event = format!("{}.{}", delivery.model(), delivery.event());
+                    let last_error: String = delivery.last_error().unwrap_or_default().chars().take(32).collect();
+                    println!("| {:<36} | {:<16} | {:<24} | {:<9} | {:<8} | {:<32} |", delivery.id(), delivery.webhook(), event, delivery.status(), delivery.attempts(), last_error);
+                }
+            }
+            println!("+-{:<36}---{:<16}---{:<24}---{:<9}---{:<8}---{:<32}-+", "-".repeat(36), "-".repeat(16), "-".repeat(24), "-".repeat(9), "-".repeat(8), "-".repeat(32));
+        }
+        WebhooksCommand::Replay(replay_command) => {
+            let ids = if let Some(ids) = &replay_command.ids {
+                ids.clone()
+            } else {
+                let ctx = transaction::Ctx::new(Ctx::conn_ctx().clone());
+                WebhookDelivery::find_many(teon!({
+                    "where": { "status": "dead" },
+                    "orderBy": { "createdAt": "asc" },
+                }), ctx).await?.iter().map(|d| d.id()).collect()
+            };
+            for id in ids {
+                match replay(&id).await? {
+                    Replayed::Delivered => info_message(format!("webhook delivery `{}` delivered", id)),
+                    Replayed::Failed => info_message(format!("webhook delivery `{}` failed and is scheduled for retry", id)),
+                    Replayed::Leased => info_message(format!("webhook delivery `{}` is being sent by another worker", id)),
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,94 @@
+pub mod models;
+pub mod outbox;
+pub mod worker;
+pub(crate) mod command;
+
+use teo_result::{Error, Result};
+use teo_runtime::namespace::Namespace;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WebhookEvent {
+    Create,
+    Update,
+    Delete,
+}
+
+impl WebhookEvent {
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::Create => "create",
+            WebhookEvent::Update => "update",
+            WebhookEvent::Delete => "delete",
+        }
+    }
+
+    /// The event triggered by a builtin action.
+    pub(crate) fn from_action_name(name: &str) -> Option<Self> {
+        match name {
+            "create" | "copy" | "createMany" | "copyMany" => Some(WebhookEvent::Create),
+            "update" | "upsert" | "updateMany" => Some(WebhookEvent::Update),
+            "delete" | "deleteMany" => Some(WebhookEvent::Delete),
+            _ => None,
+        }
+    }
+}
+
+/// An outbound webhook.
+///
+/// Payloads are JSON, signed with HMAC-SHA256 over `"{timestamp}.{body}"`
+/// and sent in the `X-Teo-Signature` header as `t={timestamp},v1={hex}`.
+/// Deliveries are stored in `std.WebhookDelivery`, which ships with this
+/// crate, by the callbacks of the subscribed models.
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    pub(crate) name: String,
+    pub(crate) url: String,
+    pub(crate) secret: String,
+    pub(crate) subscriptions: Vec<(String, Vec<WebhookEvent>)>,
+    pub(crate) max_attempts: i32,
+}
+
+impl Webhook {
+
+    pub fn new(name: impl Into<String>, url: impl Into<String>, secret: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            url: url.into(),
+            secret: secret.into(),
+            subscriptions: vec![],
+            max_attempts: 10,
+        }
+    }
+
+    /// Subscribe to events of a model, e.g. `"Order"` or `"shop.Order"`. The
+    /// model records its changes with `@beforeSave($std.webhookBefore)`,
+    /// `@afterSave($std.webhookSave)` and `@afterDelete($std.webhookDelete)`,
+    /// a model can also subscribe to a webhook by name in these callbacks.
+    pub fn subscribe(mut self, model: impl Into<String>, events: impl IntoIterator<Item=WebhookEvent>) -> Self {
+        self.subscriptions.push((model.into(), events.into_iter().collect()));
+        self
+    }
+
+    /// Deliveries failing this many times are dead-lettered.
+    pub fn max_attempts(mut self, max_attempts: i32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    pub(crate) fn subscribes(&self, model_path: &str, event: WebhookEvent) -> bool {
+        self.subscriptions.iter().any(|(model, events)| model == model_path && events.contains(&event))
+    }
+}
+
+/// Make sure the std model backing the outbox is loaded.
+pub(crate) fn check_model(namespace: &Namespace) -> Result<()> {
+    if namespace.model_at_path(&vec!["std", "WebhookDelivery"]).is_none() {
+        Err(Error::new("webhooks require the `std.WebhookDelivery` model"))?
+    }
+    Ok(())
+}
@@ -0,0 +1,2 @@
+pub mod webhook_delivery;
+pub mod webhook_lease;
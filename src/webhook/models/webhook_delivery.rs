@@ -0,0 +1,142 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::borrow::Borrow;
+use chrono::{DateTime, Utc};
+use key_path::path;
+use teo_runtime::connection::transaction;
+use teo_runtime::model;
+use crate::prelude::{Value, Result};
+
+/// Webhook delivery
+pub struct WebhookDelivery {
+    pub(super) inner: model::Object,
+}
+
+impl WebhookDelivery {
+
+    /// Find many webhook deliveries.
+    pub async fn find_many(query: impl Borrow<Value>, ctx: transaction::Ctx) -> Result<Vec<WebhookDelivery>> {
+        let model = ctx.namespace().model_at_path(&vec!["std", "WebhookDelivery"]).unwrap();
+        Ok(ctx.find_many(model, query.borrow(), None, path![]).await?)
+    }
+
+    /// Find a unique webhook delivery.
+    pub async fn find_unique(query: impl Borrow<Value>, ctx: transaction::Ctx) -> Result<Option<WebhookDelivery>> {
+        let model = ctx.namespace().model_at_path(&vec!["std", "WebhookDelivery"]).unwrap();
+        Ok(ctx.find_unique(model, query.borrow(), None, path![]).await?)
+    }
+
+    /// Create a new webhook delivery.
+    pub async fn new(values: impl Borrow<Value>, ctx: transaction::Ctx) -> Result<Self> {
+        let model = ctx.namespace().model_at_path(&vec!["std", "WebhookDelivery"]).unwrap();
+        Ok(ctx.create_object(model, values.borrow(), None).await?.into())
+    }
+
+    /// Save this webhook delivery.
+    pub async fn save(&self) -> Result<()> {
+        self.inner.save().await
+    }
+
+    /// Delete this webhook delivery.
+    pub async fn delete(&self) -> Result<()> {
+        self.inner.delete().await
+    }
+
+    /// Id
+    pub fn id(&self) -> String {
+        self.inner.get("id").unwrap()
+    }
+
+    /// Webhook
+    pub fn webhook(&self) -> String {
+        self.inner.get("webhook").unwrap()
+    }
+
+    /// Event
+    pub fn event(&self) -> String {
+        self.inner.get("event").unwrap()
+    }
+
+    /// Model
+    pub fn model(&self) -> String {
+        self.inner.get("model").unwrap()
+    }
+
+    /// Payload
+    pub fn payload(&self) -> String {
+        self.inner.get("payload").unwrap()
+    }
+
+    /// Status
+    pub fn status(&self) -> String {
+        self.inner.get("status").unwrap()
+    }
+
+    pub fn set_status(&self, new_value: impl Into<String>) {
+        self.inner.set("status", new_value.into()).unwrap();
+    }
+
+    /// Attempts
+    pub fn attempts(&self) -> i32 {
+        self.inner.get("attempts").unwrap()
+    }
+
+    pub fn set_attempts(&self, new_value: i32) {
+        self.inner.set("attempts", new_value).unwrap();
+    }
+
+    /// Next attempt at
+    pub fn next_attempt_at(&self) -> DateTime<Utc> {
+        self.inner.get("nextAttemptAt").unwrap()
+    }
+
+    pub fn set_next_attempt_at(&self, new_value: DateTime<Utc>) {
+        self.inner.set("nextAttemptAt", new_value).unwrap();
+    }
+
+    /// Last error
+    pub fn last_error(&self) -> Option<String> {
+        self.inner.get("lastError").unwrap()
+    }
+
+    pub fn set_last_error(&self, new_value: Option<String>) {
+        self.inner.set("lastError", new_value).unwrap();
+    }
+
+    /// Created at
+    pub fn created_at(&self) -> DateTime<Utc> {
+        self.inner.get("createdAt").unwrap()
+    }
+
+    /// Delivered at
+    pub fn delivered_at(&self) -> Option<DateTime<Utc>> {
+        self.inner.get("deliveredAt").unwrap()
+    }
+
+    pub fn set_delivered_at(&self, new_value: Option<DateTime<Utc>>) {
+        self.inner.set("deliveredAt", new_value).unwrap();
+    }
+}
+
+impl Into<model::Object> for WebhookDelivery {
+    fn into(self) -> model::Object {
+        self.inner.clone()
+    }
+}
+
+impl From<model::Object> for WebhookDelivery {
+    fn from(value: model::Object) -> Self {
+        Self { inner: value }
+    }
+}
+
+impl Debug for WebhookDelivery {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.inner, f)
+    }
+}
+
+impl Display for WebhookDelivery {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.inner, f)
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt::{Debug, Display, Formatter};
+use std::borrow::Borrow;
+use chrono::{DateTime, Utc};
+use key_path::path;
+use teo_runtime::connection::transaction;
+use teo_runtime::model;
+use crate::prelude::{Value, Result};
+
+/// Webhook lease
+pub struct WebhookLease {
+    pub(super) inner: model::Object,
+}
+
+impl WebhookLease {
+
+    /// Find a unique webhook lease.
+    pub async fn find_unique(query: impl Borrow<Value>, ctx: transaction::Ctx) -> Result<Option<WebhookLease>> {
+        let model = ctx.namespace().model_at_path(&vec!["std", "WebhookLease"]).unwrap();
+        Ok(ctx.find_unique(model, query.borrow(), None, path![]).await?)
+    }
+
+    /// Find many webhook leases.
+    pub async fn find_many(query: impl Borrow<Value>, ctx: transaction::Ctx) -> Result<Vec<WebhookLease>> {
+        let model = ctx.namespace().model_at_path(&vec!["std", "WebhookLease"]).unwrap();
+        Ok(ctx.find_many(model, query.borrow(), None, path![]).await?)
+    }
+
+    /// Create a new webhook lease.
+    pub async fn new(values: impl Borrow<Value>, ctx: transaction::Ctx) -> Result<Self> {
+        let model = ctx.namespace().model_at_path(&vec!["std", "WebhookLease"]).unwrap();
+        Ok(ctx.create_object(model, values.borrow(), None).await?.into())
+    }
+
+    /// Save this webhook lease.
+    pub async fn save(&self) -> Result<()> {
+        self.inner.save().await
+    }
+
+    /// Delete this webhook lease.
+    pub async fn delete(&self) -> Result<()> {
+        self.inner.delete().await
+    }
+
+    /// Id
+    pub fn id(&self) -> String {
+        self.inner.get("id").unwrap()
+    }
+
+    /// Expires at
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.inner.get("expiresAt").unwrap()
+    }
+}
+
+impl Into<model::Object> for WebhookLease {
+    fn into(self) -> model::Object {
+        self.inner.clone()
+    }
+}
+
+impl From<model::Object> for WebhookLease {
+    fn from(value: model::Object) -> Self {
+        Self { inner: value }
+    }
+}
+
+impl Debug for WebhookLease {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.inner, f)
+    }
+}
+
+impl Display for WebhookLease {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.inner, f)
+    }
+}
@@ -0,0 +1,112 @@
+use chrono::Utc;
+use serde_json::{json, Map, Value as JsonValue};
+use teo_result::{Error, Result};
+use teo_runtime::arguments::Arguments;
+use teo_runtime::connection::transaction;
+use teo_runtime::model::Object;
+use teo_runtime::namespace::Namespace;
+use teo_runtime::teon;
+use teo_runtime::traits::named::Named;
+use crate::app::Ctx;
+use crate::stdlib::before_save::BeforeSave;
+use crate::webhook::{Webhook, WebhookEvent};
+use crate::webhook::models::webhook_delivery::WebhookDelivery;
+
+/// Store a delivery for every webhook subscribed to `event` on `model_path`.
+/// Deliveries are sent by the background worker. Pass the transaction ctx
+/// of the change, so that the delivery is only stored with it.
+pub async fn enqueue(model_path: &str, event: WebhookEvent, data: &JsonValue, ctx: transaction::Ctx) -> Result<()> {
+    for webhook in Ctx::webhooks().iter().filter(|w| w.subscribes(model_path, event)) {
+        enqueue_for(webhook, model_path, event, data, ctx.clone()).await?;
+    }
+    Ok(())
+}
+
+async fn enqueue_for(webhook: &Webhook, model_path: &str, event: WebhookEvent, data: &JsonValue, ctx: transaction::Ctx) -> Result<()> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let now = Utc::now();
+    let payload = json!({
+        "id": id.as_str(),
+        "webhook": webhook.name.as_str(),
+        "event": event.as_str(),
+        "model": model_path,
+        "data": data,
+        "createdAt": now.to_rfc3339(),
+    });
+    let delivery = WebhookDelivery::new(teon!({
+        "id": id.as_str(),
+        "webhook": webhook.name.as_str(),
+        "event": event.as_str(),
+        "model": model_path,
+        "payload": payload.to_string(),
+        "status": "pending",
+        "attempts": 0,
+        "nextAttemptAt": now,
+        "createdAt": now,
+    }), ctx).await?;
+    delivery.save().await
+}
+
+/// Whether saved objects were new, read by `webhookBefore`. Objects are no
+/// longer new once `@afterSave` callbacks run.
+static NEW: BeforeSave<bool> = BeforeSave::new();
+
+/// Implement `webhookBefore`, `webhookSave` and `webhookDelete`. Models
+/// subscribe to webhooks in the schema by declaring them in their
+/// callbacks, e.g.
+///
+/// ```teo
+/// @beforeSave($std.webhookBefore)
+/// @afterSave($std.webhookSave("orders"))
+/// @afterDelete($std.webhookDelete("orders"))
+/// model Order { ... }
+/// ```
+///
+/// Without a name, the webhooks subscribing to the model with
+/// [`Webhook::subscribe`] are used. Callbacks run in the transaction of the
+/// write, a delivery is stored if and only if the change is, and a failure
+/// to store it fails the write. Builtin actions, nested writes and saves of
+/// programs are all covered.
+pub(crate) fn define_pipeline_items(namespace: &mut Namespace) {
+    namespace.define_callback_pipeline_item("webhookBefore", |_args: Arguments, object: Object| async move {
+        NEW.insert(&object, object.is_new());
+        Ok(())
+    });
+    namespace.define_callback_pipeline_item("webhookSave", |args: Arguments, object: Object| async move {
+        let event = match NEW.take(&object) {
+            Some(true) => WebhookEvent::Create,
+            Some(false) => WebhookEvent::Update,
+            None => Err(Error::new(format!("`webhookSave` of `{}` requires `@beforeSave($std.webhookBefore)`", object.model().path().join("."))))?,
+        };
+        record(&args, &object, event).await
+    });
+    namespace.define_callback_pipeline_item("webhookDelete", |args: Arguments, object: Object| async move {
+        record(&args, &object, WebhookEvent::Delete).await
+    });
+}
+
+async fn record(args: &Arguments, object: &Object, event: WebhookEvent) -> Result<()> {
+    let model_path = object.model().path().join(".");
+    let data = snapshot(object);
+    let ctx = object.transaction_ctx();
+    let name: Option<String> = args.get_optional("name")?;
+    match name {
+        Some(name) => {
+            let Some(webhook) = Ctx::webhooks().iter().find(|w| w.name == name) else {
+                Err(Error::new(format!("webhook `{}` of `{}` is not configured", name, model_path)))?
+            };
+            enqueue_for(webhook, &model_path, event, &data, ctx).await
+        }
+        None => enqueue(&model_path, event, &data, ctx).await,
+    }
+}
+
+fn snapshot(object: &Object) -> JsonValue {
+    let mut result = Map::new();
+    for field in object.model().fields.values() {
+        if let Ok(value) = object.get_value(field.name()) {
+            result.insert(field.name().to_owned(), JsonValue::try_from(&value).unwrap_or(JsonValue::Null));
+        }
+    }
+    JsonValue::Object(result)
+}
@@ -0,0 +1,187 @@
+use std::time::Duration;
+use chrono::Utc;
+use ring::hmac;
+use teo_result::{Error, Result};
+use teo_runtime::connection::transaction;
+use teo_runtime::teon;
+use crate::app::Ctx;
+use crate::message::{error_message, info_message};
+use crate::server::watch::RELOAD_LOCK;
+use crate::webhook::models::webhook_delivery::WebhookDelivery;
+use crate::webhook::models::webhook_lease::WebhookLease;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+const BATCH_SIZE: i32 = 50;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Longer than an attempt can take.
+const LEASE_DURATION: Duration = Duration::from_secs(60);
+
+/// Deliver due webhook deliveries in the background until the process exits.
+pub(crate) fn spawn(silent: bool) {
+    if !silent {
+        info_message(format!("webhook worker started for {} webhook(s)", Ctx::webhooks().len()));
+    }
+    tokio::spawn(async move {
+        let client = reqwest::Client::new();
+        loop {
+            let guard = RELOAD_LOCK.read().await;
+            if let Err(error) = deliver_due(&client).await {
+                error_message(format!("webhook worker: {}", error));
+            }
+            drop(guard);
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+/// Send the due deliveries, each by one worker. Returns the number of
+/// deliveries attempted.
+pub(crate) async fn deliver_due(client: &reqwest::Client) -> Result<usize> {
+    let ctx = transaction::Ctx::new(Ctx::conn_ctx().clone());
+    let deliveries = WebhookDelivery::find_many(teon!({
+        "where": {
+            "status": "pending",
+            "nextAttemptAt": { "lte": Utc::now() },
+        },
+        "orderBy": { "createdAt": "asc" },
+        "take": BATCH_SIZE,
+    }), ctx.clone()).await?;
+    let mut attempted = 0;
+    for delivery in deliveries {
+        // one failing delivery doesn't hold up the others
+        match deliver_claimed(client, &delivery, ctx.clone()).await {
+            Ok(Some(_)) => attempted += 1,
+            Ok(None) => (),
+            Err(error) => error_message(format!("webhook delivery `{}`: {}", delivery.id(), error)),
+        }
+    }
+    Ok(attempted)
+}
+
+/// Deliver if this worker claims the attempt, `None` if it doesn't. Other
+/// workers skip the attempt until the lease expires.
+async fn deliver_claimed(client: &reqwest::Client, delivery: &WebhookDelivery, ctx: transaction::Ctx) -> Result<Option<bool>> {
+    let Some(lease) = claim(delivery, ctx.clone()).await? else {
+        return Ok(None);
+    };
+    // another worker may have finished the attempt before the claim
+    let current = WebhookDelivery::find_unique(teon!({ "where": { "id": delivery.id().as_str() } }), ctx).await?;
+    let result = match current {
+        Some(current) if current.status() == "pending" && current.attempts() == delivery.attempts() => deliver(client, &current).await.map(Some),
+        _ => Ok(None),
+    };
+    lease.delete().await?;
+    result
+}
+
+/// Create the lease of the current attempt, or `None` if another worker
+/// holds it.
+async fn claim(delivery: &WebhookDelivery, ctx: transaction::Ctx) -> Result<Option<WebhookLease>> {
+    let id = format!("{}:{}", delivery.id(), delivery.attempts());
+    if let Some(existing) = WebhookLease::find_unique(teon!({ "where": { "id": id.as_str() } }), ctx.clone()).await? {
+        if existing.expires_at() > Utc::now() {
+            return Ok(None);
+        }
+        // left by a stopped worker, whoever creates the new lease wins
+        existing.delete().await?;
+    }
+    let lease = WebhookLease::new(teon!({
+        "id": id.as_str(),
+        "expiresAt": Utc::now() + chrono::Duration::from_std(LEASE_DURATION).unwrap(),
+    }), ctx.clone()).await?;
+    match lease.save().await {
+        Ok(()) => Ok(Some(lease)),
+        // the id is unique, another worker claimed the attempt first
+        Err(error) => match WebhookLease::find_unique(teon!({ "where": { "id": id.as_str() } }), ctx).await? {
+            Some(_) => Ok(None),
+            None => Err(Error::new(format!("cannot claim delivery: {}", error))),
+        },
+    }
+}
+
+/// Send a delivery once and record the outcome. Failures are retried with
+/// exponential backoff and dead-lettered after the webhook's max attempts.
+async fn deliver(client: &reqwest::Client, delivery: &WebhookDelivery) -> Result<bool> {
+    let Some(webhook) = Ctx::webhooks().iter().find(|w| w.name == delivery.webhook()) else {
+        delivery.set_status("dead");
+        delivery.set_last_error(Some(format!("webhook `{}` is not configured", delivery.webhook())));
+        delivery.save().await?;
+        return Ok(false);
+    };
+    let payload = delivery.payload();
+    let timestamp = Utc::now().timestamp();
+    let result = client.post(webhook.url.as_str())
+        .header("content-type", "application/json")
+        .header("x-teo-webhook-id", delivery.id())
+        .header("x-teo-signature", format!("t={},v1={}", timestamp, sign(&webhook.secret, timestamp, &payload)))
+        .timeout(REQUEST_TIMEOUT)
+        .body(payload)
+        .send()
+        .await;
+    let failure = match result {
+        Ok(response) if response.status().is_success() => None,
+        Ok(response) => Some(format!("responded with {}", response.status())),
+        Err(error) => Some(error.to_string()),
+    };
+    let attempts = delivery.attempts() + 1;
+    delivery.set_attempts(attempts);
+    let delivered = failure.is_none();
+    match failure {
+        None => {
+            delivery.set_status("delivered");
+            delivery.set_delivered_at(Some(Utc::now()));
+            delivery.set_last_error(None);
+        }
+        Some(failure) => {
+            if attempts >= webhook.max_attempts {
+                delivery.set_status("dead");
+            } else {
+                delivery.set_next_attempt_at(Utc::now() + backoff(attempts));
+            }
+            delivery.set_last_error(Some(failure));
+        }
+    }
+    delivery.save().await?;
+    Ok(delivered)
+}
+
+/// What became of a replayed delivery.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Replayed {
+    Delivered,
+    /// Failed again and scheduled for a retry.
+    Failed,
+    /// Another worker holds the lease and sends it.
+    Leased,
+}
+
+/// Put a delivery back into the queue and send it right away, unless a
+/// worker picks it up first.
+pub(crate) async fn replay(id: &str) -> Result<Replayed> {
+    let ctx = transaction::Ctx::new(Ctx::conn_ctx().clone());
+    let Some(delivery) = WebhookDelivery::find_unique(teon!({
+        "where": { "id": id }
+    }), ctx.clone()).await? else {
+        Err(Error::new(format!("webhook delivery `{}` is not found", id)))?
+    };
+    delivery.set_status("pending");
+    delivery.set_attempts(0);
+    delivery.set_next_attempt_at(Utc::now());
+    delivery.save().await?;
+    Ok(match deliver_claimed(&reqwest::Client::new(), &delivery, ctx).await? {
+        Some(true) => Replayed::Delivered,
+        Some(false) => Replayed::Failed,
+        None => Replayed::Leased,
+    })
+}
+
+fn backoff(attempts: i32) -> chrono::Duration {
+    let seconds = 10i64.saturating_mul(1i64 << attempts.clamp(0, 12));
+    chrono::Duration::seconds(seconds.min(60 * 60))
+}
+
+fn sign(secret: &str, timestamp: i64, payload: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = hmac::sign(&key, format!("{}.{}", timestamp, payload).as_bytes());
+    tag.as_ref().iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -1,36 +1,27 @@
-use test_helpers::*;
-
-#[before_all]
-#[after_all]
+#[cfg(test)]
 mod test {
-    use std::sync::Mutex;
+    use std::path::Path;
     use serde_json::{json};
-    use crate::lib::{ExecutionHandle, req};
-    
+    use serial_test::serial;
+    use teo::prelude::App;
+    use teo::test::TestServer;
     use crate::{assert_json, matcher};
-    use once_cell::sync::Lazy;
-
-    static HANDLE: Lazy<Mutex<ExecutionHandle>> = Lazy::new(|| {
-        Mutex::new(ExecutionHandle::new())
-    });
-    static PORT: i32 = 4019;
-
-    fn before_all() {
-        HANDLE.lock().unwrap().execute(file!(), "serve");
-    }
 
-    fn after_all() {
-        HANDLE.lock().unwrap().exit();
+    async fn test_server() -> TestServer {
+        let schema = Path::new(file!()).parent().unwrap().join("schema.teo");
+        TestServer::new(App::new_with_schema_path(schema).unwrap()).await.unwrap()
     }
 
-    #[test]
-    fn create() {
-        let res = req(PORT, "create", "Support", json!({
+    #[serial]
+    #[actix_web::test]
+    async fn create() {
+        let server = test_server().await;
+        let res = server.action("Support", "create", json!({
             "create": {
                 "string": "vavotitsiangvuntiu",
                 "int": 123456,
             },
-        }));
+        })).await.unwrap().json().unwrap();
         assert_json!(res, matcher!({
             "data": {
                 "id": ignore,
@@ -40,6 +31,18 @@ mod test {
         }));
     }
 
+    #[serial]
+    #[actix_web::test]
+    async fn reset_removes_records() {
+        let server = test_server().await;
+        server.action("Support", "create", json!({
+            "create": { "int": 1 },
+        })).await.unwrap();
+        server.reset().await.unwrap();
+        let res = server.action("Support", "findMany", json!({})).await.unwrap().json().unwrap();
+        assert_json!(res.get("meta").unwrap(), matcher!({ "count": 0 }));
+    }
+
     #[test]
     fn update() {
 
@@ -0,0 +1,85 @@
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use serde_json::{json, Value};
+    use serial_test::serial;
+    use teo::audit::models::audit_log::AuditLog;
+    use teo::prelude::{App, Audit, teon};
+    use teo::test::{TestRequest, TestServer};
+
+    async fn test_server() -> TestServer {
+        let schema = Path::new(file!()).parent().unwrap().join("schema.teo");
+        let app = App::new_with_schema_path(schema).unwrap();
+        app.audit(Audit::new().exclude_field("Post", "secret"));
+        TestServer::new(app).await.unwrap()
+    }
+
+    async fn logs(server: &TestServer, model: &str) -> Vec<(String, Value)> {
+        let logs = AuditLog::find_many(teon!({
+            "where": { "model": model },
+            "orderBy": { "createdAt": "asc" },
+        }), server.transaction_ctx()).await.unwrap();
+        logs.iter().map(|log| (log.action(), serde_json::from_str(&log.changes()).unwrap())).collect()
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn records_create_update_and_delete() {
+        let server = test_server().await;
+        let created = server.action("Post", "create", json!({ "create": { "title": "Draft" } })).await.unwrap().json().unwrap();
+        let id = created["data"]["id"].clone();
+        server.action("Post", "update", json!({ "where": { "id": id }, "update": { "title": "Final" } })).await.unwrap();
+        server.action("Post", "delete", json!({ "where": { "id": id } })).await.unwrap();
+        let logs = logs(&server, "Post").await;
+        let actions = logs.iter().map(|(action, _)| action.as_str()).collect::<Vec<&str>>();
+        assert_eq!(actions, vec!["create", "update", "delete"]);
+        assert_eq!(logs[1].1, json!({ "title": { "before": "Draft", "after": "Final" } }));
+        assert_eq!(logs[2].1["title"], json!({ "before": "Final", "after": null }));
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn records_each_record_of_update_many() {
+        let server = test_server().await;
+        server.action("Post", "createMany", json!({ "create": [{ "title": "A" }, { "title": "B" }] })).await.unwrap();
+        server.action("Post", "updateMany", json!({ "where": {}, "update": { "title": "C" } })).await.unwrap();
+        let updates = logs(&server, "Post").await.into_iter().filter(|(action, _)| action == "update").collect::<Vec<(String, Value)>>();
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].1["title"]["after"], json!("C"));
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn records_nested_writes() {
+        let server = test_server().await;
+        server.action("Author", "create", json!({
+            "create": { "name": "Ann", "posts": { "create": [{ "title": "Nested" }] } }
+        })).await.unwrap();
+        assert_eq!(logs(&server, "Author").await.len(), 1);
+        let posts = logs(&server, "Post").await;
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].1["title"], json!({ "before": null, "after": "Nested" }));
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn redacts_excluded_fields() {
+        let server = test_server().await;
+        server.action("Post", "create", json!({ "create": { "title": "T", "secret": "s3cret" } })).await.unwrap();
+        let logs = logs(&server, "Post").await;
+        assert_eq!(logs[0].1["secret"], json!({ "before": "[redacted]", "after": "[redacted]" }));
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn records_request_id() {
+        let server = test_server().await;
+        let request = TestRequest::post().uri("/Post/create")
+            .insert_header(("x-request-id", "req-1"))
+            .set_json(json!({ "create": { "title": "T" } }))
+            .to_request();
+        server.call(request).await.unwrap();
+        let logs = AuditLog::find_many(teon!({}), server.transaction_ctx()).await.unwrap();
+        assert_eq!(logs[0].request_id(), Some("req-1".to_owned()));
+    }
+}
@@ -0,0 +1,49 @@
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use std::time::Duration;
+    use serde_json::{json, Value};
+    use serial_test::serial;
+    use teo::prelude::App;
+    use teo::test::TestServer;
+
+    async fn test_server() -> TestServer {
+        let schema = Path::new(file!()).parent().unwrap().join("schema.teo");
+        let app = App::new_with_schema_path(schema).unwrap();
+        TestServer::new(app).await.unwrap()
+    }
+
+    async fn find_many(server: &TestServer, model: &str) -> Value {
+        server.action(model, "findMany", json!({ "orderBy": { "id": "asc" } })).await.unwrap().json().unwrap()["data"].clone()
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn restores_dumped_records_as_they_are() {
+        let server = test_server().await;
+        let ann = server.action("Author", "create", json!({ "create": { "name": "Ann" } })).await.unwrap().json().unwrap();
+        let ann_id = ann["data"]["id"].clone();
+        server.action("Author", "create", json!({
+            "create": {
+                "name": "Bob",
+                "mentor": { "connect": { "id": ann_id } },
+                "posts": { "create": [{ "title": "First" }, { "title": "Second" }] },
+            }
+        })).await.unwrap();
+        // Ann's mentor is created after her, restoring needs a second pass
+        let cid = server.action("Author", "create", json!({ "create": { "name": "Cid" } })).await.unwrap().json().unwrap()["data"]["id"].clone();
+        server.action("Author", "update", json!({ "where": { "id": ann_id }, "update": { "mentor": { "connect": { "id": cid } } } })).await.unwrap();
+        let authors = find_many(&server, "Author").await;
+        let posts = find_many(&server, "Post").await;
+        let path = std::env::temp_dir().join(format!("teo-dump-{}.ndjson", std::process::id()));
+        server.dump(&path).await.unwrap();
+        // `@updatedAt` would change if the records were saved again
+        actix_web::rt::time::sleep(Duration::from_millis(20)).await;
+        server.restore(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(find_many(&server, "Author").await, authors);
+        assert_eq!(find_many(&server, "Post").await, posts);
+        let created = server.action("Author", "create", json!({ "create": { "name": "Dee" } })).await.unwrap().json().unwrap();
+        assert_eq!(created["data"]["id"], json!(4));
+    }
+}
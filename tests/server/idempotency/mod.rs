@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use std::time::Duration;
+    use serde_json::{json, Value};
+    use serial_test::serial;
+    use teo::prelude::{App, Idempotency};
+    use teo::test::{TestRequest, TestResponse, TestServer};
+
+    async fn test_server() -> TestServer {
+        let schema = Path::new(file!()).parent().unwrap().join("schema.teo");
+        let app = App::new_with_schema_path(schema).unwrap();
+        app.idempotency(Idempotency::new());
+        TestServer::new(app).await.unwrap()
+    }
+
+    async fn create(server: &TestServer, key: &str, authorization: Option<&str>, body: Value) -> TestResponse {
+        let mut request = TestRequest::post().uri("/Support/create").insert_header(("idempotency-key", key)).set_json(body);
+        if let Some(authorization) = authorization {
+            request = request.insert_header(("authorization", authorization));
+        }
+        server.call(request.to_request()).await.unwrap()
+    }
+
+    async fn count(server: &TestServer) -> Value {
+        server.action("Support", "count", json!({})).await.unwrap().json().unwrap()["data"].clone()
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn replays_stored_response() {
+        let server = test_server().await;
+        let body = json!({ "create": { "string": "once" } });
+        let first = create(&server, "a", None, body.clone()).await;
+        assert_eq!(first.status(), 200);
+        assert_eq!(first.header("idempotent-replayed"), None);
+        let second = create(&server, "a", None, body).await;
+        assert_eq!(second.status(), 200);
+        assert_eq!(second.header("idempotent-replayed"), Some("true"));
+        assert_eq!(second.json().unwrap(), first.json().unwrap());
+        assert_eq!(count(&server).await, json!(1));
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn rejects_key_reused_for_different_request() {
+        let server = test_server().await;
+        assert_eq!(create(&server, "b", None, json!({ "create": { "string": "one" } })).await.status(), 200);
+        assert_eq!(create(&server, "b", None, json!({ "create": { "string": "two" } })).await.status(), 422);
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn scopes_keys_to_caller() {
+        let server = test_server().await;
+        let body = json!({ "create": { "string": "scoped" } });
+        assert_eq!(create(&server, "c", Some("Bearer alice"), body.clone()).await.status(), 200);
+        let other = create(&server, "c", Some("Bearer bob"), body).await;
+        assert_eq!(other.status(), 200);
+        assert_eq!(other.header("idempotent-replayed"), None);
+        assert_eq!(count(&server).await, json!(2));
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn releases_key_of_failed_request() {
+        let server = test_server().await;
+        assert_eq!(create(&server, "d", None, json!({ "create": { "string": 1 } })).await.status(), 400);
+        // the key is released in the background
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(create(&server, "d", None, json!({ "create": { "string": "retried" } })).await.status(), 200);
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn hides_std_model_from_requests() {
+        let server = test_server().await;
+        assert_eq!(server.action("std.IdempotencyRecord", "findMany", json!({})).await.unwrap().status(), 404);
+    }
+}
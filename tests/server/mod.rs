@@ -1 +1,7 @@
 pub mod actions;
+pub mod audit;
+pub mod dump;
+pub mod idempotency;
+pub mod panic;
+pub mod static_files;
+pub mod webhooks;
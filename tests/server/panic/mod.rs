@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+    use serial_test::serial;
+    use teo::prelude::{App, PanicReport, Request, Response, Result};
+    use teo::test::{TestRequest, TestServer};
+
+    async fn boom(_request: Request) -> Result<Response> {
+        panic!("boom")
+    }
+
+    async fn fine(_request: Request) -> Result<Response> {
+        Ok(Response::string("fine", "text/plain"))
+    }
+
+    async fn test_server(reports: Arc<Mutex<Vec<PanicReport>>>) -> TestServer {
+        let schema = Path::new(file!()).parent().unwrap().join("schema.teo");
+        let app = App::new_with_schema_path(schema).unwrap();
+        app.main_namespace_mut().define_handler("boom", boom);
+        app.main_namespace_mut().define_handler("fine", fine);
+        app.on_panic(move |report| reports.lock().unwrap().push(report.clone()));
+        TestServer::new(app).await.unwrap()
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn reports_panicking_handler() {
+        let reports = Arc::new(Mutex::new(vec![]));
+        let server = test_server(reports.clone()).await;
+        let res = server.call(TestRequest::get().uri("/boom").insert_header(("x-request-id", "panicking")).to_request()).await.unwrap();
+        assert_eq!(res.status(), 500);
+        assert_eq!(res.header("x-request-id"), Some("panicking"));
+        let reports = reports.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].request_id, "panicking");
+        assert_eq!(reports[0].method, "GET");
+        assert_eq!(reports[0].path, "/boom");
+        assert!(reports[0].message.starts_with("boom at "));
+        assert!(!reports[0].backtrace.is_empty());
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn keeps_serving_after_panic() {
+        let reports = Arc::new(Mutex::new(vec![]));
+        let server = test_server(reports.clone()).await;
+        assert_eq!(server.get("/boom").await.unwrap().status(), 500);
+        let res = server.get("/fine").await.unwrap();
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.text().unwrap(), "fine");
+        assert_eq!(reports.lock().unwrap().len(), 1);
+    }
+}
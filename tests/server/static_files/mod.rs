@@ -0,0 +1,97 @@
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use serial_test::serial;
+    use teo::prelude::{App, serve_static_files, StaticFiles};
+    use teo::test::{TestRequest, TestServer};
+
+    fn public() -> String {
+        Path::new(file!()).parent().unwrap().join("public").to_str().unwrap().to_owned()
+    }
+
+    async fn test_server() -> TestServer {
+        let schema = Path::new(file!()).parent().unwrap().join("schema.teo");
+        let app = App::new_with_schema_path(schema).unwrap();
+        app.static_files("/static", StaticFiles::new(public()).cache_control(r"\.css$", "public, max-age=31536000").unwrap());
+        TestServer::new(app).await.unwrap()
+    }
+
+    #[test]
+    fn serves_existing_file() {
+        assert!(serve_static_files(public(), "app.css").is_ok());
+    }
+
+    #[test]
+    fn resolves_directory_index() {
+        assert!(StaticFiles::new(public()).serve("/docs/", None).is_ok());
+        assert!(StaticFiles::new(public()).index(None::<String>).serve("/docs/", None).is_err());
+    }
+
+    #[test]
+    fn refuses_path_traversal() {
+        assert!(serve_static_files(public(), "../mod.rs").is_err());
+        assert!(serve_static_files(public(), "docs/../../mod.rs").is_err());
+        assert!(serve_static_files(public(), "%2e%2e/mod.rs").is_err());
+        assert!(serve_static_files(public(), "..%5cmod.rs").is_err());
+    }
+
+    #[test]
+    fn falls_back_for_single_page_apps() {
+        assert!(StaticFiles::new(public()).serve("/some/client/route", None).is_err());
+        assert!(StaticFiles::new(public()).fallback("index.html").serve("/some/client/route", None).is_ok());
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn serves_precompressed_variant_as_original_file() {
+        let server = test_server().await;
+        let res = server.call(TestRequest::get().uri("/static/index.html").insert_header(("accept-encoding", "br;q=0, gzip")).to_request()).await.unwrap();
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.header("content-encoding"), Some("gzip"));
+        assert!(res.header("content-type").unwrap().starts_with("text/html"));
+        assert_eq!(res.header("content-disposition"), Some("inline; filename=\"index.html\""));
+        assert_eq!(res.header("vary"), Some("accept-encoding"));
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn serves_original_file_without_accepted_encoding() {
+        let server = test_server().await;
+        let res = server.get("/static/index.html").await.unwrap();
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.header("content-encoding"), None);
+        assert_eq!(res.text().unwrap().trim(), "<h1>Home</h1>");
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn applies_cache_control_rules() {
+        let server = test_server().await;
+        let css = server.get("/static/app.css").await.unwrap();
+        assert_eq!(css.header("cache-control"), Some("public, max-age=31536000"));
+        let html = server.get("/static/docs/").await.unwrap();
+        assert_eq!(html.header("cache-control"), None);
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn answers_conditional_requests() {
+        let server = test_server().await;
+        let res = server.get("/static/app.css").await.unwrap();
+        let etag = res.header("etag").unwrap().to_owned();
+        assert!(res.header("last-modified").is_some());
+        let res = server.call(TestRequest::get().uri("/static/app.css").insert_header(("if-none-match", etag.as_str())).to_request()).await.unwrap();
+        assert_eq!(res.status(), 304);
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn answers_range_requests() {
+        let server = test_server().await;
+        let res = server.call(TestRequest::get().uri("/static/app.css").insert_header(("range", "bytes=0-3")).to_request()).await.unwrap();
+        assert_eq!(res.status(), 206);
+        assert_eq!(res.header("accept-ranges"), Some("bytes"));
+        assert!(res.header("content-range").unwrap().starts_with("bytes 0-3/"));
+        assert_eq!(res.body(), b"body");
+    }
+}
@@ -0,0 +1,116 @@
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+    use actix_web::{web, HttpRequest, HttpResponse, HttpServer};
+    use ring::hmac;
+    use serde_json::{json, Value};
+    use serial_test::serial;
+    use teo::prelude::{App, teon, Webhook};
+    use teo::test::TestServer;
+    use teo::webhook::models::webhook_delivery::WebhookDelivery;
+
+    const SECRET: &str = "whsec";
+
+    type Received = Arc<Mutex<Vec<(String, String)>>>;
+
+    /// Accepts deliveries on `/ok` and fails them on `/fail`.
+    fn listen(port: u16) -> Received {
+        let received: Received = Arc::new(Mutex::new(vec![]));
+        let data = received.clone();
+        let server = HttpServer::new(move || {
+            let data = data.clone();
+            actix_web::App::new().default_service(web::route().to(move |request: HttpRequest, body: String| {
+                let data = data.clone();
+                async move {
+                    let signature = request.headers().get("x-teo-signature").unwrap().to_str().unwrap().to_owned();
+                    data.lock().unwrap().push((signature, body));
+                    if request.path() == "/ok" { HttpResponse::Ok().finish() } else { HttpResponse::InternalServerError().finish() }
+                }
+            }))
+        }).workers(1).bind(("127.0.0.1", port)).unwrap().run();
+        actix_web::rt::spawn(server);
+        received
+    }
+
+    async fn test_server(port: u16) -> TestServer {
+        let schema = Path::new(file!()).parent().unwrap().join("schema.teo");
+        let app = App::new_with_schema_path(schema).unwrap();
+        app.webhook(Webhook::new("orders", format!("http://127.0.0.1:{}/ok", port), SECRET));
+        app.webhook(Webhook::new("failing", format!("http://127.0.0.1:{}/fail", port), SECRET).max_attempts(2));
+        TestServer::new(app).await.unwrap()
+    }
+
+    fn verify(signature: &str, body: &str) {
+        let (timestamp, tag) = signature.strip_prefix("t=").unwrap().split_once(",v1=").unwrap();
+        let key = hmac::Key::new(hmac::HMAC_SHA256, SECRET.as_bytes());
+        let tag = (0..tag.len()).step_by(2).map(|i| u8::from_str_radix(&tag[i..i + 2], 16).unwrap()).collect::<Vec<u8>>();
+        assert!(hmac::verify(&key, format!("{}.{}", timestamp, body).as_bytes(), &tag).is_ok());
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn delivers_signed_payloads() {
+        let received = listen(4025);
+        let server = test_server(4025).await;
+        server.action("Order", "create", json!({ "create": { "note": "first" } })).await.unwrap();
+        assert_eq!(server.deliver_webhooks().await.unwrap(), 1);
+        assert_eq!(server.deliver_webhooks().await.unwrap(), 0);
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let (signature, body) = &received[0];
+        verify(signature, body);
+        let payload: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(payload["webhook"], json!("orders"));
+        assert_eq!(payload["event"], json!("create"));
+        assert_eq!(payload["model"], json!("Order"));
+        assert_eq!(payload["data"]["note"], json!("first"));
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn records_deletes() {
+        let received = listen(4026);
+        let server = test_server(4026).await;
+        let created = server.action("Order", "create", json!({ "create": {} })).await.unwrap().json().unwrap();
+        server.action("Order", "delete", json!({ "where": { "id": created["data"]["id"] } })).await.unwrap();
+        assert_eq!(server.deliver_webhooks().await.unwrap(), 2);
+        let events = received.lock().unwrap().iter()
+            .map(|(_, body)| serde_json::from_str::<Value>(body).unwrap()["event"].clone())
+            .collect::<Vec<Value>>();
+        assert_eq!(events, vec![json!("create"), json!("delete")]);
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn records_updates() {
+        let received = listen(4031);
+        let server = test_server(4031).await;
+        let created = server.action("Order", "create", json!({ "create": { "note": "first" } })).await.unwrap().json().unwrap();
+        server.action("Order", "update", json!({ "where": { "id": created["data"]["id"] }, "update": { "note": "second" } })).await.unwrap();
+        assert_eq!(server.deliver_webhooks().await.unwrap(), 2);
+        let payloads = received.lock().unwrap().iter()
+            .map(|(_, body)| serde_json::from_str::<Value>(body).unwrap())
+            .collect::<Vec<Value>>();
+        assert_eq!(payloads[0]["event"], json!("create"));
+        assert_eq!(payloads[1]["event"], json!("update"));
+        assert_eq!(payloads[1]["data"]["note"], json!("second"));
+    }
+
+    #[serial]
+    #[actix_web::test]
+    async fn retries_failed_deliveries_later() {
+        let received = listen(4027);
+        let server = test_server(4027).await;
+        server.action("Refund", "create", json!({ "create": {} })).await.unwrap();
+        assert_eq!(server.deliver_webhooks().await.unwrap(), 1);
+        // backed off, not due yet
+        assert_eq!(server.deliver_webhooks().await.unwrap(), 0);
+        assert_eq!(received.lock().unwrap().len(), 1);
+        let deliveries = WebhookDelivery::find_many(teon!({}), server.transaction_ctx()).await.unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].status(), "pending");
+        assert_eq!(deliveries[0].attempts(), 1);
+        assert_eq!(deliveries[0].last_error(), Some("responded with 500 Internal Server Error".to_owned()));
+    }
+}